@@ -0,0 +1,392 @@
+use crate::config::ConfigError;
+use crate::parse::ParseError;
+use crate::{
+    can_code_gen, can_code_gen_files, cargo_features, dbc_hash, parse, write_if_changed,
+    CodegenError, Config, DbccOpt, Diagnostic, Runtime, SplitBy,
+};
+
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors that may occur while generating code via the `Builder`.
+#[derive(Debug)]
+pub enum Error {
+    /// No DBC input file was configured
+    MissingDbc,
+    /// No output file was configured
+    MissingOutput,
+    /// `out_dir_file` was used outside of a build script
+    MissingOutDir,
+    /// Reading the DBC or writing the output failed
+    Io(io::Error),
+    /// The DBC file could not be parsed
+    Parse(ParseError),
+    /// Rust code generation failed
+    Codegen(CodegenError),
+    /// The configuration file could not be loaded
+    Config(ConfigError),
+    /// Code generation emitted warnings and `deny_warnings` is set
+    Warnings(Vec<Diagnostic>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingDbc => write!(f, "No DBC input file specified"),
+            Error::MissingOutput => write!(f, "No output file specified"),
+            Error::MissingOutDir => {
+                write!(f, "OUT_DIR is not set, is this run from a build script?")
+            }
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::Codegen(e) => write!(f, "Failed to generate rust code: {}", e),
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Warnings(diagnostics) => {
+                write!(
+                    f,
+                    "Code generation emitted {} warning(s)",
+                    diagnostics.len()
+                )?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n{}", diagnostic)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<CodegenError> for Error {
+    fn from(e: CodegenError) -> Self {
+        Error::Codegen(e)
+    }
+}
+
+#[derive(Debug)]
+enum Output {
+    /// File name relative to `OUT_DIR`
+    OutDir(PathBuf),
+    /// Explicit file path
+    File(PathBuf),
+    /// Module directory with one file per message or node
+    Dir(PathBuf),
+}
+
+/// Generate code from a DBC file in a `build.rs`.
+///
+/// Example:
+/// ```no_run
+/// fn main() -> Result<(), dbcc::builder::Error> {
+///     dbcc::Builder::new()
+///         .dbc("dbcs/j1939.dbc")
+///         .out_dir_file("j1939.rs")
+///         .with_tokio(true)
+///         .generate()?;
+///     Ok(())
+/// }
+/// ```
+///
+/// The generated file can then be included via
+/// `include!(concat!(env!("OUT_DIR"), "/j1939.rs"));`.
+#[derive(Debug, Default)]
+pub struct Builder {
+    dbc: Option<PathBuf>,
+    config: Option<PathBuf>,
+    output: Option<Output>,
+    // Unset options fall back to the configuration file
+    with_tokio: Option<bool>,
+    runtime: Option<Runtime>,
+    with_socketcan: Option<bool>,
+    with_cyclic: Option<bool>,
+    with_embedded_can: Option<bool>,
+    message_features: Option<bool>,
+    deny_warnings: bool,
+    allow_partial: bool,
+    split_by: Option<SplitBy>,
+    features_file: Option<PathBuf>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    /// DBC input file
+    pub fn dbc<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.dbc = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Project configuration file e.g. `dbcc.toml`.
+    /// Options set on the builder take precedence over the configuration file.
+    pub fn config<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Write the generated code to a file in the build script's `OUT_DIR`.
+    pub fn out_dir_file<P: AsRef<Path>>(mut self, file_name: P) -> Self {
+        self.output = Some(Output::OutDir(file_name.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Write the generated code to an explicit path e.g. `src/j1939.rs`.
+    pub fn out_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output = Some(Output::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Write the generated code to a module directory e.g. `src/j1939`,
+    /// with a `mod.rs` and one file per message or transmitting node, see `split_by`.
+    /// The directory is used via `mod j1939;`.
+    pub fn out_module_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output = Some(Output::Dir(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// How messages are split over the files of `out_module_dir`, by message by default.
+    pub fn split_by(mut self, split_by: SplitBy) -> Self {
+        self.split_by = Some(split_by);
+        self
+    }
+
+    /// Should tokio SocketCan BCM streams be generated.
+    /// This requires the `tokio-socketcan-bcm` crate.
+    pub fn with_tokio(mut self, with_tokio: bool) -> Self {
+        self.with_tokio = Some(with_tokio);
+        self
+    }
+
+    /// Generate SocketCan BCM streams for this runtime, see `Runtime`.
+    pub fn runtime(mut self, runtime: Runtime) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    /// Should `can_frame` and `send` functions for raw SocketCAN sockets be generated.
+    /// This requires the `socketcan` crate, combined with a runtime also `send_async`.
+    pub fn with_socketcan(mut self, with_socketcan: bool) -> Self {
+        self.with_socketcan = Some(with_socketcan);
+        self
+    }
+
+    /// Should `start_cyclic`, `update_cyclic` and `stop_cyclic` functions for cyclic
+    /// transmission via a `dbcc_transport::bcm::BcmSocket` be generated.
+    /// This requires the `dbcc-transport` crate, for all runtimes.
+    pub fn with_cyclic(mut self, with_cyclic: bool) -> Self {
+        self.with_cyclic = Some(with_cyclic);
+        self
+    }
+
+    /// Should `try_from_frame` and `into_frame` conversions for `embedded_can::Frame`s be generated.
+    /// `try_from_frame` is an inherent function, `TryFrom` is not implemented as a generic
+    /// `impl<F: Frame> TryFrom<&F>` is rejected by the coherence rules.
+    /// This requires the `embedded-can` crate.
+    pub fn with_embedded_can(mut self, with_embedded_can: bool) -> Self {
+        self.with_embedded_can = Some(with_embedded_can);
+        self
+    }
+
+    /// Gate each message behind a `msg_<message name>` cargo feature.
+    /// The features have to be declared in `Cargo.toml`, see `features_file`.
+    pub fn message_features(mut self, message_features: bool) -> Self {
+        self.message_features = Some(message_features);
+        self
+    }
+
+    /// Write the `[features]` table declaring the message features to a file.
+    pub fn features_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.features_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Fail instead of emitting `cargo:warning`s for code generation diagnostics.
+    pub fn deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.deny_warnings = deny_warnings;
+        self
+    }
+
+    /// Generate code for the sections parsed before the DBC parser stopped
+    /// instead of failing. The skipped sections are reported as a warning.
+    pub fn allow_partial(mut self, allow_partial: bool) -> Self {
+        self.allow_partial = allow_partial;
+        self
+    }
+
+    /// Generate the code and write it to the output file.
+    /// The output file is only rewritten if the generated code changed.
+    /// Diagnostics are reported as `cargo:warning`s.
+    /// Returns the path of the output file or directory.
+    pub fn generate(self) -> Result<PathBuf, Error> {
+        let dbc_path = self.dbc.ok_or(Error::MissingDbc)?;
+        let config = match self.config {
+            Some(config_path) => {
+                println!("cargo:rerun-if-changed={}", config_path.display());
+                Config::from_file(config_path)?
+            }
+            None => Config::default(),
+        };
+        let opt = DbccOpt {
+            with_tokio: self.with_tokio.unwrap_or(config.opt.with_tokio),
+            runtime: self.runtime.or(config.opt.runtime),
+            with_socketcan: self.with_socketcan.unwrap_or(config.opt.with_socketcan),
+            with_cyclic: self.with_cyclic.unwrap_or(config.opt.with_cyclic),
+            with_embedded_can: self
+                .with_embedded_can
+                .unwrap_or(config.opt.with_embedded_can),
+            message_features: self.message_features.unwrap_or(config.opt.message_features),
+            ..config.opt
+        };
+
+        let split_by = self.split_by.unwrap_or(config.output.split_by);
+        let features_file = self.features_file.or(config.output.features_file);
+        let (config_dir, config_file) = (config.output.dir, config.output.file);
+        let output = self
+            .output
+            .or_else(|| config_dir.map(Output::Dir))
+            .or_else(|| config_file.map(Output::File))
+            .ok_or(Error::MissingOutput)?;
+        let (out_path, is_dir) = match output {
+            Output::OutDir(file_name) => {
+                let out_dir = env::var_os("OUT_DIR").ok_or(Error::MissingOutDir)?;
+                (PathBuf::from(out_dir).join(file_name), false)
+            }
+            Output::File(path) => (path, false),
+            Output::Dir(path) => (path, true),
+        };
+
+        println!("cargo:rerun-if-changed={}", dbc_path.display());
+
+        let buffer = fs::read(&dbc_path)?;
+        let mut diagnostics = Vec::new();
+        let dbc = if self.allow_partial {
+            let (dbc, incomplete) = parse::from_slice_partial(&buffer)
+                .map_err(|e| Error::Parse(e.with_file(&dbc_path)))?;
+            diagnostics.extend(incomplete);
+            dbc
+        } else {
+            parse::from_slice(&buffer).map_err(|e| Error::Parse(e.with_file(&dbc_path)))?
+        };
+
+        let file_name = dbc_path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or("N/A");
+        let file_hash = dbc_hash(&buffer);
+        // Output path -> generated code
+        let mut files: Vec<(PathBuf, String)> = if is_dir {
+            let generated = can_code_gen_files(&opt, &dbc, file_name, &file_hash, split_by)?;
+            diagnostics.extend(generated.diagnostics);
+            generated
+                .files
+                .iter()
+                .map(|file| (out_path.join(&file.name), file.scope.to_string()))
+                .collect()
+        } else {
+            let generated = can_code_gen(&opt, &dbc, file_name, &file_hash)?;
+            diagnostics.extend(generated.diagnostics);
+            vec![(out_path.clone(), generated.scope.to_string())]
+        };
+        if let Some(features_file) = features_file {
+            files.push((features_file, cargo_features(&opt, &dbc)));
+        }
+        if self.deny_warnings && !diagnostics.is_empty() {
+            return Err(Error::Warnings(diagnostics));
+        }
+        for diagnostic in &diagnostics {
+            println!(
+                "cargo:warning={}: {} ({})",
+                dbc_path.display(),
+                diagnostic.message,
+                diagnostic.location
+            );
+        }
+
+        if is_dir {
+            fs::create_dir_all(&out_path)?;
+        }
+        for (path, code) in files {
+            write_if_changed(&path, &code)?;
+        }
+
+        Ok(out_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty scratch directory below the system temp directory
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("dbcc-builder-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn generate(dir: &Path, builder: Builder) -> String {
+        let config = dir.join("dbcc.toml");
+        fs::write(
+            &config,
+            "with_socketcan = true\nwith_embedded_can = true\nmessage_features = true\n",
+        )
+        .unwrap();
+        let out_file = dir.join("j1939.rs");
+        builder
+            .dbc(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../examples/j1939.dbc"
+            ))
+            .config(config)
+            .out_file(&out_file)
+            .generate()
+            .unwrap();
+        fs::read_to_string(out_file).unwrap()
+    }
+
+    #[test]
+    fn config_flags_apply_when_unset() {
+        let dir = scratch_dir("config");
+        let code = generate(&dir, Builder::new());
+        assert!(code.contains("pub fn can_frame("));
+        assert!(code.contains("pub fn into_frame<"));
+        assert!(code.contains("pub fn try_from_frame<"));
+        assert!(!code.contains("TryFrom"));
+        assert!(code.contains("#[cfg(feature = \"msg_"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn builder_flags_override_config() {
+        let dir = scratch_dir("override");
+        let builder = Builder::new()
+            .with_socketcan(false)
+            .with_embedded_can(false)
+            .message_features(false);
+        let code = generate(&dir, builder);
+        assert!(!code.contains("pub fn can_frame("));
+        assert!(!code.contains("pub fn into_frame<"));
+        assert!(!code.contains("pub fn try_from_frame<"));
+        assert!(!code.contains("#[cfg(feature"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
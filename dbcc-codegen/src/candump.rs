@@ -0,0 +1,289 @@
+//! Parse frames printed by `candump` of can-utils.
+//!
+//! Both the log format (`candump -L`) e.g. `(1436509052.249713) vcan0 18FEF100#0011223344556677`
+//! and the default format e.g. `  vcan0  18FEF100   [8]  00 11 22 33 44 55 66 77`,
+//! optionally prefixed by a timestamp, are supported.
+use crate::{EFF_MASK, SFF_MASK};
+
+use std::error;
+use std::fmt;
+
+/// Error frame flag of the CAN id, see `CAN_ERR_FLAG` of `linux/can.h`
+const ERR_FLAG: u32 = 0x2000_0000;
+
+/// Frame read from a candump line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandumpFrame {
+    /// Timestamp in seconds if candump was run with `-L` or `-t`
+    pub timestamp: Option<f64>,
+    pub interface: String,
+    /// CAN id without the extended frame flag
+    pub id: u32,
+    /// Extended frame format (29 bit id)
+    pub extended: bool,
+    /// Remote transmission request, `data` is empty
+    pub remote: bool,
+    /// Error frame, `id` holds the error class (`CAN_ERR_*`) and `data` the details
+    pub error: bool,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandumpError {
+    pub message: String,
+}
+
+impl CandumpError {
+    fn new<S: Into<String>>(message: S) -> Self {
+        CandumpError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for CandumpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for CandumpError {}
+
+/// Parse a candump line, empty lines are `Ok(None)`.
+///
+/// Example:
+/// ```
+/// use dbcc::candump;
+///
+/// let frame = candump::parse_line("(1436509052.249713) vcan0 0CFDCCFE#0050000000000000")
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(frame.id, 0x0CFD_CCFE);
+/// assert!(frame.extended);
+/// assert_eq!(frame.data[1], 0x50);
+///
+/// let frame = candump::parse_line("  vcan0  123   [2]  11 22").unwrap().unwrap();
+/// assert_eq!((frame.id, frame.extended, frame.data), (0x123, false, vec![0x11, 0x22]));
+/// ```
+pub fn parse_line(line: &str) -> Result<Option<CandumpFrame>, CandumpError> {
+    let mut tokens = line.split_whitespace().peekable();
+    let timestamp = match tokens.peek() {
+        None => return Ok(None),
+        Some(token) if token.starts_with('(') => {
+            let token = tokens.next().unwrap_or_default();
+            let timestamp = token.trim_start_matches('(').trim_end_matches(')');
+            Some(
+                timestamp
+                    .parse()
+                    .map_err(|_| CandumpError::new(format!("invalid timestamp `{}`", token)))?,
+            )
+        }
+        Some(_) => None,
+    };
+    let interface = tokens
+        .next()
+        .ok_or_else(|| CandumpError::new("missing interface"))?
+        .to_string();
+    let frame = tokens
+        .next()
+        .ok_or_else(|| CandumpError::new("missing CAN id"))?;
+
+    let (id, kind, remote, data) = match frame.find('#') {
+        // Log format `<id>#<data>`, `<id>#R` or CAN FD `<id>##<flags><data>`
+        Some(separator) => {
+            let (id, kind) = parse_id(&frame[..separator])?;
+            let payload = &frame[separator + 1..];
+            let (remote, data) = if payload.starts_with('R') {
+                (true, Vec::new())
+            } else if let Some(fd_payload) = payload.strip_prefix('#') {
+                (false, parse_hex_data(fd_payload.get(1..).unwrap_or(""))?)
+            } else {
+                (false, parse_hex_data(payload)?)
+            };
+            (id, kind, remote, data)
+        }
+        // Default format `<id> [<len>] <byte> <byte> ...`
+        None => {
+            let (id, kind) = parse_id(frame)?;
+            let len = tokens
+                .next()
+                .map(|len| len.trim_start_matches('[').trim_end_matches(']'))
+                .and_then(|len| len.parse::<usize>().ok())
+                .ok_or_else(|| CandumpError::new("missing frame length `[<len>]`"))?;
+            let mut rest: Vec<&str> = tokens.collect();
+            if rest.first() == Some(&"remote") {
+                (id, kind, true, Vec::new())
+            } else {
+                // `candump -e` marks error frames after the data
+                if kind == IdKind::Error && rest.last() == Some(&"ERRORFRAME") {
+                    rest.pop();
+                }
+                let data = parse_hex_data(&rest.concat())?;
+                if data.len() != len {
+                    return Err(CandumpError::new(format!(
+                        "frame length {} does not match {} data bytes",
+                        len,
+                        data.len()
+                    )));
+                }
+                (id, kind, false, data)
+            }
+        }
+    };
+
+    Ok(Some(CandumpFrame {
+        timestamp,
+        interface,
+        id,
+        extended: kind == IdKind::Extended,
+        remote,
+        error: kind == IdKind::Error,
+        data,
+    }))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum IdKind {
+    Standard,
+    Extended,
+    Error,
+}
+
+/// candump prints standard ids with 3 and extended ids with 8 hex digits,
+/// error frames with 8 digits including the error flag.
+fn parse_id(id: &str) -> Result<(u32, IdKind), CandumpError> {
+    let invalid = || CandumpError::new(format!("invalid CAN id `{}`", id));
+    // `from_str_radix` would accept a sign
+    if !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let value = u32::from_str_radix(id, 16).map_err(|_| invalid())?;
+    match id.len() {
+        3 if value <= SFF_MASK => Ok((value, IdKind::Standard)),
+        8 if value <= EFF_MASK => Ok((value, IdKind::Extended)),
+        8 if value & !EFF_MASK == ERR_FLAG => Ok((value & EFF_MASK, IdKind::Error)),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_hex_data(data: &str) -> Result<Vec<u8>, CandumpError> {
+    let data = data.replace('.', "");
+    if data.len() & 1 != 0 {
+        return Err(CandumpError::new(format!(
+            "odd number of hex digits `{}`",
+            data
+        )));
+    }
+    (0..data.len())
+        .step_by(2)
+        .map(|i| {
+            data.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| CandumpError::new(format!("invalid data `{}`", data)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(line: &str) -> CandumpFrame {
+        parse_line(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn log_format() {
+        let f = frame("(1436509052.249713) vcan0 18FEF100#0011223344556677");
+        assert_eq!(f.timestamp, Some(1_436_509_052.249_713));
+        assert_eq!(f.interface, "vcan0");
+        assert_eq!(
+            (f.id, f.extended, f.remote, f.error),
+            (0x18FE_F100, true, false, false)
+        );
+        assert_eq!(f.data, vec![0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]);
+
+        let f = frame("(0.000000) can0 123#");
+        assert_eq!((f.id, f.extended, f.data), (0x123, false, vec![]));
+
+        // CAN FD with flags nibble
+        let f = frame("(0.000000) can0 123##1001122");
+        assert_eq!(f.data, vec![0x00, 0x11, 0x22]);
+    }
+
+    #[test]
+    fn log_format_remote() {
+        let f = frame("(1436509052.249713) vcan0 123#R");
+        assert_eq!((f.id, f.remote, f.data), (0x123, true, vec![]));
+        let f = frame("(1436509052.249713) vcan0 18FEF100#R");
+        assert_eq!((f.id, f.extended, f.remote), (0x18FE_F100, true, true));
+    }
+
+    #[test]
+    fn log_format_error() {
+        let f = frame("(1436509052.249713) vcan0 20000004#0004000000000000");
+        assert_eq!((f.id, f.extended, f.error), (0x4, false, true));
+        assert_eq!(f.data[1], 0x04);
+    }
+
+    #[test]
+    fn default_format() {
+        let f = frame("  vcan0  18FEF100   [8]  00 11 22 33 44 55 66 77");
+        assert_eq!(f.timestamp, None);
+        assert_eq!(
+            (f.id, f.extended, f.remote, f.error),
+            (0x18FE_F100, true, false, false)
+        );
+        assert_eq!(f.data.len(), 8);
+
+        let f = frame(" (1436509052.249713)  vcan0  123   [2]  11 22");
+        assert_eq!(f.timestamp, Some(1_436_509_052.249_713));
+        assert_eq!((f.id, f.extended, f.data), (0x123, false, vec![0x11, 0x22]));
+
+        assert!(parse_line("  vcan0  123   [3]  11 22").is_err());
+    }
+
+    #[test]
+    fn default_format_remote() {
+        let f = frame("  vcan0  123   [0]  remote request");
+        assert_eq!((f.id, f.remote, f.data), (0x123, true, vec![]));
+        let f = frame("  vcan0  18FEF100   [8]  remote request");
+        assert_eq!((f.extended, f.remote), (true, true));
+    }
+
+    #[test]
+    fn default_format_error() {
+        let f = frame("  vcan0  20000004   [8]  00 04 00 00 00 00 00 00   ERRORFRAME");
+        assert_eq!((f.id, f.extended, f.error), (0x4, false, true));
+        assert_eq!(f.data[1], 0x04);
+    }
+
+    #[test]
+    fn id_width() {
+        assert_eq!(parse_id("7FF"), Ok((0x7FF, IdKind::Standard)));
+        assert_eq!(parse_id("00000123"), Ok((0x123, IdKind::Extended)));
+        assert_eq!(parse_id("1FFFFFFF"), Ok((0x1FFF_FFFF, IdKind::Extended)));
+        for id in &[
+            "",
+            "12",
+            "0123",
+            "1234567",
+            "123456789",
+            "800",
+            "40000000",
+            "FFFFFFFF",
+            "+12",
+            "G12",
+        ] {
+            assert!(parse_id(id).is_err(), "{}", id);
+        }
+        assert!(parse_line("(0.000000) vcan0 1234#00").is_err());
+        assert!(parse_line("  vcan0  12   [1]  00").is_err());
+    }
+
+    #[test]
+    fn empty_line() {
+        assert_eq!(parse_line(""), Ok(None));
+        assert_eq!(parse_line("   "), Ok(None));
+    }
+}
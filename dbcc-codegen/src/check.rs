@@ -0,0 +1,630 @@
+//! Semantic checks of a parsed DBC that the parser does not catch.
+use crate::{Diagnostic, DiagnosticKind, Location, Severity};
+
+use can_dbc::{
+    ByteOrder, Message, MultiplexIndicator, Signal, SignalExtendedValueType, Transmitter,
+    ValueDescription, ValueType, DBC,
+};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// Node name used by DBC editors for messages and signals without sender or receiver.
+const VECTOR_XXX: &str = "Vector__XXX";
+
+/// Check a DBC for semantic errors, e.g. overlapping signals.
+///
+/// Example:
+/// ```
+/// use dbcc::Severity;
+///
+/// # std::env::set_current_dir("..").unwrap();
+/// let buffer = std::fs::read("./examples/j1939.dbc").unwrap();
+/// let dbc = can_dbc::DBC::from_slice(&buffer).unwrap();
+/// for diagnostic in dbcc::check::check(&dbc) {
+///     if diagnostic.severity == Severity::Error {
+///         eprintln!("{}", diagnostic);
+///     }
+/// }
+/// ```
+pub fn check(dbc: &DBC) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    duplicate_messages(dbc, &mut diagnostics);
+    for message in dbc.messages() {
+        signal_layout(message, &mut diagnostics);
+        multiplexor_switch(message, &mut diagnostics);
+        for signal in message.signals() {
+            signal_range(dbc, message, signal, &mut diagnostics);
+        }
+    }
+    value_descriptions(dbc, &mut diagnostics);
+    nodes(dbc, &mut diagnostics);
+    diagnostics
+}
+
+fn diagnostic(
+    severity: Severity,
+    kind: DiagnosticKind,
+    message: &Message,
+    signal: Option<&str>,
+    text: String,
+) -> Diagnostic {
+    Diagnostic {
+        severity,
+        kind,
+        location: Location {
+            message_id: Some(message.message_id().0),
+            signal_name: signal.map(|s| s.to_string()),
+            ..Default::default()
+        },
+        message: text,
+    }
+}
+
+fn duplicate_messages(dbc: &DBC, diagnostics: &mut Vec<Diagnostic>) {
+    let mut names = BTreeMap::new();
+    let mut ids = BTreeMap::new();
+    for message in dbc.messages() {
+        if let Some(other_id) = names.insert(message.message_name(), message.message_id().0) {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                DiagnosticKind::DuplicateMessageName,
+                message,
+                None,
+                format!(
+                    "message name `{}` is also used by message {}",
+                    message.message_name(),
+                    other_id
+                ),
+            ));
+        }
+        if let Some(other_name) = ids.insert(message.message_id().0, message.message_name()) {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                DiagnosticKind::DuplicateMessageId,
+                message,
+                None,
+                format!(
+                    "message id {} is used by `{}` and `{}`",
+                    message.message_id().0,
+                    other_name,
+                    message.message_name()
+                ),
+            ));
+        }
+    }
+}
+
+/// Set of payload bit positions, one bit per position.
+#[derive(Debug, Default, PartialEq)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn insert(&mut self, bit: u64) {
+        let word = (bit / 64) as usize;
+        if word >= self.0.len() {
+            self.0.resize(word + 1, 0);
+        }
+        self.0[word] |= 1 << (bit % 64);
+    }
+
+    fn is_disjoint(&self, other: &BitSet) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a & b == 0)
+    }
+}
+
+/// Bit positions of a signal within the payload counted from the first payload bit,
+/// e.g. bit 0 of byte 1 is 8, and whether the signal extends past the payload.
+///
+/// Stops at the first bit past the payload so a malformed start bit or size
+/// can not grow the set beyond the payload.
+fn signal_bits(signal: &Signal, payload_bits: u64) -> (BitSet, bool) {
+    let mut bits = BitSet::default();
+    let mut bit = *signal.start_bit();
+    for _ in 0..*signal.signal_size() {
+        if bit >= payload_bits {
+            return (bits, true);
+        }
+        bits.insert(bit);
+        bit = match signal.byte_order() {
+            ByteOrder::LittleEndian => bit + 1,
+            // Motorola signals continue at the most significant bit of the next byte
+            ByteOrder::BigEndian if bit & 0x7 == 0 => bit.saturating_add(15),
+            ByteOrder::BigEndian => bit - 1,
+        };
+    }
+    (bits, false)
+}
+
+/// Signals multiplexed by different switch values are never present at the same time.
+fn may_overlap(a: &Signal, b: &Signal) -> bool {
+    match (a.multiplexer_indicator(), b.multiplexer_indicator()) {
+        (
+            MultiplexIndicator::MultiplexedSignal(a_value),
+            MultiplexIndicator::MultiplexedSignal(b_value),
+        ) => a_value == b_value,
+        _ => true,
+    }
+}
+
+fn signal_layout(message: &Message, diagnostics: &mut Vec<Diagnostic>) {
+    let payload_bits = message.message_size().saturating_mul(8);
+    let (bits, past_dlc): (Vec<BitSet>, Vec<bool>) = message
+        .signals()
+        .iter()
+        .map(|signal| signal_bits(signal, payload_bits))
+        .unzip();
+
+    for (i, signal) in message.signals().iter().enumerate() {
+        if past_dlc[i] {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                DiagnosticKind::SignalExceedsDlc,
+                message,
+                Some(signal.name()),
+                format!(
+                    "signal extends past the message length of {} byte(s)",
+                    message.message_size()
+                ),
+            ));
+        }
+
+        for (j, other) in message.signals().iter().enumerate().skip(i + 1) {
+            if may_overlap(signal, other) && !bits[i].is_disjoint(&bits[j]) {
+                diagnostics.push(diagnostic(
+                    Severity::Error,
+                    DiagnosticKind::OverlappingSignals,
+                    message,
+                    Some(signal.name()),
+                    format!("signal overlaps with signal `{}`", other.name()),
+                ));
+            }
+        }
+    }
+}
+
+fn multiplexor_switch(message: &Message, diagnostics: &mut Vec<Diagnostic>) {
+    let has_switch = message
+        .signals()
+        .iter()
+        .any(|signal| *signal.multiplexer_indicator() == MultiplexIndicator::Multiplexor);
+    if has_switch {
+        return;
+    }
+    for signal in message.signals() {
+        if let MultiplexIndicator::MultiplexedSignal(_) = signal.multiplexer_indicator() {
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                DiagnosticKind::MissingMultiplexorSwitch,
+                message,
+                Some(signal.name()),
+                "multiplexed signal but the message has no multiplexor switch".to_string(),
+            ));
+        }
+    }
+}
+
+/// Physical value range the raw signal value can be decoded to.
+fn physical_range(signal: &Signal) -> (f64, f64) {
+    // Malformed sizes are not representable anyway, `2^i32::MAX` is infinite
+    let size = i32::try_from(*signal.signal_size()).unwrap_or(i32::MAX);
+    let (raw_min, raw_max) = match signal.value_type() {
+        ValueType::Signed => (-(2f64.powi(size - 1)), 2f64.powi(size - 1) - 1.0),
+        ValueType::Unsigned => (0.0, 2f64.powi(size) - 1.0),
+    };
+    let a = raw_min * signal.factor() + signal.offset();
+    let b = raw_max * signal.factor() + signal.offset();
+    (a.min(b), a.max(b))
+}
+
+fn signal_range(dbc: &DBC, message: &Message, signal: &Signal, diagnostics: &mut Vec<Diagnostic>) {
+    // `[0|0]` means no range is specified, float signals are not bounded by their size.
+    let unspecified = *signal.min() == 0.0 && *signal.max() == 0.0;
+    let float = matches!(
+        dbc.extended_value_type_for_signal(*message.message_id(), signal.name()),
+        Some(SignalExtendedValueType::IEEEfloat32Bit)
+            | Some(SignalExtendedValueType::IEEEdouble64bit)
+    );
+    if unspecified || float || *signal.signal_size() == 0 {
+        return;
+    }
+
+    let (min, max) = physical_range(signal);
+    // Allow for factors that can not be represented exactly e.g. 0.1
+    let tolerance = signal.factor().abs() / 2.0;
+    if *signal.min() < min - tolerance || *signal.max() > max + tolerance {
+        diagnostics.push(diagnostic(
+            Severity::Warning,
+            DiagnosticKind::UnreachableRange,
+            message,
+            Some(signal.name()),
+            format!(
+                "range [{}|{}] can not be reached, the signal can only represent [{}|{}]",
+                signal.min(),
+                signal.max(),
+                min,
+                max
+            ),
+        ));
+    }
+}
+
+fn value_descriptions(dbc: &DBC, diagnostics: &mut Vec<Diagnostic>) {
+    for value_description in dbc.value_descriptions() {
+        if let ValueDescription::Signal {
+            message_id,
+            signal_name,
+            ..
+        } = value_description
+        {
+            if dbc.signal_by_name(*message_id, signal_name).is_none() {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Error,
+                    kind: DiagnosticKind::UndefinedSignal,
+                    location: Location {
+                        message_id: Some(message_id.0),
+                        signal_name: Some(signal_name.clone()),
+                        ..Default::default()
+                    },
+                    message: "value description for a signal that is not defined".to_string(),
+                });
+            }
+        }
+    }
+}
+
+fn nodes(dbc: &DBC, diagnostics: &mut Vec<Diagnostic>) {
+    let nodes: BTreeSet<&str> = dbc
+        .nodes()
+        .iter()
+        .flat_map(|node| node.0.iter())
+        .map(|node| node.as_str())
+        .collect();
+    let undefined = |node: &str| node != VECTOR_XXX && !nodes.contains(node);
+
+    for message in dbc.messages() {
+        if let Transmitter::NodeName(transmitter) = message.transmitter() {
+            if undefined(transmitter) {
+                diagnostics.push(diagnostic(
+                    Severity::Warning,
+                    DiagnosticKind::UndefinedNode,
+                    message,
+                    None,
+                    format!("transmitter `{}` is not defined", transmitter),
+                ));
+            }
+        }
+        for signal in message.signals() {
+            for receiver in signal.receivers() {
+                if undefined(receiver) {
+                    diagnostics.push(diagnostic(
+                        Severity::Warning,
+                        DiagnosticKind::UndefinedNode,
+                        message,
+                        Some(signal.name()),
+                        format!("receiver `{}` is not defined", receiver),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Additional transmitters defined via `BO_TX_BU_`
+    for message_transmitter in dbc.message_transmitters() {
+        for transmitter in message_transmitter.transmitter() {
+            if let Transmitter::NodeName(transmitter) = transmitter {
+                if undefined(transmitter) {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        kind: DiagnosticKind::UndefinedNode,
+                        location: Location {
+                            message_id: Some(message_transmitter.message_id().0),
+                            signal_name: None,
+                            ..Default::default()
+                        },
+                        message: format!("transmitter `{}` is not defined", transmitter),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a DBC with the message `BO_ 256 Test: <size> ECU` and the given signals
+    fn message(size: u64, signals: &str) -> DBC {
+        let dbc = format!(
+            "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\nBO_ 256 Test: {} ECU\n{}\n",
+            size, signals
+        );
+        DBC::from_slice(dbc.as_bytes()).unwrap()
+    }
+
+    /// Signal layout diagnostics as `(kind, signal name)`
+    fn layout(dbc: &DBC) -> Vec<(DiagnosticKind, String)> {
+        let mut diagnostics = Vec::new();
+        signal_layout(&dbc.messages()[0], &mut diagnostics);
+        diagnostics
+            .into_iter()
+            .map(|d| (d.kind, d.location.signal_name.unwrap()))
+            .collect()
+    }
+
+    fn bit_set(bits: &[u64]) -> BitSet {
+        let mut set = BitSet::default();
+        for bit in bits {
+            set.insert(*bit);
+        }
+        set
+    }
+
+    #[test]
+    fn big_endian_bits() {
+        let dbc = message(
+            8,
+            " SG_ Word : 7|16@0+ (1,0) [0|0] \"\" ECU\n SG_ Nibbles : 4|8@0+ (1,0) [0|0] \"\" ECU",
+        );
+        let signals = dbc.messages()[0].signals();
+        let word: Vec<u64> = (0..16).collect();
+        assert_eq!(signal_bits(&signals[0], 64), (bit_set(&word), false));
+        // Bits 4 to 0 of byte 0 continue at the most significant bits of byte 1
+        assert_eq!(
+            signal_bits(&signals[1], 64),
+            (bit_set(&[4, 3, 2, 1, 0, 15, 14, 13]), false)
+        );
+        assert_eq!(
+            signal_bits(&signals[1], 8),
+            (bit_set(&[4, 3, 2, 1, 0]), true)
+        );
+    }
+
+    #[test]
+    fn big_endian_overlap() {
+        let dbc = message(
+            8,
+            " SG_ Nibbles : 4|8@0+ (1,0) [0|0] \"\" ECU\n SG_ Low : 8|4@1+ (1,0) [0|0] \"\" ECU\n SG_ High : 12|4@1+ (1,0) [0|0] \"\" ECU",
+        );
+        assert_eq!(
+            layout(&dbc),
+            vec![(DiagnosticKind::OverlappingSignals, "Nibbles".to_string())]
+        );
+    }
+
+    #[test]
+    fn multiplexed_overlap() {
+        let dbc = message(
+            8,
+            " SG_ Mux M : 0|8@1+ (1,0) [0|0] \"\" ECU\n SG_ A m0 : 8|16@1+ (1,0) [0|0] \"\" ECU\n SG_ B m1 : 8|16@1+ (1,0) [0|0] \"\" ECU\n SG_ C m1 : 16|8@1+ (1,0) [0|0] \"\" ECU",
+        );
+        // Only signals of the same multiplexor value can overlap
+        assert_eq!(
+            layout(&dbc),
+            vec![(DiagnosticKind::OverlappingSignals, "B".to_string())]
+        );
+    }
+
+    #[test]
+    fn signal_past_dlc() {
+        let dbc = message(
+            2,
+            " SG_ Fits : 7|16@0+ (1,0) [0|0] \"\" ECU\n SG_ Le : 8|16@1+ (1,0) [0|0] \"\" ECU\n SG_ Be : 15|16@0+ (1,0) [0|0] \"\" ECU",
+        );
+        let diagnostics: Vec<_> = layout(&dbc)
+            .into_iter()
+            .filter(|(kind, _)| *kind == DiagnosticKind::SignalExceedsDlc)
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (DiagnosticKind::SignalExceedsDlc, "Le".to_string()),
+                (DiagnosticKind::SignalExceedsDlc, "Be".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_signal_past_dlc() {
+        // Must not fill a bit set of the size of the start bit or the signal size
+        let dbc = message(
+            8,
+            " SG_ Huge : 4000000000|4000000000@1+ (1,0) [0|0] \"\" ECU\n SG_ Long : 0|4000000000@1+ (1,0) [0|0] \"\" ECU\n SG_ Motorola : 4000000000|4000000000@0+ (1,0) [0|0] \"\" ECU",
+        );
+        assert_eq!(
+            layout(&dbc),
+            vec![
+                (DiagnosticKind::SignalExceedsDlc, "Huge".to_string()),
+                (DiagnosticKind::SignalExceedsDlc, "Long".to_string()),
+                (DiagnosticKind::SignalExceedsDlc, "Motorola".to_string()),
+            ]
+        );
+        let diagnostics = check(&dbc);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.kind == DiagnosticKind::SignalExceedsDlc));
+    }
+
+    /// All diagnostics of `check` as `(kind, message id, signal name)`
+    fn checked(dbc: &str) -> Vec<(DiagnosticKind, Option<u32>, Option<String>)> {
+        let dbc = DBC::from_slice(dbc.as_bytes()).unwrap();
+        check(&dbc)
+            .into_iter()
+            .map(|d| (d.kind, d.location.message_id, d.location.signal_name))
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_message_ids_and_names() {
+        let diagnostics = checked(
+            r#"VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU
+
+BO_ 256 First: 8 ECU
+ SG_ Value : 0|8@1+ (1,0) [0|0] "" ECU
+
+BO_ 256 Second: 8 ECU
+ SG_ Value : 0|8@1+ (1,0) [0|0] "" ECU
+
+BO_ 257 First: 8 ECU
+ SG_ Value : 0|8@1+ (1,0) [0|0] "" ECU
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                (DiagnosticKind::DuplicateMessageId, Some(256), None),
+                (DiagnosticKind::DuplicateMessageName, Some(257), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn value_description_of_undefined_signal() {
+        let diagnostics = checked(
+            r#"VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU
+
+BO_ 256 Test: 8 ECU
+ SG_ Defined : 0|8@1+ (1,0) [0|0] "" ECU
+
+VAL_ 256 Defined 0 "Off" 1 "On" ;
+VAL_ 256 Missing 0 "Off" 1 "On" ;
+VAL_ 257 Defined 0 "Off" 1 "On" ;
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    DiagnosticKind::UndefinedSignal,
+                    Some(256),
+                    Some("Missing".to_string())
+                ),
+                (
+                    DiagnosticKind::UndefinedSignal,
+                    Some(257),
+                    Some("Defined".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined_nodes() {
+        let diagnostics = checked(
+            r#"VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU GATEWAY
+
+BO_ 256 Test: 8 ECU
+ SG_ Known : 0|8@1+ (1,0) [0|0] "" GATEWAY,Vector__XXX
+ SG_ Unknown : 8|8@1+ (1,0) [0|0] "" DASHBOARD
+
+BO_ 257 Unsent: 8 BODY
+ SG_ Value : 0|8@1+ (1,0) [0|0] "" ECU
+
+BO_ 258 Unassigned: 8 Vector__XXX
+ SG_ Value : 0|8@1+ (1,0) [0|0] "" ECU
+
+BO_TX_BU_ 256 : GATEWAY,TESTER;
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    DiagnosticKind::UndefinedNode,
+                    Some(256),
+                    Some("Unknown".to_string())
+                ),
+                (DiagnosticKind::UndefinedNode, Some(257), None),
+                (DiagnosticKind::UndefinedNode, Some(256), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreachable_ranges() {
+        let diagnostics = checked(
+            r#"VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU
+
+BO_ 256 Test: 8 ECU
+ SG_ Fits : 0|8@1+ (1,0) [0|255] "" ECU
+ SG_ Above : 8|8@1+ (1,0) [0|256] "" ECU
+ SG_ Below : 16|8@1- (1,0) [-129|127] "" ECU
+ SG_ Scaled : 24|8@1+ (0.1,-10) [-10|15.5] "" ECU
+ SG_ ScaledAbove : 32|8@1+ (0.1,-10) [-10|16] "" ECU
+ SG_ Unspecified : 40|8@1+ (1,0) [0|0] "" ECU
+ SG_ Float : 0|32@1- (1,0) [-1000000|1000000] "" ECU
+
+SIG_VALTYPE_ 256 Float : 1;
+"#,
+        );
+        let ranges: Vec<_> = diagnostics
+            .into_iter()
+            .filter(|(kind, _, _)| *kind == DiagnosticKind::UnreachableRange)
+            .map(|(_, _, signal)| signal.unwrap())
+            .collect();
+        assert_eq!(ranges, vec!["Above", "Below", "ScaledAbove"]);
+    }
+
+    #[test]
+    fn multiplexor_switch_required() {
+        let diagnostics = checked(
+            r#"VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU
+
+BO_ 256 Switched: 8 ECU
+ SG_ Mux M : 0|8@1+ (1,0) [0|0] "" ECU
+ SG_ A m0 : 8|8@1+ (1,0) [0|0] "" ECU
+
+BO_ 257 Unswitched: 8 ECU
+ SG_ A m0 : 8|8@1+ (1,0) [0|0] "" ECU
+ SG_ B m1 : 8|8@1+ (1,0) [0|0] "" ECU
+ SG_ Plain : 16|8@1+ (1,0) [0|0] "" ECU
+"#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    DiagnosticKind::MissingMultiplexorSwitch,
+                    Some(257),
+                    Some("A".to_string())
+                ),
+                (
+                    DiagnosticKind::MissingMultiplexorSwitch,
+                    Some(257),
+                    Some("B".to_string())
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use crate::DbccOpt;
+
+use serde::Deserialize;
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors that may occur while loading a `dbcc.toml`.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Toml(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            ConfigError::Toml(path, e) => write!(f, "Invalid config {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// Project configuration, usually read from a `dbcc.toml`.
+///
+/// Example:
+/// ```toml
+/// runtime = "tokio"
+/// with_socketcan = true
+/// with_cyclic = true
+/// with_embedded_can = true
+///
+/// [messages]
+/// include = ["EEC*", "OEL"]
+/// exclude = ["EEC8"]
+///
+/// [rename.messages]
+/// EFL_P5 = "EngineFluidLevelPressure5"
+///
+/// [rename.signals]
+/// "OEL.HazardLightSwitch" = "HazardSwitch"
+///
+/// [signal_types]
+/// "EEC1.EngineSpeed" = "f32"
+///
+/// [derive]
+/// messages = ["Debug", "Clone", "PartialEq"]
+///
+/// [attributes]
+/// enums = ["#[non_exhaustive]"]
+///
+/// [types.Oel]
+/// derives = ["PartialEq"]
+/// attributes = ["#[non_exhaustive]"]
+///
+/// [output]
+/// file = "src/j1939.rs"
+/// # or a module directory with one file per message (`split_by = "message"`)
+/// # or transmitting node (`split_by = "node"`)
+/// # dir = "src/j1939"
+/// # `[features]` table to copy into `Cargo.toml` if `message_features = true`
+/// # features_file = "j1939-features.toml"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Code generation options
+    #[serde(flatten)]
+    pub opt: DbccOpt,
+    pub output: OutputConfig,
+}
+
+impl Config {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let content =
+            fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        toml::from_str(&content).map_err(|e| ConfigError::Toml(path.to_path_buf(), e))
+    }
+}
+
+/// Where generated code is written to.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Output file, the CLI prints to stdout if this is not set.
+    pub file: Option<PathBuf>,
+    /// Output module directory, generates a `mod.rs` and one file per message or node.
+    pub dir: Option<PathBuf>,
+    /// How messages are split over files in the output directory.
+    pub split_by: SplitBy,
+    /// File the `[features]` table for `message_features` is written to.
+    pub features_file: Option<PathBuf>,
+}
+
+/// How messages are distributed over the files of an output directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBy {
+    /// One file per message
+    #[default]
+    Message,
+    /// One file per transmitting node
+    Node,
+}
+
+impl std::str::FromStr for SplitBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "message" => Ok(SplitBy::Message),
+            "node" => Ok(SplitBy::Node),
+            _ => Err(format!("expected `message` or `node`, got `{}`", s)),
+        }
+    }
+}
+
+/// Async runtime generated streams and async send functions are written for.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Runtime {
+    /// tokio 0.1 via `tokio-socketcan-bcm` 0.2 and `tokio-socketcan`,
+    /// adapted to futures 0.3 by `futures-util`'s compat layer
+    Compat,
+    /// tokio 1.x via `dbcc-transport`'s `tokio` feature
+    Tokio,
+    /// async-std via `dbcc-transport`'s `async-std` feature
+    AsyncStd,
+}
+
+impl std::str::FromStr for Runtime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compat" => Ok(Runtime::Compat),
+            "tokio" => Ok(Runtime::Tokio),
+            "async-std" => Ok(Runtime::AsyncStd),
+            _ => Err(format!(
+                "expected `compat`, `tokio` or `async-std`, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// Select the messages code is generated for by message name.
+/// Patterns may contain `*` (any sequence) and `?` (any single character) wildcards.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MessageFilter {
+    /// Only generate messages matching one of these patterns, all messages if empty.
+    pub include: Vec<String>,
+    /// Skip messages matching one of these patterns.
+    pub exclude: Vec<String>,
+}
+
+impl MessageFilter {
+    pub fn matches(&self, message_name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| wildcard_match(p, message_name)))
+            && !self.exclude.iter().any(|p| wildcard_match(p, message_name))
+    }
+}
+
+/// Rename DBC names that do not make good Rust identifiers.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenameConfig {
+    /// DBC message name -> name used for the generated message.
+    pub messages: std::collections::BTreeMap<String, String>,
+    /// `"<DBC message name>.<DBC signal name>"` -> name used for the generated signal.
+    pub signals: std::collections::BTreeMap<String, String>,
+}
+
+/// Rust type a signal is decoded to, overriding the type derived from the DBC.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl SignalType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SignalType::Bool => "bool",
+            SignalType::U8 => "u8",
+            SignalType::U16 => "u16",
+            SignalType::U32 => "u32",
+            SignalType::U64 => "u64",
+            SignalType::I8 => "i8",
+            SignalType::I16 => "i16",
+            SignalType::I32 => "i32",
+            SignalType::I64 => "i64",
+            SignalType::F32 => "f32",
+            SignalType::F64 => "f64",
+        }
+    }
+}
+
+/// Derives of generated types by kind.
+/// Setting a list replaces the default derives of that kind.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeriveConfig {
+    /// Message structs, default: `["Debug"]`
+    pub messages: Vec<String>,
+    /// Signal value enums, default: `["Debug", "Clone", "Copy", "PartialEq"]`
+    pub enums: Vec<String>,
+}
+
+impl Default for DeriveConfig {
+    fn default() -> Self {
+        DeriveConfig {
+            messages: vec!["Debug".to_string()],
+            enums: ["Debug", "Clone", "Copy", "PartialEq"]
+                .iter()
+                .map(|derive| derive.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Additional attributes of generated types by kind.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttributeConfig {
+    /// Message structs
+    pub messages: Vec<String>,
+    /// Signal value enums
+    pub enums: Vec<String>,
+}
+
+/// Additional derives and attributes for a single generated type.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypeConfig {
+    /// e.g. `["PartialEq", "Hash"]`
+    pub derives: Vec<String>,
+    /// e.g. `["#[non_exhaustive]", "#[cfg_attr(feature = \"serde\", derive(Serialize))]"]`
+    pub attributes: Vec<String>,
+}
+
+/// Match `name` against a pattern with `*` and `?` wildcards.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern and the name position it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wildcard_match;
+
+    #[test]
+    fn wildcard_star() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "EEC1"));
+        assert!(wildcard_match("*1", "EEC1"));
+        assert!(!wildcard_match("*2", "EEC1"));
+    }
+
+    #[test]
+    fn wildcard_question_mark() {
+        assert!(wildcard_match("EEC?", "EEC1"));
+        assert!(!wildcard_match("EEC?", "EEC"));
+        assert!(!wildcard_match("EEC?", "EEC12"));
+        assert!(wildcard_match("???", "OEL"));
+    }
+
+    #[test]
+    fn wildcard_trailing_star() {
+        assert!(wildcard_match("EEC*", "EEC"));
+        assert!(wildcard_match("EEC*", "EEC12"));
+        assert!(wildcard_match("EEC**", "EEC1"));
+        assert!(!wildcard_match("EEC*", "EE"));
+        assert!(!wildcard_match("EEC*", "XEEC1"));
+    }
+
+    #[test]
+    fn wildcard_backtracking() {
+        assert!(wildcard_match("a*b*c", "abc"));
+        assert!(wildcard_match("a*b*c", "aXbYc"));
+        // The first `b` and `c` candidates do not lead to a match
+        assert!(wildcard_match("a*b*c", "abcbXc"));
+        assert!(wildcard_match("a*b*c", "acbbc"));
+        assert!(!wildcard_match("a*b*c", "abcb"));
+        assert!(!wildcard_match("a*b*c", "ab"));
+    }
+
+    #[test]
+    fn wildcard_empty_pattern() {
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "EEC1"));
+    }
+}
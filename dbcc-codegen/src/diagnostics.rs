@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use std::fmt;
+
+/// Severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Kind of a code generation or `check` diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// A name not starting with an alphabetic character was prefixed
+    PrefixedName,
+    /// A character that is not valid in a Rust identifier was replaced
+    ReplacedCharacter,
+    /// Two signals of a message share bits
+    OverlappingSignals,
+    /// A signal extends past the message length (DLC)
+    SignalExceedsDlc,
+    /// The signal minimum or maximum can not be reached given size, factor and offset
+    UnreachableRange,
+    /// A multiplexed signal is defined in a message without multiplexor switch
+    MissingMultiplexorSwitch,
+    /// A value description refers to a message or signal that is not defined
+    UndefinedSignal,
+    /// The message name is used by more than one message
+    DuplicateMessageName,
+    /// The message id is used by more than one message
+    DuplicateMessageId,
+    /// A transmitter or receiver is not defined in the node list (`BU_`)
+    UndefinedNode,
+    /// The DBC parser stopped before the end of the file, later sections were skipped
+    IncompleteParse,
+    /// A signal the runtime `Decoder` can not read is skipped
+    UnsupportedSignal,
+}
+
+/// Part of the DBC a diagnostic refers to.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Location {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal_name: Option<String>,
+    /// Line in the DBC file, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Column in the DBC file, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(line) = self.line {
+            parts.push(format!("line {}", line));
+        }
+        if let Some(column) = self.column {
+            parts.push(format!("column {}", column));
+        }
+        if let Some(message_id) = self.message_id {
+            parts.push(format!("message {}", message_id));
+        }
+        if let Some(ref signal_name) = self.signal_name {
+            parts.push(format!("signal {}", signal_name));
+        }
+        if parts.is_empty() {
+            write!(f, "DBC")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// Finding about a DBC.
+/// Code generation only emits warnings, the generated code is still valid
+/// but may not look like the DBC.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.severity, self.message, self.location)
+    }
+}
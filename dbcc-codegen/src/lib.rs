@@ -0,0 +1,2665 @@
+//! Code generation and DBC tooling behind the `dbcc` CLI, `dbcc::Builder` and `dbcc::include_dbc!`.
+//!
+//! Use it via the `dbcc` crate, which re-exports everything.
+use blake2::{Blake2b, Digest};
+use can_dbc::{
+    AttributeDefinition, AttributeValue, AttributeValuedForObjectType, ByteOrder, Message,
+    MessageId, MultiplexIndicator, Signal, SignalExtendedValueType, Transmitter, ValueDescription,
+    ValueType, DBC,
+};
+use codegen::{Enum, Function, Impl, Scope, Struct};
+use heck::{CamelCase, ShoutySnakeCase, SnakeCase};
+use serde::Deserialize;
+
+use std::cmp;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub mod builder;
+pub mod candump;
+pub mod check;
+pub mod config;
+pub mod diagnostics;
+pub mod parse;
+pub mod runtime;
+
+pub use builder::Builder;
+pub use config::Config;
+use config::{AttributeConfig, DeriveConfig, MessageFilter, RenameConfig, SignalType, TypeConfig};
+pub use config::{Runtime, SplitBy};
+pub use diagnostics::{Diagnostic, DiagnosticKind, Location, Severity};
+
+/// Character that is prefixed before type names that are
+/// are not starting with an alphabetic char.
+const PREFIX_CHAR: char = 'X';
+
+/// Character that is used to replace invalid characters
+/// in type names.
+const REPLACEMENT_CHAR: char = 'X';
+
+/// Suffix that is append to the raw signal function
+const RAW_FN_SUFFIX: &str = "raw_value";
+
+/// Message attribute defining the transmission interval in milliseconds
+const CYCLE_TIME_ATTRIBUTE: &str = "GenMsgCycleTime";
+
+/// Message attribute defining the frame format, see `J1939_FRAME_FORMAT`
+const FRAME_FORMAT_ATTRIBUTE: &str = "VFrameFormat";
+
+/// Frame format of J1939 parameter groups
+const J1939_FRAME_FORMAT: &str = "J1939PG";
+
+/// Network attribute defining the protocol, see `J1939_PROTOCOL`
+const PROTOCOL_TYPE_ATTRIBUTE: &str = "ProtocolType";
+
+/// Protocol of J1939 networks
+const J1939_PROTOCOL: &str = "J1939";
+
+/// Extended frame format flag of DBC message ids and SocketCAN frame ids,
+/// see `CAN_EFF_FLAG` of `linux/can.h`
+pub(crate) const EFF_FLAG: u32 = 0x8000_0000;
+
+/// Identifier bits of extended frames, see `CAN_EFF_MASK` of `linux/can.h`
+pub(crate) const EFF_MASK: u32 = 0x1FFF_FFFF;
+
+/// Identifier bits of standard frames, see `CAN_SFF_MASK` of `linux/can.h`
+pub(crate) const SFF_MASK: u32 = 0x7FF;
+
+type Result<T> = std::result::Result<T, CodegenError>;
+
+/// Errors that may occur while generating code from a DBC.
+#[derive(Debug)]
+pub enum CodegenError {
+    /// A value description (`VAL_`) refers to a signal that is not defined in the message
+    MissingSignal {
+        message_id: MessageId,
+        signal_name: String,
+    },
+    /// A multiplexed signal is defined in a message without multiplexor switch signal
+    MissingMultiplexorSwitch {
+        message_id: MessageId,
+        signal_name: String,
+    },
+    /// The signal layout can not be decoded e.g. because it is larger than 64 bit
+    UnsupportedSignal {
+        message_id: MessageId,
+        signal_name: String,
+        reason: String,
+    },
+    /// The message name collides with the `Message` enum or its `Unknown` variant
+    ReservedMessageName { message_id: MessageId, name: String },
+    /// Writing the generated code failed
+    Fmt(fmt::Error),
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodegenError::MissingSignal {
+                message_id,
+                signal_name,
+            } => write!(
+                f,
+                "Message {}: value description for undefined signal `{}`",
+                message_id.0, signal_name
+            ),
+            CodegenError::MissingMultiplexorSwitch {
+                message_id,
+                signal_name,
+            } => write!(
+                f,
+                "Message {}: multiplexed signal `{}` but the message has no multiplexor switch",
+                message_id.0, signal_name
+            ),
+            CodegenError::UnsupportedSignal {
+                message_id,
+                signal_name,
+                reason,
+            } => write!(
+                f,
+                "Message {}: signal `{}` is not supported, {}",
+                message_id.0, signal_name, reason
+            ),
+            CodegenError::ReservedMessageName { message_id, name } => write!(
+                f,
+                "Message {}: the name `{}` is used by the generated `Message` enum, rename the message in `[rename.messages]`",
+                message_id.0, name
+            ),
+            CodegenError::Fmt(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for CodegenError {}
+
+impl From<fmt::Error> for CodegenError {
+    fn from(e: fmt::Error) -> Self {
+        CodegenError::Fmt(e)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DbccOpt {
+    /// Should tokio SocketCan BCM streams be generated.
+    /// This requires the `tokio-socketcan-bcm` crate, same as `runtime = "compat"`.
+    pub with_tokio: bool,
+    /// Generate SocketCan BCM streams for this runtime, see `Runtime`.
+    /// Takes precedence over `with_tokio`.
+    pub runtime: Option<Runtime>,
+    /// Should `can_frame` and `send` functions for raw SocketCAN sockets be generated.
+    /// This requires the `socketcan` crate, combined with a runtime also `send_async`.
+    pub with_socketcan: bool,
+    /// Should `start_cyclic`, `update_cyclic` and `stop_cyclic` functions for cyclic
+    /// transmission via a `dbcc_transport::bcm::BcmSocket` be generated.
+    /// This requires the `dbcc-transport` crate, for all runtimes.
+    pub with_cyclic: bool,
+    /// Should `try_from_frame` and `into_frame` conversions for `embedded_can::Frame`s be generated.
+    /// `try_from_frame` is an inherent function, `TryFrom` is not implemented as a generic
+    /// `impl<F: Frame> TryFrom<&F>` is rejected by the coherence rules.
+    /// This requires the `embedded-can` crate.
+    pub with_embedded_can: bool,
+    /// Messages code is generated for, all messages by default.
+    pub messages: MessageFilter,
+    /// Names used instead of the DBC message and signal names.
+    pub rename: RenameConfig,
+    /// Decoded type overrides keyed by `"<DBC message name>.<DBC signal name>"`.
+    pub signal_types: BTreeMap<String, SignalType>,
+    /// Derives of generated message structs and enums.
+    pub derive: DeriveConfig,
+    /// Additional attributes of generated message structs and enums.
+    pub attributes: AttributeConfig,
+    /// Additional derives and attributes keyed by generated type name.
+    pub types: BTreeMap<String, TypeConfig>,
+    /// Gate the code of each message behind a `msg_<message name>` cargo feature,
+    /// see `cargo_features`.
+    pub message_features: bool,
+}
+
+impl DbccOpt {
+    /// Runtime streams are generated for, `None` if no streams are generated.
+    pub fn stream_runtime(&self) -> Option<Runtime> {
+        self.runtime.or(if self.with_tokio {
+            Some(Runtime::Compat)
+        } else {
+            None
+        })
+    }
+
+    /// `#[cfg(feature = "msg_<message name>")]` if `message_features` is enabled.
+    fn message_cfg(&self, message: &Message) -> Option<String> {
+        if self.message_features {
+            Some(format!(
+                "#[cfg(feature = \"{}\")]",
+                message_feature(self, message)
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Name used for the generated message code, the DBC name unless renamed.
+    pub fn message_name<'a>(&'a self, message: &'a Message) -> &'a str {
+        self.rename
+            .messages
+            .get(message.message_name())
+            .map(String::as_str)
+            .unwrap_or_else(|| message.message_name())
+    }
+
+    /// Name used for the generated signal code, the DBC name unless renamed.
+    pub fn signal_name<'a>(&'a self, message: &Message, signal: &'a Signal) -> &'a str {
+        self.rename
+            .signals
+            .get(&signal_key(message, signal))
+            .map(String::as_str)
+            .unwrap_or_else(|| signal.name())
+    }
+
+    /// Decoded type override for a signal
+    pub fn signal_type(&self, message: &Message, signal: &Signal) -> Option<SignalType> {
+        self.signal_types.get(&signal_key(message, signal)).cloned()
+    }
+
+    /// Attributes of a generated type, attributes of the kind followed by type specific ones.
+    fn type_attributes(&self, kind_attributes: &[String], type_name: &str) -> Vec<String> {
+        let type_attributes = self
+            .types
+            .get(type_name)
+            .map(|type_config| type_config.attributes.as_slice())
+            .unwrap_or(&[]);
+        kind_attributes
+            .iter()
+            .chain(type_attributes)
+            .cloned()
+            .collect()
+    }
+
+    fn type_derives(&self, type_name: &str) -> &[String] {
+        self.types
+            .get(type_name)
+            .map(|type_config| type_config.derives.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// Key of a signal in the configuration maps
+fn signal_key(message: &Message, signal: &Signal) -> String {
+    format!("{}.{}", message.message_name(), signal.name())
+}
+
+fn message_by_id(dbc: &DBC, message_id: MessageId) -> Option<&Message> {
+    dbc.messages()
+        .iter()
+        .find(|message| *message.message_id() == message_id)
+}
+
+/// `use` declarations of a generated file, grouped by path and `cfg` attribute.
+#[derive(Default)]
+struct Imports(Vec<(String, Option<String>, Vec<String>)>);
+
+impl Imports {
+    fn add(&mut self, path: &str, ty: &str) {
+        self.add_gated(path, ty, None);
+    }
+
+    /// Import only if the `cfg` attribute e.g. a message feature is enabled.
+    fn add_gated(&mut self, path: &str, ty: &str, cfg: Option<String>) {
+        match self.0.iter_mut().find(|(p, c, _)| p == path && *c == cfg) {
+            Some((_, _, tys)) => {
+                if !tys.iter().any(|t| t == ty) {
+                    tys.push(ty.to_string());
+                }
+            }
+            None => self.0.push((path.to_string(), cfg, vec![ty.to_string()])),
+        }
+    }
+
+    /// Unused imports are allowed when items may be disabled by message features.
+    fn push_to(&self, scope: &mut Scope, allow_unused: bool) {
+        if !allow_unused {
+            for (path, _, tys) in &self.0 {
+                for ty in tys {
+                    scope.import(path, ty);
+                }
+            }
+            return;
+        }
+        let mut out = String::new();
+        for (path, cfg, tys) in &self.0 {
+            let tys = if tys.len() == 1 {
+                tys[0].clone()
+            } else {
+                format!("{{{}}}", tys.join(", "))
+            };
+            if let Some(cfg) = cfg {
+                out.push_str(cfg);
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "#[allow(unused_imports)]\nuse {}::{};\n",
+                path, tys
+            ));
+        }
+        if !out.is_empty() {
+            scope.raw(out.trim_end());
+        }
+    }
+}
+
+fn push_impl(scope: &mut Scope, item: Impl, attributes: &[String]) {
+    if attributes.is_empty() {
+        scope.push_impl(item);
+    } else {
+        let mut item_scope = Scope::new();
+        item_scope.push_impl(item);
+        scope.raw(&with_attributes(&item_scope, attributes));
+    }
+}
+
+fn push_struct(scope: &mut Scope, item: Struct, attributes: &[String]) {
+    if attributes.is_empty() {
+        scope.push_struct(item);
+    } else {
+        let mut item_scope = Scope::new();
+        item_scope.push_struct(item);
+        scope.raw(&with_attributes(&item_scope, attributes));
+    }
+}
+
+fn push_enum(scope: &mut Scope, item: Enum, attributes: &[String]) {
+    if attributes.is_empty() {
+        scope.push_enum(item);
+    } else {
+        let mut item_scope = Scope::new();
+        item_scope.push_enum(item);
+        scope.raw(&with_attributes(&item_scope, attributes));
+    }
+}
+
+/// Render an item with additional attributes.
+/// `codegen` only supports derives and `allow` on types so other attributes are prepended
+/// to the formatted item.
+fn with_attributes(item: &Scope, attributes: &[String]) -> String {
+    let mut out = String::new();
+    for attribute in attributes {
+        if attribute.starts_with('#') {
+            out.push_str(attribute);
+        } else {
+            out.push_str(&format!("#[{}]", attribute));
+        }
+        out.push('\n');
+    }
+    out.push_str(item.to_string().trim_end());
+    out
+}
+
+/// Blake2b hash of the DBC file content as written into the generated file header.
+pub fn dbc_hash(buffer: &[u8]) -> String {
+    format!("Blake2b: {:X}", Blake2b::digest(buffer))
+}
+
+pub trait TypeName: ToOwned {
+    fn to_type_name(&self) -> Self::Owned;
+}
+
+impl TypeName for str {
+    fn to_type_name(&self) -> String {
+        fix_type_name(self, |_, _| {})
+    }
+}
+
+/// Turn `name` into a valid type name, `on_fixup` is called for every change made.
+fn fix_type_name<F: FnMut(DiagnosticKind, String)>(name: &str, mut on_fixup: F) -> String {
+    let mut out = String::with_capacity(name.len() + 1);
+    let mut chars = name.chars();
+    if let Some(first) = chars.next() {
+        if !first.is_alphabetic() && first != '_' {
+            on_fixup(
+                DiagnosticKind::PrefixedName,
+                format!("`{}` is prefixed with `{}`", name, PREFIX_CHAR),
+            );
+            out.push(PREFIX_CHAR);
+        }
+        out.push(first);
+    }
+
+    for chr in chars {
+        if chr.is_ascii_digit() || chr.is_alphabetic() || chr == '_' {
+            out.push(chr);
+        } else {
+            on_fixup(
+                DiagnosticKind::ReplacedCharacter,
+                format!(
+                    "`{}` character in `{}` is replaced by `{}`",
+                    chr, name, REPLACEMENT_CHAR
+                ),
+            );
+            out.push(REPLACEMENT_CHAR);
+        }
+    }
+
+    out
+}
+
+/// Diagnostics for the enum variant names generated from a value description.
+fn value_description_diagnostics(val_desc: &ValueDescription) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if let ValueDescription::Signal {
+        message_id,
+        signal_name,
+        value_descriptions,
+    } = val_desc
+    {
+        for desc in value_descriptions {
+            fix_type_name(&desc.b().to_camel_case(), |kind, message| {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    kind,
+                    location: Location {
+                        message_id: Some(message_id.0),
+                        signal_name: Some(signal_name.clone()),
+                        ..Default::default()
+                    },
+                    message,
+                })
+            });
+        }
+    }
+    diagnostics
+}
+
+fn to_enum_name(message_id: MessageId, signal_name: &str) -> String {
+    format!("{}{}", &signal_name.to_camel_case(), message_id.0)
+}
+
+/// Enum name of a value description, using the renamed signal name if configured.
+fn value_description_enum_name(
+    opt: &DbccOpt,
+    dbc: &DBC,
+    message_id: MessageId,
+    signal_name: &str,
+) -> String {
+    let message = message_by_id(dbc, message_id);
+    let signal = dbc.signal_by_name(message_id, signal_name);
+    match (message, signal) {
+        (Some(message), Some(signal)) => to_enum_name(message_id, opt.signal_name(message, signal)),
+        _ => to_enum_name(message_id, signal_name),
+    }
+}
+
+pub fn signal_enum(opt: &DbccOpt, dbc: &DBC, val_desc: &ValueDescription) -> Option<Enum> {
+    if let ValueDescription::Signal {
+        ref message_id,
+        ref signal_name,
+        ref value_descriptions,
+    } = val_desc
+    {
+        let enum_name = value_description_enum_name(opt, dbc, *message_id, signal_name);
+        let mut sig_enum = Enum::new(&enum_name);
+        sig_enum.allow("dead_code");
+        sig_enum.vis("pub");
+        sig_enum.repr("u64");
+        for derive in opt.derive.enums.iter().chain(opt.type_derives(&enum_name)) {
+            sig_enum.derive(derive);
+        }
+        for desc in value_descriptions {
+            sig_enum.new_variant(&desc.b().to_camel_case().to_type_name());
+        }
+
+        let message = message_by_id(dbc, *message_id);
+        let signal = dbc.signal_by_name(*message_id, signal_name);
+        if let (Some(message), Some(signal)) = (message, signal) {
+            let decoded_type = signal_rust_type(opt, dbc, message, signal);
+            sig_enum.new_variant(&format!("XValue({})", decoded_type));
+        } else {
+            sig_enum.new_variant("XValue(u64)");
+        }
+        return Some(sig_enum);
+    }
+    None
+}
+
+pub fn signal_enum_impl_from(
+    opt: &DbccOpt,
+    dbc: &DBC,
+    val_desc: &ValueDescription,
+) -> Result<Option<Impl>> {
+    if let ValueDescription::Signal {
+        ref message_id,
+        ref signal_name,
+        ref value_descriptions,
+    } = val_desc
+    {
+        let missing_signal = || CodegenError::MissingSignal {
+            message_id: *message_id,
+            signal_name: signal_name.clone(),
+        };
+        let message = message_by_id(dbc, *message_id).ok_or_else(missing_signal)?;
+        let signal = dbc
+            .signal_by_name(*message_id, signal_name)
+            .ok_or_else(missing_signal)?;
+        let signal_type = signal_rust_type(opt, dbc, message, signal);
+
+        let enum_name = value_description_enum_name(opt, dbc, *message_id, signal_name);
+        let mut enum_impl = Impl::new(codegen::Type::new(&enum_name));
+        enum_impl.impl_trait(format!("From<{}>", signal_type));
+
+        let from_fn = enum_impl.new_fn("from");
+        from_fn.allow("dead_code");
+        from_fn.arg("val", codegen::Type::new(&signal_type));
+        from_fn.ret(codegen::Type::new("Self"));
+
+        let mut matching = String::new();
+        writeln!(&mut matching, "match val as u64 {{")?;
+        for value_description in value_descriptions {
+            writeln!(
+                &mut matching,
+                "    {} => {}::{},",
+                value_description.a(),
+                enum_name,
+                value_description.b().to_camel_case().to_type_name()
+            )?;
+        }
+        writeln!(&mut matching, "    _ => {}::XValue(val),", enum_name)?;
+        write!(&mut matching, "}}")?;
+
+        from_fn.line(matching);
+
+        return Ok(Some(enum_impl));
+    }
+    Ok(None)
+}
+
+pub fn signal_fn_raw(
+    opt: &DbccOpt,
+    dbc: &DBC,
+    message: &Message,
+    signal: &Signal,
+) -> Result<Function> {
+    let message_id = *message.message_id();
+    let raw_fn_name = format!(
+        "{}_{}",
+        opt.signal_name(message, signal).to_snake_case(),
+        RAW_FN_SUFFIX
+    );
+
+    let mut signal_fn = codegen::Function::new(&raw_fn_name);
+    signal_fn.allow(signal_fn_allow(&raw_fn_name));
+    signal_fn.vis("pub");
+    signal_fn.arg_ref_self();
+
+    let signal_decoded_type = signal_rust_type(opt, dbc, message, signal);
+    let signal_decoded_type = wrap_multiplex_indicator_type(signal, signal_decoded_type);
+    signal_fn.ret(codegen::Type::new(&signal_decoded_type));
+
+    let default_signal_comment = format!("Read {} signal from can frame", signal.name());
+    let signal_comment = dbc
+        .signal_comment(message_id, signal.name())
+        .unwrap_or(&default_signal_comment);
+
+    let signal_unit = if signal.unit().is_empty() {
+        String::default()
+    } else {
+        format!("\nUnit: {}", signal.unit())
+    };
+
+    signal_fn.doc(&format!("{}{}", signal_comment, signal_unit));
+
+    // Multiplexed signals are only available when the multiplexer switch value matches
+    // the multiplexed indicator value defined in the DBC.
+    if let MultiplexIndicator::MultiplexedSignal(switch_value) = signal.multiplexer_indicator() {
+        let multiplexor_switch = dbc.message_multiplexor_switch(message_id).ok_or_else(|| {
+            CodegenError::MissingMultiplexorSwitch {
+                message_id,
+                signal_name: signal.name().clone(),
+            }
+        })?;
+        let multiplexor_switch_fn = format!(
+            "self.{}_{}()",
+            opt.signal_name(message, multiplexor_switch).to_snake_case(),
+            RAW_FN_SUFFIX
+        );
+        signal_fn.line(format!(
+            "if {} != {} {{",
+            multiplexor_switch_fn, switch_value
+        ));
+        signal_fn.line("    return None;");
+        signal_fn.line("}");
+    }
+
+    let (byte_offset, signal_shift) = signal_position(message, signal)?;
+    let payload = if byte_offset == 0 {
+        "&self.frame_payload".to_string()
+    } else {
+        format!("&self.frame_payload[{}..]", byte_offset)
+    };
+    let read_byte_order = match signal.byte_order() {
+        ByteOrder::LittleEndian => format!("let frame_payload: u64 = LE::read_u64({});", payload),
+        ByteOrder::BigEndian => format!("let frame_payload: u64 = BE::read_u64({});", payload),
+    };
+    signal_fn.line(read_byte_order);
+
+    let bit_msk_const = bit_mask(*signal.signal_size());
+
+    let calc = calc_raw(opt, dbc, message, signal, signal_shift, bit_msk_const)?;
+    let wrapped_calc = wrap_multiplex_indicator_value(signal, calc);
+    signal_fn.line(wrapped_calc);
+
+    Ok(signal_fn)
+}
+
+pub fn signal_fn_enum(
+    opt: &DbccOpt,
+    message: &Message,
+    signal: &Signal,
+    enum_type: String,
+) -> Result<Function> {
+    let fn_name = opt.signal_name(message, signal).to_snake_case();
+    let mut signal_fn = codegen::Function::new(&fn_name);
+    signal_fn.allow(signal_fn_allow(&fn_name));
+    signal_fn.vis("pub");
+    signal_fn.arg_ref_self();
+
+    signal_fn.ret(wrap_multiplex_indicator_type(signal, enum_type.clone()));
+
+    let raw_fn_name = format!("{}_{}", fn_name, RAW_FN_SUFFIX);
+
+    // Multiplexed signals are only available when the multiplexer switch value matches
+    // the multiplexed indicator value defined in the DBC.
+    let _ = match signal.multiplexer_indicator() {
+        MultiplexIndicator::MultiplexedSignal(_) => {
+            signal_fn.line(format!("self.{}().map({}::from)", raw_fn_name, enum_type))
+        }
+        _ => signal_fn.line(format!("{}::from(self.{}())", enum_type, raw_fn_name)),
+    };
+
+    Ok(signal_fn)
+}
+
+/// Setter writing a signal into the payload, the inverse of `signal_fn_raw`.
+pub fn signal_fn_set(
+    opt: &DbccOpt,
+    dbc: &DBC,
+    message: &Message,
+    signal: &Signal,
+) -> Result<Function> {
+    let message_id = *message.message_id();
+    let set_fn_name = format!(
+        "set_{}_{}",
+        opt.signal_name(message, signal).to_snake_case(),
+        RAW_FN_SUFFIX
+    );
+    let mut set_fn = codegen::Function::new(&set_fn_name);
+    set_fn.allow("dead_code");
+    set_fn.vis("pub");
+    set_fn.arg_mut_self();
+
+    // Same type as returned by the getter
+    let signal_rust_type = signal_rust_type(opt, dbc, message, signal);
+    set_fn.arg("value", codegen::Type::new(&signal_rust_type));
+
+    let mut doc = format!("Write {} signal into the can frame", signal.name());
+    if let MultiplexIndicator::MultiplexedSignal(switch_value) = signal.multiplexer_indicator() {
+        write!(
+            &mut doc,
+            "\nOnly read back while the multiplexor switch is {}",
+            switch_value
+        )?;
+    }
+    set_fn.doc(&doc);
+
+    let (byte_offset, signal_shift) = signal_position(message, signal)?;
+    let payload = if byte_offset == 0 {
+        "self.frame_payload".to_string()
+    } else {
+        format!("self.frame_payload[{}..]", byte_offset)
+    };
+    let byte_order = match signal.byte_order() {
+        ByteOrder::LittleEndian => "LE",
+        ByteOrder::BigEndian => "BE",
+    };
+
+    let signal_decoded_type = signal_decoded_type(dbc, message_id, signal);
+    if signal_rust_type != signal_decoded_type {
+        set_fn.line(format!(
+            "let value = {};",
+            convert_type(&signal_rust_type, &signal_decoded_type)
+        ));
+    }
+    set_fn.line(format!(
+        "let raw: u64 = {};",
+        encode_raw(dbc, message_id, signal)?
+    ));
+    let bit_msk_const = bit_mask(*signal.signal_size());
+    if bit_msk_const == u64::MAX {
+        set_fn.line(format!("{}::write_u64(&mut {}, raw);", byte_order, payload));
+    } else {
+        set_fn.line(format!(
+            "let frame_payload: u64 = {}::read_u64(&{});",
+            byte_order, payload
+        ));
+        let shifted = if signal_shift == 0 {
+            format!("(raw & {:#X})", bit_msk_const)
+        } else {
+            format!("((raw & {:#X}) << {})", bit_msk_const, signal_shift)
+        };
+        set_fn.line(format!(
+            "{}::write_u64(&mut {}, (frame_payload & {:#X}) | {});",
+            byte_order,
+            payload,
+            !(bit_msk_const << signal_shift),
+            shifted
+        ));
+    }
+
+    Ok(set_fn)
+}
+
+/// Expression converting `value` of a configured signal type back to the decoded type,
+/// the inverse of the conversion at the end of `calc_raw`.
+fn convert_type(from: &str, to: &str) -> String {
+    match (from, to) {
+        (_, "bool") if from.starts_with('f') => "value != 0.0".to_string(),
+        (_, "bool") => "value != 0".to_string(),
+        ("bool", _) if to.starts_with('f') => format!("{}::from(u8::from(value))", to),
+        ("bool", _) => format!("{}::from(value)", to),
+        _ => format!("value as {}", to),
+    }
+}
+
+/// Expression converting `value` of the decoded type back to the raw signal bits,
+/// the inverse of `calc_raw` without the configured type conversion.
+fn encode_raw(dbc: &DBC, message_id: MessageId, signal: &Signal) -> Result<String> {
+    let signal_decoded_type = signal_decoded_type(dbc, message_id, signal);
+    let mut physical = "value".to_string();
+    if *signal.offset() != 0.0 {
+        let sign = if *signal.offset() < 0.0 { '+' } else { '-' };
+        physical = format!(
+            "{} {} {}{}",
+            physical,
+            sign,
+            signal.offset().abs(),
+            signal_decoded_type
+        );
+    }
+    if *signal.factor() != 1.0 {
+        if *signal.offset() != 0.0 {
+            physical = format!("({})", physical);
+        }
+        physical = format!("{} / {:?}", physical, signal.factor());
+    }
+
+    let raw = match dbc.extended_value_type_for_signal(message_id, signal.name()) {
+        // IEEE floats are stored as their bit pattern
+        Some(SignalExtendedValueType::IEEEfloat32Bit) if physical == "value" => {
+            "u64::from(value.to_bits())".to_string()
+        }
+        Some(SignalExtendedValueType::IEEEfloat32Bit) => {
+            format!("u64::from(({}).to_bits())", physical)
+        }
+        Some(SignalExtendedValueType::IEEEdouble64bit) if physical == "value" => {
+            "value.to_bits()".to_string()
+        }
+        Some(SignalExtendedValueType::IEEEdouble64bit) => format!("({}).to_bits()", physical),
+        // Scaled signals are decoded to f64
+        _ if physical != "value" => match signal.value_type() {
+            ValueType::Signed => format!("({}).round() as i64 as u64", physical),
+            ValueType::Unsigned => format!("({}).round() as u64", physical),
+        },
+        _ if signal_decoded_type == "u64" => "value".to_string(),
+        _ if signal_decoded_type.starts_with('i') => "value as u64".to_string(),
+        _ => "u64::from(value)".to_string(),
+    };
+    Ok(raw)
+}
+
+/// Signal accessor names are dictated by the DBC, so names that look like
+/// conversions (e.g. `FromPort` -> `from_port`) must not trip clippy's naming lints.
+fn signal_fn_allow(fn_name: &str) -> &'static str {
+    if fn_name.starts_with("from_") || fn_name.starts_with("into_") {
+        "dead_code, clippy::wrong_self_convention"
+    } else {
+        "dead_code"
+    }
+}
+
+fn calc_raw(
+    opt: &DbccOpt,
+    dbc: &DBC,
+    message: &Message,
+    signal: &Signal,
+    signal_shift: u64,
+    bit_msk_const: u64,
+) -> Result<String> {
+    let message_id = *message.message_id();
+    let signal_decoded_type = signal_decoded_type(dbc, message_id, signal);
+    let boolean_signal =
+        *signal.signal_size() == 1 && *signal.factor() == 1.0 && *signal.offset() == 0.0;
+
+    let mut calc = String::new();
+
+    // No shift required if start_bit == 0
+    let shift = if signal_shift == 0 {
+        "frame_payload".to_string()
+    } else {
+        format!("(frame_payload >> {})", signal_shift)
+    };
+
+    // A 64 bit signal spans the whole payload, masking is a no-op
+    let (masked, masked_operand) = if bit_msk_const == u64::MAX {
+        (shift.clone(), shift)
+    } else {
+        let masked = format!("{} & {:#X}", shift, bit_msk_const);
+        (masked.clone(), format!("({})", masked))
+    };
+
+    // IEEE floats are stored as their bit pattern and must be reinterpreted,
+    // a numeric cast would convert the integer value instead.
+    match dbc.extended_value_type_for_signal(message_id, signal.name()) {
+        Some(SignalExtendedValueType::IEEEfloat32Bit) => {
+            write!(&mut calc, "f32::from_bits({} as u32)", masked_operand)?
+        }
+        Some(SignalExtendedValueType::IEEEdouble64bit) => {
+            write!(&mut calc, "f64::from_bits({})", masked)?
+        }
+        _ if boolean_signal => write!(&mut calc, "{}", masked_operand)?,
+        _ if signal_decoded_type == "u64" => write!(&mut calc, "{}", masked)?,
+        _ => write!(&mut calc, "{} as {}", masked_operand, signal_decoded_type)?,
+    }
+
+    if *signal.factor() != 1.0 {
+        // Debug formatting keeps the shortest exact representation including a decimal point
+        write!(&mut calc, " * {:?}", signal.factor())?;
+    }
+
+    if *signal.offset() != 0.0 {
+        write!(&mut calc, " + {}{}", signal.offset(), signal_decoded_type)?;
+    }
+
+    if boolean_signal {
+        write!(&mut calc, " == 1")?;
+    }
+
+    // Convert to the configured type, booleans are true for any non zero value
+    let calc = match opt.signal_type(message, signal) {
+        Some(target) if target.as_str() == signal_decoded_type => calc,
+        Some(SignalType::Bool) if signal_decoded_type.starts_with('f') => {
+            format!("({}) != 0.0", calc)
+        }
+        Some(SignalType::Bool) => format!("({}) != 0", calc),
+        Some(target) if boolean_signal => format!("{} as {}", masked_operand, target.as_str()),
+        Some(target) => format!("({}) as {}", calc, target.as_str()),
+        None => calc,
+    };
+
+    Ok(calc)
+}
+
+/// This wraps multiplex indicators in  Option types.
+/// Multiplexed signals are only available when the multiplexer switch value matches
+/// the multiplexed indicator value defined in the DBC.
+fn wrap_multiplex_indicator_type(signal: &Signal, signal_type: String) -> String {
+    match signal.multiplexer_indicator() {
+        MultiplexIndicator::MultiplexedSignal(_) => format!("Option<{}>", signal_type).to_string(),
+        _ => signal_type,
+    }
+}
+
+/// This wraps multiplex indicators in  Option types.
+/// Multiplexed signals are only available when the multiplexer switch value matches
+/// the multiplexed indicator value defined in the DBC.
+fn wrap_multiplex_indicator_value(signal: &Signal, signal_value: String) -> String {
+    match signal.multiplexer_indicator() {
+        MultiplexIndicator::MultiplexedSignal(_) => format!("Some({})", signal_value).to_string(),
+        _ => signal_value,
+    }
+}
+
+/// Type a signal is decoded to, taking configured overrides into account.
+fn signal_rust_type(opt: &DbccOpt, dbc: &DBC, message: &Message, signal: &Signal) -> String {
+    match opt.signal_type(message, signal) {
+        Some(signal_type) => signal_type.as_str().to_string(),
+        None => signal_decoded_type(dbc, *message.message_id(), signal),
+    }
+}
+
+/// Type a signal is decoded to based on the DBC definition
+fn signal_decoded_type(dbc: &DBC, message_id: MessageId, signal: &Signal) -> String {
+    if let Some(extended_value_type) = dbc.extended_value_type_for_signal(message_id, signal.name())
+    {
+        match extended_value_type {
+            SignalExtendedValueType::IEEEfloat32Bit => return "f32".to_string(),
+            SignalExtendedValueType::IEEEdouble64bit => return "f64".to_string(),
+            SignalExtendedValueType::SignedOrUnsignedInteger => (), // Handled below, also part of the Signal itself
+        }
+    }
+
+    if !(*signal.offset() == 0.0 && *signal.factor() == 1.0) {
+        return "f64".to_string();
+    }
+
+    let prefix_int_sign = match *signal.value_type() {
+        ValueType::Signed => "i",
+        ValueType::Unsigned => "u",
+    };
+
+    match signal.signal_size() {
+        _ if *signal.signal_size() == 1 => "bool".to_string(),
+        _ if *signal.signal_size() > 1 && *signal.signal_size() <= 8 => {
+            format!("{}8", prefix_int_sign).to_string()
+        }
+        _ if *signal.signal_size() > 8 && *signal.signal_size() <= 16 => {
+            format!("{}16", prefix_int_sign).to_string()
+        }
+        _ if *signal.signal_size() > 16 && *signal.signal_size() <= 32 => {
+            format!("{}32", prefix_int_sign).to_string()
+        }
+        _ => format!("{}64", prefix_int_sign).to_string(),
+    }
+}
+
+/// Mask covering the lowest `signal_size` bits
+fn bit_mask(signal_size: u64) -> u64 {
+    if signal_size >= 64 {
+        u64::MAX
+    } else {
+        (1 << signal_size) - 1
+    }
+}
+
+/// Byte offset of the 64 bit window a signal is read from and the
+/// right shift within that window.
+/// Signals within the first 8 bytes are read from the start of the payload,
+/// signals of longer messages are read from the byte they start in.
+fn signal_position(message: &Message, signal: &Signal) -> Result<(u64, u64)> {
+    let unsupported = |reason: String| CodegenError::UnsupportedSignal {
+        message_id: *message.message_id(),
+        signal_name: signal.name().clone(),
+        reason,
+    };
+    let signal_size = *signal.signal_size();
+    if signal_size == 0 || signal_size > 64 {
+        return Err(unsupported(format!(
+            "signal size must be between 1 and 64 bit but is {} bit",
+            signal_size
+        )));
+    }
+    let start_bit = *signal.start_bit();
+    let (byte_offset, end_bit) = match signal.byte_order() {
+        ByteOrder::LittleEndian => {
+            if start_bit.saturating_add(signal_size) <= 64 {
+                (0, start_bit + signal_size)
+            } else {
+                (start_bit / 8, start_bit % 8 + signal_size)
+            }
+        }
+        ByteOrder::BigEndian => {
+            // Position of the most significant bit counted from the first payload bit
+            let msb = (start_bit / 8).saturating_mul(8) + (7 - (start_bit % 8));
+            let byte_offset = if msb.saturating_add(signal_size) <= 64 {
+                0
+            } else {
+                msb / 8
+            };
+            (byte_offset, msb - byte_offset * 8 + signal_size)
+        }
+    };
+    if end_bit > 64 {
+        return Err(unsupported(format!(
+            "start bit {} and size {} do not fit into a 64 bit window",
+            start_bit, signal_size
+        )));
+    }
+    let signal_shift = match signal.byte_order() {
+        ByteOrder::LittleEndian => end_bit - signal_size,
+        ByteOrder::BigEndian => 64 - end_bit,
+    };
+    Ok((byte_offset, signal_shift))
+}
+
+/// Payload length that allows reading every signal of a message as a 64 bit window.
+fn payload_len(message: &Message) -> Result<u64> {
+    let mut len = cmp::max(8, *message.message_size());
+    for signal in message.signals() {
+        let (byte_offset, _) = signal_position(message, signal)?;
+        len = cmp::max(len, byte_offset + 8);
+    }
+    Ok(len)
+}
+
+fn message_const(opt: &DbccOpt, dbc: &DBC, message: &Message) -> String {
+    let const_name = opt.message_name(message).to_shouty_snake_case();
+    let cfg = opt
+        .message_cfg(message)
+        .map(|cfg| format!("{}\n", cfg))
+        .unwrap_or_default();
+    let mut message_const = format!(
+        "{}#[allow(dead_code)]\npub const MESSAGE_ID_{}: u32 = {};",
+        cfg,
+        const_name,
+        message.message_id().0
+    );
+    if let Some(pgn) = j1939_pgn(dbc, message) {
+        message_const.push_str(&format!(
+            "\n/// J1939 parameter group number\n{}#[allow(dead_code)]\npub const PGN_{}: u32 = {:#X};",
+            cfg, const_name, pgn
+        ));
+    }
+    message_const
+}
+
+fn message_struct(opt: &DbccOpt, dbc: &DBC, message: &Message) -> Struct {
+    let struct_name = opt.message_name(message).to_camel_case();
+    let mut message_struct = Struct::new(&struct_name);
+    if let Some(message_comment) = dbc.message_comment(*message.message_id()) {
+        message_struct.doc(message_comment);
+    }
+    message_struct.allow("dead_code");
+    for derive in opt
+        .derive
+        .messages
+        .iter()
+        .chain(opt.type_derives(&struct_name))
+    {
+        message_struct.derive(derive);
+    }
+    message_struct.vis("pub");
+    message_struct.field("frame_payload", "Vec<u8>");
+    if j1939_pgn(dbc, message).is_some() {
+        message_struct.field("id", "u32");
+    }
+    message_struct
+}
+
+fn message_impl(opt: &DbccOpt, dbc: &DBC, message: &Message) -> Result<Impl> {
+    let struct_name = opt.message_name(message).to_camel_case();
+    let mut msg_impl = Impl::new(codegen::Type::new(&struct_name));
+
+    let new_fn = msg_impl.new_fn("new");
+    new_fn.allow("dead_code");
+    new_fn.vis("pub");
+    new_fn.arg("mut frame_payload", codegen::Type::new("Vec<u8>"));
+    new_fn.line(format!(
+        "frame_payload.resize({}, 0);",
+        payload_len(message)?
+    ));
+    let pgn = j1939_pgn(dbc, message);
+    if pgn.is_some() {
+        new_fn.line(format!(
+            "{} {{ frame_payload, id: {:#010X} }}",
+            struct_name,
+            message.message_id().0 & EFF_MASK
+        ));
+    } else {
+        new_fn.line(format!("{} {{ frame_payload }}", struct_name));
+    }
+    new_fn.ret(codegen::Type::new(&struct_name));
+
+    let payload_fn = msg_impl.new_fn("frame_payload");
+    payload_fn.doc("Payload of the message length (DLC), e.g. after setting signals");
+    payload_fn.allow("dead_code");
+    payload_fn.vis("pub");
+    payload_fn.arg_ref_self();
+    payload_fn.ret(codegen::Type::new("&[u8]"));
+    payload_fn.line(format!("&self.frame_payload[..{}]", message.message_size()));
+
+    if let Some(pgn) = pgn {
+        for j1939_fn in message_j1939(opt, message, pgn) {
+            msg_impl.push_fn(j1939_fn);
+        }
+    }
+
+    if let Some(runtime) = opt.stream_runtime() {
+        msg_impl.push_fn(message_stream(opt, runtime, message));
+        if runtime != Runtime::Compat {
+            msg_impl.push_fn(message_stream_from(opt, message, pgn));
+            msg_impl.push_fn(message_frame(message, pgn));
+            msg_impl.push_fn(message_send_to());
+            if pgn.is_some() {
+                msg_impl.push_fn(message_parameter_group(message));
+            } else if *message.message_size() > 8 {
+                for isotp_fn in message_isotp(opt, message) {
+                    msg_impl.push_fn(isotp_fn);
+                }
+            }
+        }
+    }
+
+    if opt.with_cyclic {
+        for cyclic_fn in message_cyclic(dbc, message) {
+            msg_impl.push_fn(cyclic_fn);
+        }
+    }
+
+    if opt.with_socketcan {
+        msg_impl.push_fn(message_can_frame(message, pgn));
+        msg_impl.push_fn(message_send());
+        if let Some(runtime) = opt.stream_runtime() {
+            msg_impl.push_fn(message_send_async(runtime));
+        }
+    }
+
+    if opt.with_embedded_can {
+        msg_impl.push_fn(message_try_from_frame(opt, message, pgn));
+        msg_impl.push_fn(message_into_frame(message, pgn));
+    }
+
+    for signal in message.signals() {
+        msg_impl.push_fn(signal_fn_raw(opt, dbc, message, signal)?);
+        msg_impl.push_fn(signal_fn_set(opt, dbc, message, signal)?);
+
+        // Check if this signal can be turned into an enum
+        let enum_type = dbc
+            .value_descriptions_for_signal(*message.message_id(), signal.name())
+            .map(|_| to_enum_name(*message.message_id(), opt.signal_name(message, signal)));
+        if let Some(enum_type) = enum_type {
+            msg_impl.push_fn(signal_fn_enum(opt, message, signal, enum_type)?);
+        }
+    }
+
+    Ok(msg_impl)
+}
+
+/// J1939 identifier accessors and builders, the identifier defaults to the one of the DBC.
+fn message_j1939(opt: &DbccOpt, message: &Message, pgn: u32) -> Vec<Function> {
+    let struct_name = opt.message_name(message).to_camel_case();
+    let mut fns = Vec::new();
+
+    let mut new_with_id_fn = codegen::Function::new("new_with_id");
+    new_with_id_fn
+        .doc("Message sent with the J1939 identifier `id`, e.g. by another source address");
+    new_with_id_fn.allow("dead_code");
+    new_with_id_fn.vis("pub");
+    new_with_id_fn.arg("id", codegen::Type::new("u32"));
+    new_with_id_fn.arg("frame_payload", codegen::Type::new("Vec<u8>"));
+    new_with_id_fn.ret(codegen::Type::new(&struct_name));
+    new_with_id_fn.line(format!(
+        "{} {{ id: id & {:#X}, ..{}::new(frame_payload) }}",
+        struct_name, EFF_MASK, struct_name
+    ));
+    fns.push(new_with_id_fn);
+
+    let mut getter = |name: &str, doc: &str, ret: &str, line: String| {
+        let mut getter_fn = codegen::Function::new(name);
+        getter_fn.doc(doc);
+        getter_fn.allow("dead_code");
+        getter_fn.vis("pub");
+        getter_fn.arg_ref_self();
+        getter_fn.ret(ret);
+        getter_fn.line(line);
+        fns.push(getter_fn);
+    };
+    getter(
+        "id",
+        "J1939 identifier (29 bit) this message is sent or was received with",
+        "u32",
+        "self.id".to_string(),
+    );
+    getter(
+        "priority",
+        "Priority, 0 is the highest",
+        "u8",
+        "(self.id >> 26) as u8 & 0x7".to_string(),
+    );
+    getter(
+        "source_address",
+        "Address of the sender",
+        "u8",
+        "self.id as u8".to_string(),
+    );
+    if j1939_is_pdu1(pgn) {
+        getter(
+            "destination_address",
+            "Address of the receiver, `0xFF` for all",
+            "u8",
+            "(self.id >> 8) as u8".to_string(),
+        );
+    } else {
+        getter(
+            "destination_address",
+            "Address of the receiver, always `0xFF` (all) as this message is broadcast (PDU2)",
+            "u8",
+            "0xFF".to_string(),
+        );
+    }
+
+    let mut setter = |name: &str, doc: &str, arg: &str, mask: u32, bits: &str| {
+        let mut setter_fn = codegen::Function::new(name);
+        setter_fn.doc(doc);
+        setter_fn.allow("dead_code");
+        setter_fn.vis("pub");
+        setter_fn.arg_self();
+        setter_fn.arg(arg, codegen::Type::new("u8"));
+        setter_fn.ret(codegen::Type::new(&struct_name));
+        setter_fn.line(format!(
+            "{} {{ id: (self.id & !{:#X}) | {}, ..self }}",
+            struct_name, mask, bits
+        ));
+        fns.push(setter_fn);
+    };
+    setter(
+        "with_priority",
+        "Send this message with `priority` (0 to 7)",
+        "priority",
+        0x1C00_0000,
+        "(u32::from(priority) << 26) & 0x1C000000",
+    );
+    setter(
+        "with_source_address",
+        "Send this message from `source_address`",
+        "source_address",
+        0xFF,
+        "u32::from(source_address)",
+    );
+    if j1939_is_pdu1(pgn) {
+        setter(
+            "with_destination_address",
+            "Send this message to `destination_address`, `0xFF` for all",
+            "destination_address",
+            0xFF00,
+            "u32::from(destination_address) << 8",
+        );
+    }
+
+    fns
+}
+
+/// Generate message stream using socketcan's Broadcast Manager filters,
+/// via tokio-socketcan-bcm for the compat runtime and dbcc-transport otherwise.
+fn message_stream(opt: &DbccOpt, runtime: Runtime, message: &Message) -> Function {
+    let mut stream_fn = codegen::Function::new("stream");
+    stream_fn.allow("dead_code");
+    stream_fn.vis("pub");
+
+    stream_fn.arg("can_interface", codegen::Type::new("&str"));
+    stream_fn.arg("ival1", codegen::Type::new("&std::time::Duration"));
+    stream_fn.arg("ival2", codegen::Type::new("&std::time::Duration"));
+
+    let ret = format!(
+        "std::io::Result<impl Stream<Item = Result<{}, std::io::Error>>>",
+        opt.message_name(message).to_camel_case()
+    );
+    stream_fn.ret(ret);
+
+    if runtime == Runtime::Compat {
+        stream_fn.line("let socket = BCMSocket::open_nb(can_interface)?;");
+
+        let message_id = match message.message_id().0 & EFF_MASK {
+            0..=SFF_MASK => format!(
+                "let message_id = CANMessageId::SFF({} as u16);",
+                message.message_id().0 & SFF_MASK
+            ),
+            _ => format!(
+                "let message_id = CANMessageId::EFF({});",
+                message.message_id().0 & EFF_MASK
+            ),
+        };
+        stream_fn.line(message_id);
+
+        stream_fn.line("let frame_stream = socket.filter_id_incoming_frames(message_id, *ival1, *ival2)?.compat();");
+    } else {
+        stream_fn.line(format!(
+            "let frame_stream = BcmStream::filter_id(can_interface, {}, *ival1, *ival2)?;",
+            message.message_id().0
+        ));
+    }
+    stream_fn.line(format!(
+        "let f = frame_stream.map(|frame| frame.map(|frame| {}::new(frame.data().to_vec())));",
+        opt.message_name(message).to_camel_case()
+    ));
+    stream_fn.line("Ok(f)");
+
+    stream_fn
+}
+
+/// Generate a message stream over any `dbcc_transport::CanSource`.
+fn message_stream_from(opt: &DbccOpt, message: &Message, pgn: Option<u32>) -> Function {
+    let struct_name = opt.message_name(message).to_camel_case();
+    let mut stream_fn = codegen::Function::new("stream_from");
+    stream_fn
+        .doc("Decode the frames of this message received by `source`, other frames are skipped");
+    stream_fn.allow("dead_code");
+    stream_fn.vis("pub");
+    stream_fn.generic("S");
+    stream_fn.bound("S", "dbcc_transport::CanSource + Unpin");
+    stream_fn.arg("source", codegen::Type::new("S"));
+    stream_fn.ret(format!(
+        "impl Stream<Item = Result<{}, std::io::Error>>",
+        struct_name
+    ));
+    stream_fn.line("dbcc_transport::frames(source).filter_map(|frame| {");
+    stream_fn.line("    futures::future::ready(match frame {");
+    let matching_frame = match pgn {
+        Some(pgn) => {
+            let (mask, id) = j1939_id_filter(pgn);
+            format!(
+                "        Ok(frame) if frame.raw_id() & {:#X} == {:#X} => Some(Ok({}::new_with_id(frame.raw_id(), frame.data().to_vec()))),",
+                mask, id, struct_name
+            )
+        }
+        None => format!(
+            "        Ok(frame) if frame.raw_id() == {:#X} => Some(Ok({}::new(frame.data().to_vec()))),",
+            frame_id(message),
+            struct_name
+        ),
+    };
+    stream_fn.line(matching_frame);
+    stream_fn.line("        Ok(_) => None,");
+    stream_fn.line("        Err(e) => Some(Err(e)),");
+    stream_fn.line("    })");
+    stream_fn.line("})");
+    stream_fn
+}
+
+/// Value of a message attribute or its default.
+fn message_attribute<'a>(
+    dbc: &'a DBC,
+    message: &Message,
+    name: &str,
+) -> Option<&'a AttributeValue> {
+    dbc.attribute_values()
+        .iter()
+        .filter(|attribute| attribute.attribute_name() == name)
+        .find_map(|attribute| match attribute.attribute_value() {
+            AttributeValuedForObjectType::MessageDefinitionAttributeValue(message_id, value)
+                if message_id == message.message_id() =>
+            {
+                value.as_ref()
+            }
+            _ => None,
+        })
+        .or_else(|| {
+            dbc.attribute_defaults()
+                .iter()
+                .find(|default| default.attribute_name() == name)
+                .map(|default| default.attribute_value())
+        })
+}
+
+/// Cycle time in milliseconds of the `GenMsgCycleTime` attribute or its default,
+/// `None` if the message is not sent cyclically.
+fn message_cycle_time(dbc: &DBC, message: &Message) -> Option<u64> {
+    let cycle_time = match message_attribute(dbc, message, CYCLE_TIME_ATTRIBUTE)? {
+        AttributeValue::AttributeValueU64(millis) => *millis,
+        AttributeValue::AttributeValueI64(millis) => *millis as u64,
+        AttributeValue::AttributeValueF64(millis) => *millis as u64,
+        AttributeValue::AttributeValueCharString(_) => return None,
+    };
+    Some(cycle_time).filter(|millis| *millis > 0)
+}
+
+/// Parameter group number of a J1939 message, `None` for other messages.
+///
+/// Messages are J1939 parameter groups if their `VFrameFormat` is `J1939PG` or,
+/// for DBCs without frame formats, if the `ProtocolType` of the network is `J1939`.
+/// Only extended frames are parameter groups.
+fn j1939_pgn(dbc: &DBC, message: &Message) -> Option<u32> {
+    let message_id = message.message_id().0;
+    if message_id & !EFF_MASK == 0 && message_id <= SFF_MASK {
+        return None;
+    }
+
+    let frame_format = dbc
+        .attribute_definitions()
+        .iter()
+        .find_map(|definition| match definition {
+            AttributeDefinition::Message(definition)
+                if definition.starts_with(&format!("\"{}\"", FRAME_FORMAT_ATTRIBUTE)) =>
+            {
+                Some(definition)
+            }
+            _ => None,
+        });
+    let is_j1939 = match frame_format {
+        Some(definition) => {
+            // Enum attribute values are stored by index, defaults by name
+            let names: Vec<&str> = definition
+                .split_once("ENUM")
+                .map(|(_, names)| names)
+                .map(|names| {
+                    names
+                        .split(',')
+                        .map(|name| name.trim().trim_matches('"'))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let name = match message_attribute(dbc, message, FRAME_FORMAT_ATTRIBUTE) {
+                Some(AttributeValue::AttributeValueU64(index)) => {
+                    names.get(*index as usize).copied()
+                }
+                Some(AttributeValue::AttributeValueI64(index)) => {
+                    names.get(*index as usize).copied()
+                }
+                Some(AttributeValue::AttributeValueF64(index)) => {
+                    names.get(*index as usize).copied()
+                }
+                Some(AttributeValue::AttributeValueCharString(name)) => Some(name.as_str()),
+                None => None,
+            };
+            name == Some(J1939_FRAME_FORMAT)
+        }
+        None => dbc.attribute_values().iter().any(|attribute| {
+            attribute.attribute_name() == PROTOCOL_TYPE_ATTRIBUTE
+                && matches!(
+                    attribute.attribute_value(),
+                    AttributeValuedForObjectType::RawAttributeValue(
+                        AttributeValue::AttributeValueCharString(protocol)
+                    ) if protocol == J1939_PROTOCOL
+                )
+        }),
+    };
+    if !is_j1939 {
+        return None;
+    }
+
+    let pgn = (message_id & EFF_MASK) >> 8 & 0x3FFFF;
+    if j1939_is_pdu1(pgn) {
+        // The PDU specific byte is the destination address
+        Some(pgn & 0x3FF00)
+    } else {
+        Some(pgn)
+    }
+}
+
+/// PDU1 parameter groups (PDU format below 240) are sent to a destination address,
+/// PDU2 parameter groups are broadcast.
+fn j1939_is_pdu1(pgn: u32) -> bool {
+    (pgn >> 8) & 0xFF < 240
+}
+
+/// Mask and value matching the SocketCAN frame ids (with `CAN_EFF_FLAG`) of a parameter group
+/// with any priority, source address and, for PDU1, destination address.
+fn j1939_id_filter(pgn: u32) -> (u32, u32) {
+    let mask = if j1939_is_pdu1(pgn) {
+        0x03FF_0000
+    } else {
+        0x03FF_FF00
+    };
+    (EFF_FLAG | mask, EFF_FLAG | pgn << 8)
+}
+
+/// Cyclic transmission via the kernel broadcast manager, the default interval is
+/// the message cycle time.
+fn message_cyclic(dbc: &DBC, message: &Message) -> Vec<Function> {
+    let message_id = message.message_id().0;
+    let payload = format!("&self.frame_payload[..{}]", message.message_size());
+    // dbcc-transport's socket for all runtimes, tokio-socketcan-bcm only sets up RX filters
+    let socket_type = "&BcmSocket";
+    let mut fns = Vec::new();
+
+    if let Some(cycle_time) = message_cycle_time(dbc, message) {
+        let mut start_fn = codegen::Function::new("start_cyclic");
+        start_fn.doc(&format!(
+            "Send this message every {} ms (`GenMsgCycleTime`) via the kernel broadcast manager",
+            cycle_time
+        ));
+        start_fn.allow("dead_code");
+        start_fn.vis("pub");
+        start_fn.arg_ref_self();
+        start_fn.arg("socket", codegen::Type::new(socket_type));
+        start_fn.ret("std::io::Result<()>");
+        start_fn.line(format!(
+            "self.start_cyclic_with(socket, std::time::Duration::from_millis({}))",
+            cycle_time
+        ));
+        fns.push(start_fn);
+    }
+
+    let mut start_with_fn = codegen::Function::new("start_cyclic_with");
+    start_with_fn.doc("Send this message every `interval` via the kernel broadcast manager");
+    start_with_fn.allow("dead_code");
+    start_with_fn.vis("pub");
+    start_with_fn.arg_ref_self();
+    start_with_fn.arg("socket", codegen::Type::new(socket_type));
+    start_with_fn.arg("interval", codegen::Type::new("std::time::Duration"));
+    start_with_fn.ret("std::io::Result<()>");
+    start_with_fn.line(format!(
+        "socket.tx_setup({}, {}, interval)",
+        message_id, payload
+    ));
+    fns.push(start_with_fn);
+
+    let mut update_fn = codegen::Function::new("update_cyclic");
+    update_fn.doc("Replace the payload sent cyclically, keeping the interval");
+    update_fn.allow("dead_code");
+    update_fn.vis("pub");
+    update_fn.arg_ref_self();
+    update_fn.arg("socket", codegen::Type::new(socket_type));
+    update_fn.ret("std::io::Result<()>");
+    update_fn.line(format!("socket.tx_update({}, {})", message_id, payload));
+    fns.push(update_fn);
+
+    let mut stop_fn = codegen::Function::new("stop_cyclic");
+    stop_fn.doc("Stop sending this message cyclically");
+    stop_fn.allow("dead_code");
+    stop_fn.vis("pub");
+    stop_fn.arg_ref_self();
+    stop_fn.arg("socket", codegen::Type::new(socket_type));
+    stop_fn.ret("std::io::Result<()>");
+    stop_fn.line(format!("socket.tx_delete({})", message_id));
+    fns.push(stop_fn);
+
+    fns
+}
+
+/// Build a SocketCAN frame with the message id and a payload of the message length (DLC).
+/// J1939 messages use the identifier set by the builders.
+fn message_can_frame(message: &Message, pgn: Option<u32>) -> Function {
+    let mut frame_fn = codegen::Function::new("can_frame");
+    frame_fn.doc("SocketCAN frame containing this message");
+    frame_fn.allow("dead_code");
+    frame_fn.vis("pub");
+    frame_fn.arg_ref_self();
+    frame_fn.ret("std::io::Result<CANFrame>");
+
+    let message_id = message.message_id().0;
+    let extended = message_id & !EFF_MASK != 0;
+    let unsupported_id = "Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, \"socketcan can not build extended frames with ids below 0x800\"))";
+    // socketcan decides on the frame format based on the id
+    let id = if pgn.is_some() {
+        frame_fn.line(format!("if self.id <= {:#X} {{", SFF_MASK));
+        frame_fn.line(format!("    return {};", unsupported_id));
+        frame_fn.line("}");
+        "self.id".to_string()
+    } else if extended && message_id & EFF_MASK <= SFF_MASK {
+        frame_fn.line(unsupported_id);
+        return frame_fn;
+    } else {
+        format!("{:#X}", message_id & EFF_MASK)
+    };
+    frame_fn.line(format!(
+        "CANFrame::new({}, &self.frame_payload[..{}], false, false)",
+        id,
+        message.message_size()
+    ));
+    frame_fn.line(
+        "    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))",
+    );
+    frame_fn
+}
+
+/// Send the message via a raw SocketCAN socket.
+fn message_send() -> Function {
+    let mut send_fn = codegen::Function::new("send");
+    send_fn.doc("Send this message, retrying if the socket is busy");
+    send_fn.allow("dead_code");
+    send_fn.vis("pub");
+    send_fn.arg_ref_self();
+    send_fn.arg("socket", codegen::Type::new("&CANSocket"));
+    send_fn.ret("std::io::Result<()>");
+    send_fn.line("socket.write_frame_insist(&self.can_frame()?)");
+    send_fn
+}
+
+/// Send the message via a tokio-socketcan socket or an async dbcc-transport socket.
+fn message_send_async(runtime: Runtime) -> Function {
+    let mut send_fn = codegen::Function::new("send_async");
+    send_fn.doc("Send this message asynchronously");
+    send_fn.allow("dead_code");
+    send_fn.vis("pub");
+    send_fn.arg_ref_self();
+    let socket_type = match runtime {
+        Runtime::Compat => {
+            send_fn.arg("socket", codegen::Type::new("&tokio_socketcan::CANSocket"));
+            send_fn.ret("impl std::future::Future<Output = std::io::Result<()>>");
+            send_fn.line("let write = self.can_frame().map(|frame| socket.write_frame(frame));");
+            send_fn.line("async move { write?.compat().await }");
+            return send_fn;
+        }
+        Runtime::Tokio => "&'a dbcc_transport::tokio::CanSocket",
+        Runtime::AsyncStd => "&'a dbcc_transport::async_std::CanSocket",
+    };
+    send_fn.generic("'a");
+    send_fn.arg("socket", codegen::Type::new(socket_type));
+    send_fn.ret("impl std::future::Future<Output = std::io::Result<()>> + 'a");
+    send_fn.line("let frame = self.frame();");
+    send_fn.line("async move { socket.write_frame(&frame?).await }");
+    send_fn
+}
+
+/// Build a dbcc-transport frame with the message id and a payload of the message length (DLC).
+fn message_frame(message: &Message, pgn: Option<u32>) -> Function {
+    let mut frame_fn = codegen::Function::new("frame");
+    frame_fn.doc("dbcc-transport frame containing this message");
+    frame_fn.allow("dead_code");
+    frame_fn.vis("pub");
+    frame_fn.arg_ref_self();
+    frame_fn.ret("std::io::Result<dbcc_transport::Frame>");
+    let id = match pgn {
+        Some(_) => format!("self.id | {:#X}", EFF_FLAG),
+        None => message.message_id().0.to_string(),
+    };
+    frame_fn.line(format!(
+        "dbcc_transport::Frame::new({}, &self.frame_payload[..{}])",
+        id,
+        message.message_size()
+    ));
+    frame_fn
+}
+
+/// Send the message via any dbcc-transport transport, e.g. a virtual bus endpoint.
+fn message_send_to() -> Function {
+    let mut send_fn = codegen::Function::new("send_to");
+    send_fn.doc("Send this message via any `dbcc_transport::CanTransport`");
+    send_fn.allow("dead_code");
+    send_fn.vis("pub");
+    send_fn.generic("'a");
+    send_fn.generic("T");
+    send_fn.bound("T", "dbcc_transport::CanTransport + ?Sized");
+    send_fn.arg_ref_self();
+    send_fn.arg("transport", codegen::Type::new("&'a mut T"));
+    send_fn.ret("impl std::future::Future<Output = std::io::Result<()>> + 'a");
+    send_fn.line("let frame = self.frame();");
+    send_fn.line("async move { dbcc_transport::CanTransportExt::send(transport, frame?).await }");
+    send_fn
+}
+
+/// J1939 parameter group for the transport protocol, e.g. of messages longer than 8 bytes.
+fn message_parameter_group(message: &Message) -> Function {
+    let mut group_fn = codegen::Function::new("parameter_group");
+    group_fn.doc("J1939 parameter group containing this message, e.g. to send it via `dbcc_transport::j1939::TpTransport`");
+    group_fn.allow("dead_code");
+    group_fn.vis("pub");
+    group_fn.arg_ref_self();
+    group_fn.ret("dbcc_transport::j1939::ParameterGroup");
+    group_fn.line("dbcc_transport::j1939::ParameterGroup {");
+    group_fn.line("    id: dbcc_transport::j1939::Id::from_raw(self.id),");
+    group_fn.line(format!(
+        "    data: self.frame_payload[..{}].to_vec(),",
+        message.message_size()
+    ));
+    group_fn.line("}");
+    group_fn
+}
+
+/// Send and receive messages longer than 8 bytes via ISO-TP.
+/// The ids are those of the `IsoTp` connection, the `compat` runtime has no `CanTransport` to send with.
+fn message_isotp(opt: &DbccOpt, message: &Message) -> Vec<Function> {
+    let struct_name = opt.message_name(message).to_camel_case();
+    let const_name = opt.message_name(message).to_shouty_snake_case();
+    let limitations = format!(
+        "The ids of the `IsoTp` of `transport` are used, not `MESSAGE_ID_{}`.\n\
+         Only generated for the `tokio` and `async-std` runtimes, not for `compat`.",
+        const_name
+    );
+    let transport_type = "&'a mut dbcc_transport::isotp::IsoTpTransport<T>";
+    let bound = "dbcc_transport::CanTransport + Unpin";
+
+    let mut send_fn = codegen::Function::new("send_isotp");
+    send_fn.doc(&format!(
+        "Send this message via ISO-TP (ISO 15765-2).\n{}",
+        limitations
+    ));
+    send_fn.allow("dead_code");
+    send_fn.vis("pub");
+    send_fn.generic("'a");
+    send_fn.generic("T");
+    send_fn.bound("T", bound);
+    send_fn.arg_ref_self();
+    send_fn.arg("transport", codegen::Type::new(transport_type));
+    send_fn.ret("impl std::future::Future<Output = std::io::Result<()>> + 'a");
+    send_fn.line(format!(
+        "let payload = self.frame_payload[..{}].to_vec();",
+        message.message_size()
+    ));
+    send_fn.line("async move { transport.send(&payload).await }");
+
+    let mut recv_fn = codegen::Function::new("recv_isotp");
+    recv_fn.doc(&format!(
+        "Receive the next message via ISO-TP (ISO 15765-2).\n{}",
+        limitations
+    ));
+    recv_fn.allow("dead_code");
+    recv_fn.vis("pub");
+    recv_fn.generic("'a");
+    recv_fn.generic("T");
+    recv_fn.bound("T", bound);
+    recv_fn.arg("transport", codegen::Type::new(transport_type));
+    recv_fn.ret(format!(
+        "impl std::future::Future<Output = std::io::Result<{}>> + 'a",
+        struct_name
+    ));
+    recv_fn.line(format!(
+        "async move {{ transport.recv().await.map({}::new) }}",
+        struct_name
+    ));
+
+    vec![send_fn, recv_fn]
+}
+
+/// `embedded_can` id type and raw id of a message, DBC ids above the standard range
+/// or with the extended flag are extended ids.
+fn embedded_can_id(message: &Message) -> (&'static str, u32) {
+    let message_id = message.message_id().0;
+    if message_id & !EFF_MASK != 0 || message_id > SFF_MASK {
+        ("ExtendedId", message_id & EFF_MASK)
+    } else {
+        ("StandardId", message_id)
+    }
+}
+
+/// Build a frame of any embedded-can driver.
+fn message_into_frame(message: &Message, pgn: Option<u32>) -> Function {
+    let (id_type, id) = embedded_can_id(message);
+    let id = match pgn {
+        Some(_) => "self.id".to_string(),
+        None => format!("{:#X}", id),
+    };
+    let mut frame_fn = codegen::Function::new("into_frame");
+    frame_fn.doc("embedded-can frame containing this message, `None` if the frame type rejects it");
+    frame_fn.allow("dead_code");
+    frame_fn.vis("pub");
+    frame_fn.generic("F");
+    frame_fn.bound("F", "embedded_can::Frame");
+    frame_fn.arg_self();
+    frame_fn.ret("Option<F>");
+    frame_fn.line(format!(
+        "F::new(embedded_can::{}::new({})?, &self.frame_payload[..{}])",
+        id_type,
+        id,
+        message.message_size()
+    ));
+    frame_fn
+}
+
+/// Decode a frame of any embedded-can driver, data frames with other ids are rejected.
+/// J1939 messages accept any priority, source and destination address.
+/// A blanket `impl<F: Frame> TryFrom<&F>` would conflict with core's `TryFrom<U> for T where U: Into<T>`,
+/// this is an inherent function instead.
+fn message_try_from_frame(opt: &DbccOpt, message: &Message, pgn: Option<u32>) -> Function {
+    let struct_name = opt.message_name(message).to_camel_case();
+    let (id_type, id) = embedded_can_id(message);
+    let id_variant = id_type.trim_end_matches("Id");
+
+    let mut try_from_fn = codegen::Function::new("try_from_frame");
+    try_from_fn.doc(
+        "Read this message from an embedded-can frame, fails with the frame id if the frame is not a data frame of this message",
+    );
+    try_from_fn.allow("dead_code");
+    try_from_fn.vis("pub");
+    try_from_fn.generic("F");
+    try_from_fn.bound("F", "embedded_can::Frame");
+    try_from_fn.arg("frame", codegen::Type::new("&F"));
+    try_from_fn.ret("Result<Self, embedded_can::Id>");
+    try_from_fn.line("match frame.id() {");
+    let matching_frame = match pgn {
+        Some(pgn) => {
+            let (mask, id) = j1939_id_filter(pgn);
+            format!(
+                "    embedded_can::Id::Extended(id) if id.as_raw() & {:#X} == {:#X} && frame.is_data_frame() => Ok({}::new_with_id(id.as_raw(), frame.data().to_vec())),",
+                mask & EFF_MASK, id & EFF_MASK, struct_name
+            )
+        }
+        None => format!(
+            "    embedded_can::Id::{}(id) if id.as_raw() == {:#X} && frame.is_data_frame() => Ok({}::new(frame.data().to_vec())),",
+            id_variant, id, struct_name
+        ),
+    };
+    try_from_fn.line(matching_frame);
+    try_from_fn.line("    id => Err(id),");
+    try_from_fn.line("}");
+    try_from_fn
+}
+
+/// Code generated from a DBC.
+#[derive(Debug)]
+pub struct Generated {
+    pub scope: Scope,
+    /// Warnings about names that had to be changed to be valid Rust
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// File of a generated module directory.
+#[derive(Debug)]
+pub struct GeneratedFile {
+    /// File name within the module directory e.g. `mod.rs`
+    pub name: String,
+    pub scope: Scope,
+}
+
+/// Module directory generated from a DBC, see `can_code_gen_files`.
+#[derive(Debug)]
+pub struct GeneratedFiles {
+    pub files: Vec<GeneratedFile>,
+    /// Warnings about names that had to be changed to be valid Rust
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl GeneratedFiles {
+    /// Write all files to `dir`, creating it if necessary.
+    /// Files are only rewritten if their content changed.
+    /// Files of messages that are no longer generated are not removed.
+    pub fn write_to<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        for file in &self.files {
+            write_if_changed(&dir.join(&file.name), &file.scope.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `content` to `path` unless the file already has exactly this content.
+pub(crate) fn write_if_changed(path: &Path, content: &str) -> io::Result<()> {
+    let unchanged = fs::read(path)
+        .map(|existing| existing == content.as_bytes())
+        .unwrap_or(false);
+    if !unchanged {
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Genérate code for reading CAN signals
+///
+/// Example:
+/// ```
+/// use dbcc::{can_code_gen, dbc_hash, DbccOpt};
+///
+/// use std::fs;
+/// use std::io;
+///
+/// fn main() -> io::Result<()> {
+///    # std::env::set_current_dir("..")?;
+///    let buffer = fs::read("./examples/j1939.dbc")?;
+///    let dbc_content = can_dbc::DBC::from_slice(&buffer).expect("Failed to parse DBC file");
+///    let opt = DbccOpt { with_tokio: true, ..Default::default() };
+///    let generated = can_code_gen(&opt, &dbc_content, "j1939.dbc", &dbc_hash(&buffer)).expect("Failed to generate rust code");
+///    for diagnostic in &generated.diagnostics {
+///        eprintln!("{}", diagnostic);
+///    }
+///    println!("{}", generated.scope.to_string());
+///    Ok(())
+/// }
+///```
+///
+/// See `Builder` for generating code from a `build.rs`.
+pub fn can_code_gen(
+    opt: &DbccOpt,
+    dbc: &DBC,
+    file_name: &str,
+    file_hash: &str,
+) -> Result<Generated> {
+    let mut scope = Scope::new();
+
+    push_header(&mut scope, dbc, file_name, file_hash);
+    let mut imports = Imports::default();
+    imports.add("byteorder", "{ByteOrder, BE, LE}");
+    push_socket_imports(&mut imports, opt);
+    imports.push_to(&mut scope, opt.message_features);
+
+    let messages = filtered_messages(opt, dbc);
+    for message in &messages {
+        scope.raw(&message_const(opt, dbc, message));
+    }
+
+    let mut diagnostics = push_signal_enums(&mut scope, opt, dbc)?;
+
+    for message in &messages {
+        push_message(&mut scope, opt, dbc, message)?;
+    }
+
+    if let Some(runtime) = opt.stream_runtime() {
+        diagnostics.extend(push_message_enum(&mut scope, opt, dbc, runtime, &messages)?);
+    }
+
+    Ok(Generated { scope, diagnostics })
+}
+
+/// Generate a module directory instead of a single file.
+///
+/// `mod.rs` contains the message ids and signal enums and re-exports the
+/// message structs, which are split over files according to `split_by`.
+/// The public API is identical to the single file generated by `can_code_gen`.
+///
+/// Example:
+/// ```no_run
+/// use dbcc::{can_code_gen_files, dbc_hash, DbccOpt, SplitBy};
+///
+/// let buffer = std::fs::read("./examples/j1939.dbc").unwrap();
+/// let dbc = can_dbc::DBC::from_slice(&buffer).unwrap();
+/// let opt = DbccOpt::default();
+/// let generated = can_code_gen_files(&opt, &dbc, "j1939.dbc", &dbc_hash(&buffer), SplitBy::Message)
+///     .expect("Failed to generate rust code");
+/// generated.write_to("src/j1939").unwrap();
+/// ```
+pub fn can_code_gen_files(
+    opt: &DbccOpt,
+    dbc: &DBC,
+    file_name: &str,
+    file_hash: &str,
+    split_by: SplitBy,
+) -> Result<GeneratedFiles> {
+    let messages = filtered_messages(opt, dbc);
+
+    // Module name -> messages in that module, in DBC order
+    let mut modules: Vec<(String, Vec<&Message>)> = Vec::new();
+    for message in &messages {
+        let mut module_name = match split_by {
+            SplitBy::Message => opt.message_name(message).to_snake_case(),
+            SplitBy::Node => match message.transmitter() {
+                Transmitter::NodeName(node) => node.to_snake_case(),
+                Transmitter::VectorXXX => "vector_xxx".to_string(),
+            },
+        };
+        // These can not be used as raw identifiers
+        if ["crate", "self", "super", "mod"].contains(&module_name.as_str()) {
+            module_name.push('_');
+        }
+        match modules.iter_mut().find(|(name, _)| *name == module_name) {
+            Some((_, module_messages)) => module_messages.push(message),
+            None => modules.push((module_name, vec![message])),
+        }
+    }
+
+    let mut mod_scope = Scope::new();
+    push_header(&mut mod_scope, dbc, file_name, file_hash);
+    let mut module_decls = String::new();
+    for (module_name, module_messages) in &modules {
+        let module_ident = module_ident(module_name);
+        if opt.message_features {
+            // Gate the module if it only contains a single message, otherwise each re-export
+            if let [message] = module_messages.as_slice() {
+                if let Some(cfg) = opt.message_cfg(message) {
+                    writeln!(&mut module_decls, "{}", cfg)?;
+                }
+            }
+            writeln!(&mut module_decls, "mod {};", module_ident)?;
+            for message in module_messages {
+                if let Some(cfg) = opt.message_cfg(message) {
+                    writeln!(&mut module_decls, "{}", cfg)?;
+                }
+                writeln!(
+                    &mut module_decls,
+                    "#[allow(unused_imports)]\npub use self::{}::{};",
+                    module_ident,
+                    opt.message_name(message).to_camel_case()
+                )?;
+            }
+            continue;
+        }
+
+        let struct_names: Vec<String> = module_messages
+            .iter()
+            .map(|message| opt.message_name(message).to_camel_case())
+            .collect();
+        let reexport = if struct_names.len() == 1 {
+            struct_names[0].clone()
+        } else {
+            format!("{{{}}}", struct_names.join(", "))
+        };
+        writeln!(
+            &mut module_decls,
+            "mod {};\n#[allow(unused_imports)]\npub use self::{}::{};",
+            module_ident, module_ident, reexport
+        )?;
+    }
+    mod_scope.raw(module_decls.trim_end());
+    for message in &messages {
+        mod_scope.raw(&message_const(opt, dbc, message));
+    }
+    let mut diagnostics = push_signal_enums(&mut mod_scope, opt, dbc)?;
+    if let Some(runtime) = opt.stream_runtime() {
+        diagnostics.extend(push_message_enum(
+            &mut mod_scope,
+            opt,
+            dbc,
+            runtime,
+            &messages,
+        )?);
+    }
+
+    let mut files = vec![GeneratedFile {
+        name: "mod.rs".to_string(),
+        scope: mod_scope,
+    }];
+    for (module_name, module_messages) in modules {
+        let mut scope = Scope::new();
+        push_header(&mut scope, dbc, file_name, file_hash);
+        push_message_imports(&mut scope, opt, dbc, &module_messages);
+        for message in module_messages {
+            push_message(&mut scope, opt, dbc, message)?;
+        }
+        files.push(GeneratedFile {
+            name: format!("{}.rs", module_name),
+            scope,
+        });
+    }
+
+    Ok(GeneratedFiles { files, diagnostics })
+}
+
+/// Frame id as reported by SocketCAN, with `CAN_EFF_FLAG` for all extended frames.
+fn frame_id(message: &Message) -> u32 {
+    let message_id = message.message_id().0;
+    if message_id & !EFF_MASK != 0 || message_id > SFF_MASK {
+        (message_id & EFF_MASK) | EFF_FLAG
+    } else {
+        message_id
+    }
+}
+
+/// `cfg` attribute of a `Message::decode` arm matching `key`, an id or PGN.
+/// Earlier arms of the same key take precedence, so the arm is only enabled if their
+/// message features are disabled. `None` if an earlier arm is always enabled.
+fn decode_arm_cfg<K: Ord>(
+    opt: &DbccOpt,
+    message: &Message,
+    key: K,
+    earlier: &mut BTreeMap<K, Option<Vec<String>>>,
+) -> Option<Option<String>> {
+    let features = earlier.entry(key).or_insert_with(|| Some(Vec::new()));
+    let earlier_features = features.as_mut()?;
+    let mut conditions = Vec::new();
+    if opt.message_features {
+        conditions.push(format!("feature = \"{}\"", message_feature(opt, message)));
+    }
+    if !earlier_features.is_empty() {
+        let any: Vec<String> = earlier_features
+            .iter()
+            .map(|feature| format!("feature = \"{}\"", feature))
+            .collect();
+        conditions.push(format!("not(any({}))", any.join(", ")));
+    }
+    if opt.message_features {
+        earlier_features.push(message_feature(opt, message));
+    } else {
+        *features = None;
+    }
+    Some(match conditions.len() {
+        0 => None,
+        1 => Some(format!("#[cfg({})]", conditions[0])),
+        _ => Some(format!("#[cfg(all({}))]", conditions.join(", "))),
+    })
+}
+
+/// `Message` enum of all generated messages, `stream_messages` receiving any
+/// number of messages via a single BCM socket and `stream_messages_from` any `CanSource`.
+/// Paths are fully qualified since `mod.rs` of a module directory has no imports,
+/// `::std` as a message module may be named `std`.
+fn push_message_enum(
+    scope: &mut Scope,
+    opt: &DbccOpt,
+    dbc: &DBC,
+    runtime: Runtime,
+    messages: &[&Message],
+) -> Result<Vec<Diagnostic>> {
+    for message in messages {
+        let struct_name = opt.message_name(message).to_camel_case();
+        if struct_name == "Message" || struct_name == "Unknown" {
+            return Err(CodegenError::ReservedMessageName {
+                message_id: *message.message_id(),
+                name: struct_name,
+            });
+        }
+    }
+
+    let mut code = String::new();
+    writeln!(
+        &mut code,
+        "/// Message decoded by `Message::decode` or `stream_messages`"
+    )?;
+    let derives: Vec<&str> = opt
+        .derive
+        .messages
+        .iter()
+        .chain(opt.type_derives("Message"))
+        .map(String::as_str)
+        .collect();
+    if !derives.is_empty() {
+        writeln!(&mut code, "#[derive({})]", derives.join(", "))?;
+    }
+    writeln!(&mut code, "#[allow(dead_code)]")?;
+    writeln!(&mut code, "pub enum Message {{")?;
+    for message in messages {
+        if let Some(cfg) = opt.message_cfg(message) {
+            writeln!(&mut code, "    {}", cfg)?;
+        }
+        let struct_name = opt.message_name(message).to_camel_case();
+        writeln!(&mut code, "    {}({}),", struct_name, struct_name)?;
+    }
+    writeln!(
+        &mut code,
+        "    /// Frame with an id no message is generated for"
+    )?;
+    writeln!(&mut code, "    Unknown {{ id: u32, data: Vec<u8> }},")?;
+    writeln!(&mut code, "}}")?;
+    writeln!(&mut code)?;
+
+    writeln!(&mut code, "impl Message {{")?;
+    writeln!(
+        &mut code,
+        "    /// Decode a frame, `id` has bit 31 (`CAN_EFF_FLAG`) set for extended frames."
+    )?;
+    writeln!(
+        &mut code,
+        "    /// J1939 messages sent with other priorities or addresses are matched by their PGN."
+    )?;
+    writeln!(&mut code, "    #[allow(dead_code)]")?;
+    writeln!(
+        &mut code,
+        "    pub fn decode(id: u32, data: &[u8]) -> Message {{"
+    )?;
+    writeln!(&mut code, "        match id {{")?;
+    let mut diagnostics = Vec::new();
+    let mut id_arms = BTreeMap::new();
+    for message in messages {
+        let cfg = match decode_arm_cfg(opt, message, frame_id(message), &mut id_arms) {
+            Some(cfg) => cfg,
+            None => {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    kind: DiagnosticKind::DuplicateMessageId,
+                    location: Location {
+                        message_id: Some(message.message_id().0),
+                        ..Default::default()
+                    },
+                    message: format!(
+                        "`Message::decode` decodes the id of `{}` as an earlier message of the same id",
+                        message.message_name()
+                    ),
+                });
+                continue;
+            }
+        };
+        if let Some(cfg) = cfg {
+            writeln!(&mut code, "            {}", cfg)?;
+        }
+        writeln!(
+            &mut code,
+            "            {:#X} => Message::{}({}::new(data.to_vec())),",
+            frame_id(message),
+            opt.message_name(message).to_camel_case(),
+            opt.message_name(message).to_camel_case()
+        )?;
+    }
+    // After the exact ids, messages of the same PGN from different source addresses keep their own variant.
+    // Other addresses decode to the first enabled message of a PGN.
+    let mut pgn_arms = BTreeMap::new();
+    for message in messages {
+        if let Some(pgn) = j1939_pgn(dbc, message) {
+            match decode_arm_cfg(opt, message, pgn, &mut pgn_arms) {
+                Some(Some(cfg)) => writeln!(&mut code, "            {}", cfg)?,
+                Some(None) => {}
+                None => continue,
+            }
+            let (mask, pgn_id) = j1939_id_filter(pgn);
+            writeln!(
+                &mut code,
+                "            id if id & {:#X} == {:#X} => Message::{}({}::new_with_id(id, data.to_vec())),",
+                mask,
+                pgn_id,
+                opt.message_name(message).to_camel_case(),
+                opt.message_name(message).to_camel_case()
+            )?;
+        }
+    }
+    writeln!(
+        &mut code,
+        "            _ => Message::Unknown {{ id, data: data.to_vec() }},"
+    )?;
+    writeln!(&mut code, "        }}")?;
+    writeln!(&mut code, "    }}")?;
+    writeln!(&mut code, "}}")?;
+    writeln!(&mut code)?;
+
+    writeln!(
+        &mut code,
+        "/// Receive the messages with `message_ids` (e.g. `MESSAGE_ID_OEL`) via a single BCM socket.
+/// Frames of ids no message is generated for are yielded as `Message::Unknown` if `with_unknown` is set."
+    )?;
+    writeln!(&mut code, "#[allow(dead_code)]")?;
+    writeln!(&mut code, "pub fn stream_messages(can_interface: &str, message_ids: &[u32], ival1: &::std::time::Duration, ival2: &::std::time::Duration, with_unknown: bool) -> ::std::io::Result<impl futures::stream::Stream<Item = Result<Message, ::std::io::Error>>> {{")?;
+    match runtime {
+        Runtime::Compat => {
+            writeln!(
+                &mut code,
+                "    let socket = tokio_socketcan_bcm::BCMSocket::open_nb(can_interface)?;
+    for message_id in message_ids {{
+        let extended = message_id & 0x8000_0000 != 0 || *message_id > 0x7FF;
+        let message_id = if extended {{
+            tokio_socketcan_bcm::CANMessageId::EFF(message_id & 0x1FFF_FFFF)
+        }} else {{
+            tokio_socketcan_bcm::CANMessageId::SFF(*message_id as u16)
+        }};
+        socket.filter_id(message_id, *ival1, *ival2)?;
+    }}
+    let frames = futures_util::compat::Stream01CompatExt::compat(socket.incoming_frames());
+    let messages = futures::stream::StreamExt::map(frames, |frame| {{
+        frame.map(|frame| {{
+            let flag = if frame.is_extended() {{ 0x8000_0000 }} else {{ 0 }};
+            Message::decode(frame.id() | flag, frame.data())
+        }})
+    }});"
+            )?;
+        }
+        Runtime::Tokio | Runtime::AsyncStd => {
+            let module = if runtime == Runtime::Tokio {
+                "tokio"
+            } else {
+                "async_std"
+            };
+            writeln!(
+                &mut code,
+                "    let socket = dbcc_transport::bcm::BcmSocket::open(can_interface)?;
+    for message_id in message_ids {{
+        socket.rx_setup(*message_id, *ival1, *ival2)?;
+    }}
+    let frames = dbcc_transport::{}::BcmStream::new(socket)?;
+    let messages = futures::stream::StreamExt::map(frames, |frame| {{
+        frame.map(|frame| Message::decode(frame.raw_id(), frame.data()))
+    }});",
+                module
+            )?;
+        }
+    }
+    writeln!(&mut code, "    Ok(filter_unknown(messages, with_unknown))")?;
+    writeln!(&mut code, "}}")?;
+
+    if runtime != Runtime::Compat {
+        writeln!(&mut code)?;
+        writeln!(
+            &mut code,
+            "/// Decode the frames received by `source`, see `stream_messages`.
+#[allow(dead_code)]
+pub fn stream_messages_from<S>(source: S, with_unknown: bool) -> impl futures::stream::Stream<Item = Result<Message, ::std::io::Error>>
+where S: dbcc_transport::CanSource + Unpin,
+{{
+    let messages = futures::stream::StreamExt::map(dbcc_transport::frames(source), |frame| {{
+        frame.map(|frame| Message::decode(frame.raw_id(), frame.data()))
+    }});
+    filter_unknown(messages, with_unknown)
+}}"
+        )?;
+    }
+
+    writeln!(&mut code)?;
+    write!(
+        &mut code,
+        "fn filter_unknown(messages: impl futures::stream::Stream<Item = Result<Message, ::std::io::Error>>, with_unknown: bool) -> impl futures::stream::Stream<Item = Result<Message, ::std::io::Error>> {{
+    futures::stream::StreamExt::filter(messages, move |message| {{
+        futures::future::ready(with_unknown || !matches!(message, Ok(Message::Unknown {{ .. }})))
+    }})
+}}"
+    )?;
+
+    scope.raw(&code);
+    Ok(diagnostics)
+}
+
+/// Cargo feature gating the code of a message if `message_features` is enabled,
+/// e.g. `msg_eec1`.
+pub fn message_feature(opt: &DbccOpt, message: &Message) -> String {
+    format!("msg_{}", opt.message_name(message).to_snake_case())
+}
+
+/// `[features]` table for `Cargo.toml` with one feature per message and an
+/// `all_messages` feature enabling all of them, see `DbccOpt::message_features`.
+///
+/// Example:
+/// ```
+/// use dbcc::{cargo_features, DbccOpt};
+///
+/// # std::env::set_current_dir("..").unwrap();
+/// let buffer = std::fs::read("./examples/j1939.dbc").unwrap();
+/// let dbc = can_dbc::DBC::from_slice(&buffer).unwrap();
+/// let opt = DbccOpt { message_features: true, ..Default::default() };
+/// assert!(cargo_features(&opt, &dbc).contains("msg_oel = []"));
+/// ```
+pub fn cargo_features(opt: &DbccOpt, dbc: &DBC) -> String {
+    let features: Vec<String> = filtered_messages(opt, dbc)
+        .iter()
+        .map(|message| message_feature(opt, message))
+        .collect();
+    let mut table = String::from("[features]\nall_messages = [\n");
+    for feature in &features {
+        table.push_str(&format!("    \"{}\",\n", feature));
+    }
+    table.push_str("]\n");
+    for feature in &features {
+        table.push_str(&format!("{} = []\n", feature));
+    }
+    table
+}
+
+/// Module name as identifier, keywords are escaped as raw identifiers e.g. `r#as`.
+fn module_ident(module_name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let",
+        "loop", "macro", "match", "move", "mut", "override", "priv", "pub", "ref", "return",
+        "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+        "virtual", "where", "while", "yield",
+    ];
+    if KEYWORDS.contains(&module_name) {
+        format!("r#{}", module_name)
+    } else {
+        module_name.to_string()
+    }
+}
+
+fn push_header(scope: &mut Scope, dbc: &DBC, file_name: &str, file_hash: &str) {
+    scope.raw(&format!(
+        "// Generated based on\n// File Name: {}\n// DBC Version: {}\n// {}",
+        file_name,
+        dbc.version().0,
+        file_hash
+    ));
+}
+
+fn push_socket_imports(imports: &mut Imports, opt: &DbccOpt) {
+    let runtime = opt.stream_runtime();
+    match runtime {
+        Some(Runtime::Compat) => {
+            imports.add("futures::stream", "Stream");
+            imports.add("futures_util::compat", "Stream01CompatExt");
+            imports.add("futures_util::stream", "StreamExt");
+            imports.add("tokio_socketcan_bcm", "CANMessageId");
+            imports.add("tokio_socketcan_bcm", "BCMSocket");
+        }
+        Some(Runtime::Tokio) => {
+            imports.add("futures::stream", "{Stream, StreamExt}");
+            imports.add("dbcc_transport::tokio", "BcmStream");
+        }
+        Some(Runtime::AsyncStd) => {
+            imports.add("futures::stream", "{Stream, StreamExt}");
+            imports.add("dbcc_transport::async_std", "BcmStream");
+        }
+        None => (),
+    }
+    if opt.with_cyclic {
+        imports.add("dbcc_transport::bcm", "BcmSocket");
+    }
+    if opt.with_socketcan {
+        imports.add("socketcan", "{CANFrame, CANSocket}");
+        if runtime == Some(Runtime::Compat) {
+            imports.add("futures_util::compat", "Future01CompatExt");
+        }
+    }
+}
+
+/// Imports of a file containing only `messages`, in multi file mode.
+fn push_message_imports(scope: &mut Scope, opt: &DbccOpt, dbc: &DBC, messages: &[&Message]) {
+    let mut imports = Imports::default();
+    let signals = messages.iter().flat_map(|message| {
+        message
+            .signals()
+            .iter()
+            .map(move |signal| (message, signal))
+    });
+    for (message, signal) in signals {
+        imports.add("byteorder", "ByteOrder");
+        match signal.byte_order() {
+            ByteOrder::LittleEndian => imports.add("byteorder", "LE"),
+            ByteOrder::BigEndian => imports.add("byteorder", "BE"),
+        };
+        if dbc
+            .value_descriptions_for_signal(*message.message_id(), signal.name())
+            .is_some()
+        {
+            let enum_name = to_enum_name(*message.message_id(), opt.signal_name(message, signal));
+            imports.add_gated("super", &enum_name, opt.message_cfg(message));
+        }
+    }
+    push_socket_imports(&mut imports, opt);
+    imports.push_to(scope, opt.message_features);
+}
+
+fn filtered_messages<'a>(opt: &DbccOpt, dbc: &'a DBC) -> Vec<&'a Message> {
+    dbc.messages()
+        .iter()
+        .filter(|message| opt.messages.matches(message.message_name()))
+        .collect()
+}
+
+/// Push the value description enums of all included messages.
+fn push_signal_enums(scope: &mut Scope, opt: &DbccOpt, dbc: &DBC) -> Result<Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    for value_description in dbc.value_descriptions() {
+        let (enum_name, message) = match value_description {
+            ValueDescription::Signal {
+                message_id,
+                signal_name,
+                ..
+            } => {
+                let message = message_by_id(dbc, *message_id);
+                let excluded = message
+                    .map(|message| !opt.messages.matches(message.message_name()))
+                    .unwrap_or(false);
+                if excluded {
+                    continue;
+                }
+                let enum_name = value_description_enum_name(opt, dbc, *message_id, signal_name);
+                (enum_name, message)
+            }
+            ValueDescription::EnvironmentVariable { .. } => continue,
+        };
+
+        diagnostics.extend(value_description_diagnostics(value_description));
+
+        let cfg: Vec<String> = message
+            .and_then(|message| opt.message_cfg(message))
+            .into_iter()
+            .collect();
+        if let Some(signal_enum) = signal_enum(opt, dbc, value_description) {
+            let mut attributes = cfg.clone();
+            attributes.extend(opt.type_attributes(&opt.attributes.enums, &enum_name));
+            push_enum(scope, signal_enum, &attributes);
+        }
+
+        if let Some(enum_impl) = signal_enum_impl_from(opt, dbc, value_description)? {
+            push_impl(scope, enum_impl, &cfg);
+        }
+    }
+    Ok(diagnostics)
+}
+
+/// Push the struct and impl of a message.
+fn push_message(scope: &mut Scope, opt: &DbccOpt, dbc: &DBC, message: &Message) -> Result<()> {
+    let struct_name = opt.message_name(message).to_camel_case();
+    let cfg: Vec<String> = opt.message_cfg(message).into_iter().collect();
+    let mut attributes = cfg.clone();
+    attributes.extend(opt.type_attributes(&opt.attributes.messages, &struct_name));
+    push_struct(scope, message_struct(opt, dbc, message), &attributes);
+    push_impl(scope, message_impl(opt, dbc, message)?, &cfg);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// J1939 DBC with `Ccvs1` and `Ccvs2` sharing a PGN and `Same1` and `Same2` sharing an id
+    const SHARED_IDS: &str = r#"VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU
+
+BO_ 2566844672 Ccvs1: 8 ECU
+ SG_ Speed : 8|16@1+ (0.00390625,0) [0|250.996] "km/h" ECU
+
+BO_ 2566844673 Ccvs2: 8 ECU
+ SG_ Speed : 8|16@1+ (0.00390625,0) [0|250.996] "km/h" ECU
+
+BO_ 256 Same1: 8 ECU
+ SG_ Value : 0|8@1+ (1,0) [0|0] "" ECU
+
+BO_ 256 Same2: 8 ECU
+ SG_ Value : 0|8@1+ (1,0) [0|0] "" ECU
+
+BA_DEF_  "ProtocolType" STRING ;
+BA_ "ProtocolType" "J1939";
+"#;
+
+    fn generate(opt: &DbccOpt) -> (String, Vec<Diagnostic>) {
+        let dbc = DBC::from_slice(SHARED_IDS.as_bytes()).unwrap();
+        let generated = can_code_gen(opt, &dbc, "shared.dbc", "").unwrap();
+        (generated.scope.to_string(), generated.diagnostics)
+    }
+
+    #[test]
+    fn decode_arms_without_features() {
+        let opt = DbccOpt {
+            with_tokio: true,
+            ..Default::default()
+        };
+        let (code, diagnostics) = generate(&opt);
+        assert_eq!(code.matches("0x100 => Message::").count(), 1);
+        assert_eq!(code.matches("id if id & ").count(), 1);
+        assert!(code.contains("id if id & 0x83FFFF00 == 0x80FEF100 => Message::Ccvs1("));
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.kind, d.location.message_id))
+                .collect::<Vec<_>>(),
+            vec![(DiagnosticKind::DuplicateMessageId, Some(256))]
+        );
+    }
+
+    #[test]
+    fn decode_arms_with_features() {
+        let opt = DbccOpt {
+            with_tokio: true,
+            message_features: true,
+            ..Default::default()
+        };
+        let (code, diagnostics) = generate(&opt);
+        assert!(diagnostics.is_empty());
+        // Later arms of the same id or PGN are only enabled without the earlier messages
+        assert!(code.contains(
+            "#[cfg(all(feature = \"msg_same2\", not(any(feature = \"msg_same1\"))))]\n            0x100 => Message::Same2("
+        ));
+        assert!(code.contains(
+            "#[cfg(all(feature = \"msg_ccvs2\", not(any(feature = \"msg_ccvs1\"))))]\n            id if id & 0x83FFFF00 == 0x80FEF100 => Message::Ccvs2("
+        ));
+    }
+
+    #[test]
+    fn message_consts_with_features() {
+        let opt = DbccOpt {
+            message_features: true,
+            ..Default::default()
+        };
+        let (code, _) = generate(&opt);
+        assert!(code.contains(
+            "#[cfg(feature = \"msg_ccvs1\")]\n#[allow(dead_code)]\npub const MESSAGE_ID_CCVS1: u32"
+        ));
+        assert!(code.contains(
+            "#[cfg(feature = \"msg_ccvs1\")]\n#[allow(dead_code)]\npub const PGN_CCVS1: u32"
+        ));
+    }
+
+    /// Messages named like the `Message` enum, its `Unknown` variant and the `std` crate
+    const NAMES: &str = r#"VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU
+
+BO_ 100 std: 8 ECU
+ SG_ io : 0|8@1+ (1,0) [0|255] "" ECU
+
+BO_ 101 Message: 8 ECU
+ SG_ Value : 0|8@1+ (1,0) [0|255] "" ECU
+
+BO_ 102 Unknown: 8 ECU
+ SG_ Value : 0|8@1+ (1,0) [0|255] "" ECU
+"#;
+
+    #[test]
+    fn reserved_message_names() {
+        let dbc = DBC::from_slice(NAMES.as_bytes()).unwrap();
+        let opt = DbccOpt {
+            with_tokio: true,
+            ..Default::default()
+        };
+        match can_code_gen(&opt, &dbc, "names.dbc", "") {
+            Err(CodegenError::ReservedMessageName { message_id, name }) => {
+                assert_eq!((message_id.0, name.as_str()), (101, "Message"))
+            }
+            result => panic!(
+                "expected a reserved name error, got {:?}",
+                result.map(|_| ())
+            ),
+        }
+
+        let mut opt = DbccOpt {
+            with_tokio: true,
+            ..Default::default()
+        };
+        opt.rename
+            .messages
+            .insert("Message".to_string(), "Msg".to_string());
+        let error = can_code_gen_files(&opt, &dbc, "names.dbc", "", SplitBy::Message)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Message 102: the name `Unknown` is used by the generated `Message` enum, rename the message in `[rename.messages]`"
+        );
+
+        opt.rename
+            .messages
+            .insert("Unknown".to_string(), "Unrecognized".to_string());
+        let code = can_code_gen(&opt, &dbc, "names.dbc", "")
+            .unwrap()
+            .scope
+            .to_string();
+        assert!(code.contains("    Msg(Msg),\n    Unrecognized(Unrecognized),\n"));
+
+        // Without streams there is no `Message` enum
+        let code = can_code_gen(&DbccOpt::default(), &dbc, "names.dbc", "")
+            .unwrap()
+            .scope
+            .to_string();
+        assert!(code.contains("pub struct Message {"));
+    }
+
+    #[test]
+    fn std_message_module() {
+        let dbc = DBC::from_slice(NAMES.as_bytes()).unwrap();
+        let mut opt = DbccOpt {
+            with_tokio: true,
+            ..Default::default()
+        };
+        opt.rename
+            .messages
+            .insert("Message".to_string(), "Msg".to_string());
+        opt.rename
+            .messages
+            .insert("Unknown".to_string(), "Unrecognized".to_string());
+        let generated = can_code_gen_files(&opt, &dbc, "names.dbc", "", SplitBy::Message).unwrap();
+        let files: Vec<&str> = generated
+            .files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect();
+        assert_eq!(files, ["mod.rs", "std.rs", "msg.rs", "unrecognized.rs"]);
+
+        // `std::` would resolve to the `std` message module in `mod.rs`
+        let module = generated.files[0].scope.to_string();
+        assert!(module.contains("mod std;\n#[allow(unused_imports)]\npub use self::std::Std;"));
+        let mut rest = module.as_str();
+        while let Some(i) = rest.find("std::") {
+            assert!(
+                rest[..i].ends_with("::"),
+                "unqualified std path in {}",
+                &rest[i.saturating_sub(40)..]
+            );
+            rest = &rest[i + 5..];
+        }
+    }
+
+    #[test]
+    fn isotp_functions() {
+        let dbc = DBC::from_slice(
+            b"VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\nBO_ 2016 DiagRequest: 20 ECU\n SG_ Service : 0|8@1+ (1,0) [0|255] \"\" ECU\n",
+        )
+        .unwrap();
+        let opt = DbccOpt {
+            runtime: Some(Runtime::Tokio),
+            ..Default::default()
+        };
+        let code = can_code_gen(&opt, &dbc, "diag.dbc", "").unwrap().scope.to_string();
+        assert!(code.contains("pub fn send_isotp<"));
+        assert!(code.contains("pub fn recv_isotp<"));
+        assert!(code.contains("not `MESSAGE_ID_DIAG_REQUEST`"));
+
+        let opt = DbccOpt {
+            runtime: Some(Runtime::Compat),
+            ..Default::default()
+        };
+        let code = can_code_gen(&opt, &dbc, "diag.dbc", "").unwrap().scope.to_string();
+        assert!(!code.contains("isotp"));
+    }
+    #[test]
+    fn cyclic_functions() {
+        let dbc = DBC::from_slice(SHARED_IDS.as_bytes()).unwrap();
+        for runtime in [None, Some(Runtime::Compat), Some(Runtime::Tokio)] {
+            let opt = DbccOpt {
+                runtime,
+                with_cyclic: true,
+                ..Default::default()
+            };
+            let code = can_code_gen(&opt, &dbc, "shared.dbc", "")
+                .unwrap()
+                .scope
+                .to_string();
+            assert!(code.contains("use dbcc_transport::bcm::BcmSocket;"));
+            assert!(code.contains("pub fn start_cyclic_with(&self, socket: &BcmSocket, interval: std::time::Duration) -> std::io::Result<()> {\n        socket.tx_setup(256, &self.frame_payload[..8], interval)\n"));
+            assert!(code.contains("pub fn update_cyclic(&self, socket: &BcmSocket) -> std::io::Result<()> {\n        socket.tx_update(256, &self.frame_payload[..8])\n"));
+            assert!(code.contains("pub fn stop_cyclic(&self, socket: &BcmSocket) -> std::io::Result<()> {\n        socket.tx_delete(256)\n"));
+            assert!(!code.contains("socket.fd"));
+        }
+    }
+}
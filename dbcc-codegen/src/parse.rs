@@ -0,0 +1,453 @@
+//! DBC parsing helpers on top of `can_dbc`.
+use crate::{Diagnostic, DiagnosticKind, Location, Severity};
+
+use can_dbc::DBC;
+use nom::verbose_errors::Context;
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Keywords starting a DBC section.
+const SECTION_KEYWORDS: &[&str] = &[
+    "BA_DEF_DEF_REL_",
+    "SIG_TYPE_REF_",
+    "ENVVAR_DATA_",
+    "SG_MUL_VAL_",
+    "SIG_VALTYPE_",
+    "BA_DEF_DEF_",
+    "BA_DEF_REL_",
+    "BU_SG_REL_",
+    "BU_EV_REL_",
+    "BU_BO_REL_",
+    "BO_TX_BU_",
+    "SIG_GROUP_",
+    "VAL_TABLE_",
+    "BA_DEF_",
+    "BA_REL_",
+    "CAT_DEF_",
+    "SGTYPE_",
+    "VERSION",
+    "FILTER",
+    "CAT_",
+    "VAL_",
+    "BA_",
+    "BO_",
+    "BS_",
+    "BU_",
+    "CM_",
+    "EV_",
+    "NS_",
+    "SG_",
+];
+
+/// Line and column (both starting at 1) of a byte offset in `buffer`.
+pub fn line_column(buffer: &[u8], offset: usize) -> (usize, usize) {
+    let before = &buffer[..offset];
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+    let column = String::from_utf8_lossy(&before[line_start..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// Number of sections per keyword, e.g. `BO_` or `VAL_`, in unparsed DBC content.
+pub fn skipped_sections(remaining: &[u8]) -> BTreeMap<&'static str, usize> {
+    let mut sections = BTreeMap::new();
+    for line in String::from_utf8_lossy(remaining).lines() {
+        let first_word = line.split_whitespace().next().unwrap_or("");
+        if let Some(keyword) = SECTION_KEYWORDS.iter().find(|k| **k == first_word) {
+            *sections.entry(*keyword).or_insert(0) += 1;
+        }
+    }
+    sections
+}
+
+/// Offset of the first non whitespace character of the unparsed `remaining` input.
+fn stop_offset(buffer: &[u8], remaining: &[u8]) -> usize {
+    let whitespace = remaining
+        .iter()
+        .take_while(|b| b.is_ascii_whitespace())
+        .count();
+    buffer.len().saturating_sub(remaining.len()) + whitespace
+}
+
+/// e.g. `2 SG_, 20 VAL_`
+fn skipped_summary(remaining: &[u8]) -> Option<String> {
+    let sections = skipped_sections(remaining);
+    if sections.is_empty() {
+        return None;
+    }
+    let skipped: Vec<String> = sections
+        .iter()
+        .map(|(keyword, count)| format!("{} {}", count, keyword))
+        .collect();
+    Some(skipped.join(", "))
+}
+
+/// Warning describing where the parser stopped for `can_dbc::Error::Incomplete`.
+pub fn incomplete_diagnostic(buffer: &[u8], remaining: &[u8]) -> Diagnostic {
+    let offset = stop_offset(buffer, remaining);
+    let (line, column) = line_column(buffer, offset);
+
+    let message = if offset == buffer.len() {
+        "parsing stopped at trailing whitespace, nothing was skipped".to_string()
+    } else {
+        match skipped_summary(remaining) {
+            Some(skipped) => format!(
+                "parsing stopped before the end of the file, skipped sections: {}",
+                skipped
+            ),
+            None => "parsing stopped before the end of the file, skipped the remaining content"
+                .to_string(),
+        }
+    };
+
+    Diagnostic {
+        severity: Severity::Warning,
+        kind: DiagnosticKind::IncompleteParse,
+        location: Location {
+            line: Some(line),
+            column: Some(column),
+            ..Default::default()
+        },
+        message,
+    }
+}
+
+/// DBC parser error with the position in the input.
+///
+/// `Display` renders the error in the style of rustc, without the leading `error: `:
+/// ```text
+/// failed to parse DBC, unexpected input
+///    --> j1939.dbc:12:2
+///     |
+///  12 |  SG_ Oops : garbage
+///     |  ^
+///     = note: skipped sections: 2 SG_, 20 VAL_
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// DBC file, if known
+    pub file: Option<PathBuf>,
+    /// Line starting at 1
+    pub line: usize,
+    /// Column starting at 1
+    pub column: usize,
+    /// Content of the line the error occurred in
+    pub source_line: String,
+    pub message: String,
+    /// Additional information e.g. the skipped sections
+    pub notes: Vec<String>,
+    /// The parser stopped before the end of the input,
+    /// `from_slice_partial` accepts the sections parsed before.
+    pub incomplete: bool,
+}
+
+impl ParseError {
+    /// Map a `can_dbc` error back to the position in `buffer`.
+    pub fn new(buffer: &[u8], e: &can_dbc::Error) -> Self {
+        let (offset, message, notes) = match e {
+            can_dbc::Error::Incomplete(_, remaining) => (
+                stop_offset(buffer, remaining),
+                "unexpected input".to_string(),
+                skipped_summary(remaining)
+                    .map(|skipped| format!("skipped sections: {}", skipped))
+                    .into_iter()
+                    .collect(),
+            ),
+            can_dbc::Error::NomError(nom::Err::Incomplete(_)) => (
+                buffer.len(),
+                "unexpected end of file".to_string(),
+                Vec::new(),
+            ),
+            can_dbc::Error::NomError(nom::Err::Error(context))
+            | can_dbc::Error::NomError(nom::Err::Failure(context)) => {
+                let (input, kind) = match context {
+                    Context::Code(input, kind) => (input, kind),
+                    Context::List(list) => match list.first() {
+                        Some((input, kind)) => (input, kind),
+                        None => {
+                            return ParseError::at(buffer, 0, "invalid DBC".to_string(), vec![])
+                        }
+                    },
+                };
+                (
+                    stop_offset(buffer, input.0),
+                    "unexpected input".to_string(),
+                    vec![format!("parser: {:?}", kind)],
+                )
+            }
+        };
+        let mut error = ParseError::at(buffer, offset, message, notes);
+        error.incomplete = matches!(e, can_dbc::Error::Incomplete(..));
+        error
+    }
+
+    fn at(buffer: &[u8], offset: usize, message: String, notes: Vec<String>) -> Self {
+        let offset = offset.min(buffer.len());
+        let (line, column) = line_column(buffer, offset);
+        let source_line = String::from_utf8_lossy(buffer)
+            .lines()
+            .nth(line - 1)
+            .unwrap_or("")
+            .to_string();
+        ParseError {
+            file: None,
+            line,
+            column,
+            source_line,
+            message,
+            notes,
+            incomplete: false,
+        }
+    }
+
+    /// Set the file name shown in the error message.
+    pub fn with_file<P: AsRef<Path>>(mut self, file: P) -> Self {
+        self.file = Some(file.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file = self
+            .file
+            .as_ref()
+            .map(|file| file.display().to_string())
+            .unwrap_or_else(|| "<dbc>".to_string());
+        let gutter = " ".repeat(self.line.to_string().len());
+        // Keep tabs so the caret lines up with the source line
+        let caret_indent: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        writeln!(f, "failed to parse DBC, {}", self.message)?;
+        writeln!(f, "{}--> {}:{}:{}", gutter, file, self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.source_line)?;
+        write!(f, "{} | {}^", gutter, caret_indent)?;
+        for note in &self.notes {
+            write!(f, "\n{} = note: {}", gutter, note)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Parse a DBC, mapping errors to the position in `buffer`.
+pub fn from_slice(buffer: &[u8]) -> Result<DBC, ParseError> {
+    DBC::from_slice(buffer).map_err(|e| ParseError::new(buffer, &e))
+}
+
+/// Parse a DBC, accepting content the parser could not read.
+/// Returns everything parsed before the parser stopped together with a
+/// warning naming the position and the skipped sections.
+///
+/// Example:
+/// ```
+/// # std::env::set_current_dir("..").unwrap();
+/// let buffer = std::fs::read("./examples/j1939.dbc").unwrap();
+/// let (dbc, incomplete) = dbcc::parse::from_slice_partial(&buffer).unwrap();
+/// if let Some(warning) = incomplete {
+///     eprintln!("{}", warning);
+/// }
+/// println!("{} messages", dbc.messages().len());
+/// ```
+pub fn from_slice_partial(buffer: &[u8]) -> Result<(DBC, Option<Diagnostic>), ParseError> {
+    match DBC::from_slice(buffer) {
+        Ok(dbc) => Ok((dbc, None)),
+        Err(can_dbc::Error::Incomplete(dbc, remaining)) => {
+            let diagnostic = incomplete_diagnostic(buffer, &remaining);
+            Ok((dbc, Some(diagnostic)))
+        }
+        Err(e) => Err(ParseError::new(buffer, &e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nom::types::CompleteByteSlice;
+
+    const HEADER: &str = "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\n";
+
+    /// Parser error at `input`, which must be a suffix of the buffer
+    fn code_error(input: &[u8]) -> can_dbc::Error<'_> {
+        can_dbc::Error::NomError(nom::Err::Error(Context::Code(
+            CompleteByteSlice(input),
+            nom::ErrorKind::Tag,
+        )))
+    }
+
+    #[test]
+    fn line_columns() {
+        let buffer = "ab\ncd\n\nä€x".as_bytes();
+        assert_eq!(line_column(buffer, 0), (1, 1));
+        assert_eq!(line_column(buffer, 2), (1, 3));
+        assert_eq!(line_column(buffer, 3), (2, 1));
+        assert_eq!(line_column(buffer, 7), (4, 1));
+        // Columns count characters, not bytes
+        assert_eq!(line_column(buffer, buffer.len() - 1), (4, 3));
+        assert_eq!(line_column(buffer, buffer.len()), (4, 4));
+    }
+
+    #[test]
+    fn skipped_section_counts() {
+        let remaining = b"BO_ 1 A: 8 ECU\n SG_ X : 0|8@1+ (1,0) [0|0] \"\" ECU\n SG_ Y : 8|8@1+ (1,0) [0|0] \"\" ECU\nVAL_ 1 X 0 \"Off\" ;\nBO_TX_BU_ 1 : ECU;\ngarbage\n";
+        let sections: Vec<_> = skipped_sections(remaining).into_iter().collect();
+        assert_eq!(
+            sections,
+            vec![("BO_", 1), ("BO_TX_BU_", 1), ("SG_", 2), ("VAL_", 1)]
+        );
+        assert!(skipped_sections(b"garbage\n").is_empty());
+    }
+
+    #[test]
+    fn incomplete_with_skipped_sections() {
+        let dbc = format!(
+            "{}BO_ 256 Parsed: 8 ECU\n SG_ Value : 0|8@1+ (1,0) [0|0] \"\" ECU\n\n  garbage\n\nBO_ 257 Skipped: 8 ECU\n SG_ Value : 0|8@1+ (1,0) [0|0] \"\" ECU\n\nVAL_ 257 Value 0 \"Off\" ;\n",
+            HEADER
+        );
+        let buffer = dbc.as_bytes();
+
+        let error = from_slice(buffer).unwrap_err();
+        assert!(error.incomplete);
+        assert_eq!((error.line, error.column), (12, 3));
+        assert_eq!(error.source_line, "  garbage");
+        assert_eq!(error.message, "unexpected input");
+        assert_eq!(error.notes, vec!["skipped sections: 1 BO_, 1 SG_, 1 VAL_"]);
+
+        let (dbc, warning) = from_slice_partial(buffer).unwrap();
+        assert_eq!(dbc.messages().len(), 1);
+        let warning = warning.unwrap();
+        assert_eq!(warning.kind, DiagnosticKind::IncompleteParse);
+        assert_eq!(
+            (warning.location.line, warning.location.column),
+            (Some(12), Some(3))
+        );
+        assert_eq!(
+            warning.message,
+            "parsing stopped before the end of the file, skipped sections: 1 BO_, 1 SG_, 1 VAL_"
+        );
+    }
+
+    #[test]
+    fn incomplete_without_sections() {
+        let dbc = format!(
+            "{}BO_ 256 Parsed: 8 ECU\n SG_ Value : 0|8@1+ (1,0) [0|0] \"\" ECU\n\ngarbage\n",
+            HEADER
+        );
+        let error = from_slice(dbc.as_bytes()).unwrap_err();
+        assert!(error.notes.is_empty());
+        let (_, warning) = from_slice_partial(dbc.as_bytes()).unwrap();
+        assert_eq!(
+            warning.unwrap().message,
+            "parsing stopped before the end of the file, skipped the remaining content"
+        );
+    }
+
+    #[test]
+    fn incomplete_trailing_whitespace() {
+        // The parser leaves the whitespace after a message without signals
+        let dbc = format!("{}BO_ 256 Empty: 8 ECU\n  \n", HEADER);
+        let buffer = dbc.as_bytes();
+        let (dbc, warning) = from_slice_partial(buffer).unwrap();
+        assert_eq!(dbc.messages().len(), 1);
+        let warning = warning.unwrap();
+        assert_eq!(
+            warning.message,
+            "parsing stopped at trailing whitespace, nothing was skipped"
+        );
+        assert_eq!(stop_offset(buffer, b"\n  \n"), buffer.len());
+    }
+
+    #[test]
+    fn nom_incomplete_at_eof() {
+        let buffer = b"VERSION \"\"\n\nNS_ :\n";
+        let e = can_dbc::Error::NomError(nom::Err::Incomplete(nom::Needed::Size(1)));
+        let error = ParseError::new(buffer, &e);
+        assert_eq!(error.message, "unexpected end of file");
+        assert_eq!((error.line, error.column), (4, 1));
+        assert_eq!(error.source_line, "");
+        assert!(!error.incomplete);
+    }
+
+    #[test]
+    fn nom_error_offset() {
+        let buffer = b"VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\nBO_ x\n";
+        let input = &buffer[buffer.len() - 7..];
+        assert_eq!(input, b"\nBO_ x\n");
+
+        let error = ParseError::new(buffer, &code_error(input));
+        // Leading whitespace of the unparsed input is skipped
+        assert_eq!((error.line, error.column), (9, 1));
+        assert_eq!(error.source_line, "BO_ x");
+        assert_eq!(error.notes, vec!["parser: Tag"]);
+
+        let failure = can_dbc::Error::NomError(nom::Err::Failure(Context::Code(
+            CompleteByteSlice(&buffer[buffer.len() - 2..]),
+            nom::ErrorKind::Tag,
+        )));
+        let error = ParseError::new(buffer, &failure);
+        assert_eq!((error.line, error.column), (9, 5));
+        assert!(!error.incomplete);
+    }
+
+    #[test]
+    fn nom_error_list() {
+        let buffer = b"VERSION \"\"\nBO_ x\n";
+        let list = can_dbc::Error::NomError(nom::Err::Error(Context::List(vec![(
+            CompleteByteSlice(&buffer[11..]),
+            nom::ErrorKind::Alt,
+        )])));
+        let error = ParseError::new(buffer, &list);
+        assert_eq!((error.line, error.column), (2, 1));
+        assert_eq!(error.notes, vec!["parser: Alt"]);
+
+        let empty = can_dbc::Error::NomError(nom::Err::Error(Context::List(vec![])));
+        let error = ParseError::new(buffer, &empty);
+        assert_eq!(error.message, "invalid DBC");
+        assert_eq!((error.line, error.column), (1, 1));
+        assert_eq!(error.source_line, "VERSION \"\"");
+        assert!(error.notes.is_empty());
+    }
+
+    #[test]
+    fn display_caret_alignment() {
+        let buffer = "VERSION \"\"\n\tÄ€ oops\n".as_bytes();
+        let input = &buffer[buffer.len() - 5..];
+        assert_eq!(input, b"oops\n");
+
+        let error = ParseError::new(buffer, &code_error(input)).with_file("tabs.dbc");
+        assert_eq!(error.column, 5);
+        assert_eq!(
+            error.to_string(),
+            "failed to parse DBC, unexpected input\n --> tabs.dbc:2:5\n  |\n2 | \tÄ€ oops\n  | \t   ^\n  = note: parser: Tag"
+        );
+    }
+
+    #[test]
+    fn display_without_file() {
+        let lines = "\n".repeat(99);
+        let buffer = format!("{}garbage", lines);
+        let error = ParseError::new(buffer.as_bytes(), &code_error(b"garbage"));
+        assert_eq!(
+            error.to_string(),
+            "failed to parse DBC, unexpected input\n   --> <dbc>:100:1\n    |\n100 | garbage\n    | ^\n    = note: parser: Tag"
+        );
+    }
+}
@@ -0,0 +1,782 @@
+//! Decode and encode CAN frames based on a DBC loaded at runtime.
+//!
+//! The `Decoder` reads signals exactly like the generated code does, so both
+//! agree on every frame. Signal types are derived from the DBC, configured
+//! renames and type overrides of `DbccOpt` are not applied.
+use crate::{
+    bit_mask, signal_decoded_type, signal_position, CodegenError, Diagnostic, DiagnosticKind,
+    Location, Severity, TypeName, EFF_FLAG,
+};
+
+use byteorder::{ByteOrder as _, BE, LE};
+use can_dbc::{
+    ByteOrder, Message, MultiplexIndicator, Signal, SignalExtendedValueType, Transmitter, DBC,
+};
+use heck::CamelCase;
+use serde::Serialize;
+
+use std::cmp;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+
+/// Decoded or to be encoded signal value.
+/// The variant matches the type the generated code decodes the signal to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+impl Value {
+    /// Value as used to look up value descriptions, same as `val as u64` in generated code.
+    pub fn as_u64(self) -> u64 {
+        match self {
+            Value::Bool(value) => value as u64,
+            Value::Unsigned(value) => value,
+            Value::Signed(value) => value as u64,
+            Value::Float(value) => value as u64,
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Value::Bool(value) => value as u64 as f64,
+            Value::Unsigned(value) => value as f64,
+            Value::Signed(value) => value as f64,
+            Value::Float(value) => value,
+        }
+    }
+
+    /// Integral value, floats are only accepted if they have no fractional part.
+    fn as_integer(self) -> Option<i128> {
+        match self {
+            Value::Bool(value) => Some(value as i128),
+            Value::Unsigned(value) => Some(value as i128),
+            Value::Signed(value) => Some(value as i128),
+            Value::Float(value) if value.is_finite() && value.fract() == 0.0 => Some(value as i128),
+            Value::Float(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Unsigned(value) => write!(f, "{}", value),
+            Value::Signed(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Rust type the generated code decodes a signal to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SignalKind {
+    Bool,
+    /// Unsigned integer of the given bit width
+    Unsigned(u32),
+    /// Signed integer of the given bit width
+    Signed(u32),
+    /// Scaled by factor and offset
+    Scaled,
+    Float32,
+    Float64,
+}
+
+/// Signal definition with its precomputed position in the payload.
+#[derive(Clone, Debug)]
+pub struct SignalDef {
+    pub name: String,
+    pub unit: String,
+    pub factor: f64,
+    pub offset: f64,
+    pub min: f64,
+    pub max: f64,
+    pub multiplexer: MultiplexIndicator,
+    /// Value descriptions (`VAL_`) keyed by value
+    pub descriptions: BTreeMap<u64, String>,
+    kind: SignalKind,
+    byte_order: ByteOrder,
+    byte_offset: usize,
+    shift: u64,
+    mask: u64,
+}
+
+impl SignalDef {
+    fn new(dbc: &DBC, message: &Message, signal: &Signal) -> Result<Self, CodegenError> {
+        let (byte_offset, shift) = signal_position(message, signal)?;
+        let message_id = *message.message_id();
+        let boolean_signal =
+            *signal.signal_size() == 1 && *signal.factor() == 1.0 && *signal.offset() == 0.0;
+        let decoded_type = signal_decoded_type(dbc, message_id, signal);
+        let kind = match dbc.extended_value_type_for_signal(message_id, signal.name()) {
+            Some(SignalExtendedValueType::IEEEfloat32Bit) => SignalKind::Float32,
+            Some(SignalExtendedValueType::IEEEdouble64bit) => SignalKind::Float64,
+            _ if boolean_signal => SignalKind::Bool,
+            _ if decoded_type == "f64" => SignalKind::Scaled,
+            _ => {
+                let width = decoded_type[1..].parse().unwrap_or(64);
+                if decoded_type.starts_with('i') {
+                    SignalKind::Signed(width)
+                } else {
+                    SignalKind::Unsigned(width)
+                }
+            }
+        };
+        let descriptions = dbc
+            .value_descriptions_for_signal(message_id, signal.name())
+            .map(|descriptions| {
+                descriptions
+                    .iter()
+                    .map(|description| (*description.a() as u64, description.b().clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(SignalDef {
+            name: signal.name().clone(),
+            unit: signal.unit().clone(),
+            factor: *signal.factor(),
+            offset: *signal.offset(),
+            min: *signal.min(),
+            max: *signal.max(),
+            multiplexer: *signal.multiplexer_indicator(),
+            descriptions,
+            kind,
+            byte_order: *signal.byte_order(),
+            byte_offset: byte_offset as usize,
+            shift,
+            mask: bit_mask(*signal.signal_size()),
+        })
+    }
+
+    /// Value of a value description, given as DBC text or as generated enum variant name.
+    pub fn description_value(&self, description: &str) -> Option<Value> {
+        self.descriptions
+            .iter()
+            .find(|(_, text)| {
+                text.as_str() == description || text.to_camel_case().to_type_name() == description
+            })
+            .map(|(value, _)| Value::Unsigned(*value))
+    }
+
+    /// Whether the value is within the DBC minimum and maximum, `[0|0]` means no range.
+    pub fn in_range(&self, value: Value) -> bool {
+        if self.min == 0.0 && self.max == 0.0 {
+            return true;
+        }
+        // Allow for factors that can not be represented exactly e.g. 0.1
+        let tolerance = self.factor.abs() / 2.0;
+        let value = value.as_f64();
+        value >= self.min - tolerance && value <= self.max + tolerance
+    }
+
+    /// Raw signal bits, `payload` must be at least `payload_len` long.
+    fn read_raw(&self, payload: &[u8]) -> u64 {
+        let window = &payload[self.byte_offset..];
+        let window = match self.byte_order {
+            ByteOrder::LittleEndian => LE::read_u64(window),
+            ByteOrder::BigEndian => BE::read_u64(window),
+        };
+        (window >> self.shift) & self.mask
+    }
+
+    fn write_raw(&self, payload: &mut [u8], raw: u64) {
+        let window = &mut payload[self.byte_offset..];
+        let current = match self.byte_order {
+            ByteOrder::LittleEndian => LE::read_u64(window),
+            ByteOrder::BigEndian => BE::read_u64(window),
+        };
+        let value = (current & !(self.mask << self.shift)) | ((raw & self.mask) << self.shift);
+        match self.byte_order {
+            ByteOrder::LittleEndian => LE::write_u64(window, value),
+            ByteOrder::BigEndian => BE::write_u64(window, value),
+        }
+    }
+
+    /// Decoded value of the raw signal bits.
+    fn value(&self, raw: u64) -> Value {
+        match self.kind {
+            SignalKind::Bool => Value::Bool(raw == 1),
+            SignalKind::Unsigned(_) => Value::Unsigned(raw),
+            SignalKind::Signed(8) => Value::Signed(raw as i8 as i64),
+            SignalKind::Signed(16) => Value::Signed(raw as i16 as i64),
+            SignalKind::Signed(32) => Value::Signed(raw as i32 as i64),
+            SignalKind::Signed(_) => Value::Signed(raw as i64),
+            SignalKind::Scaled => Value::Float(self.scale(raw as f64)),
+            SignalKind::Float32 => {
+                // Generated code calculates in the signal's float type
+                let mut value = f32::from_bits(raw as u32);
+                if self.factor != 1.0 {
+                    value *= self.factor as f32;
+                }
+                if self.offset != 0.0 {
+                    value += self.offset as f32;
+                }
+                Value::Float(value as f64)
+            }
+            SignalKind::Float64 => Value::Float(self.scale(f64::from_bits(raw))),
+        }
+    }
+
+    /// Apply factor and offset, both are skipped if they do not change the value,
+    /// same as in generated code.
+    fn scale(&self, mut value: f64) -> f64 {
+        if self.factor != 1.0 {
+            value *= self.factor;
+        }
+        if self.offset != 0.0 {
+            value += self.offset;
+        }
+        value
+    }
+
+    /// Raw signal bits encoding `value`, `None` if the signal can not represent it.
+    fn raw(&self, value: Value) -> Option<u64> {
+        let raw = match self.kind {
+            SignalKind::Float32 => {
+                let value = (value.as_f64() as f32 - self.offset as f32) / self.factor as f32;
+                return Some(value.to_bits() as u64);
+            }
+            SignalKind::Float64 => {
+                return Some(((value.as_f64() - self.offset) / self.factor).to_bits());
+            }
+            SignalKind::Scaled => {
+                let raw = ((value.as_f64() - self.offset) / self.factor).round();
+                if raw < 0.0 || raw > self.mask as f64 {
+                    return None;
+                }
+                raw as u64
+            }
+            _ => {
+                let value = value.as_integer()?;
+                // Signed values are only sign extended by the generated code if the
+                // signal fills its decoded type, so encode and decode must round trip.
+                let raw = value as u64 & self.mask;
+                if self.value(raw).as_integer() != Some(value) {
+                    return None;
+                }
+                raw
+            }
+        };
+        Some(raw)
+    }
+}
+
+/// Message definition with the signals the `Decoder` reads.
+#[derive(Clone, Debug)]
+pub struct MessageDef {
+    /// Message id as defined in the DBC, extended ids have bit 31 set
+    pub id: u32,
+    pub name: String,
+    /// Message length (DLC) in bytes
+    pub size: u64,
+    /// Transmitting node, `None` for `Vector__XXX`
+    pub transmitter: Option<String>,
+    pub signals: Vec<SignalDef>,
+    payload_len: usize,
+}
+
+impl MessageDef {
+    /// Signals the generated code could not read either are skipped with a warning.
+    fn new(dbc: &DBC, message: &Message, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        let mut signals = Vec::with_capacity(message.signals().len());
+        for signal in message.signals() {
+            let reason = match SignalDef::new(dbc, message, signal) {
+                Ok(signal) => {
+                    signals.push(signal);
+                    continue;
+                }
+                Err(CodegenError::UnsupportedSignal { reason, .. }) => reason,
+                Err(e) => e.to_string(),
+            };
+            diagnostics.push(skipped_signal(
+                message,
+                signal.name(),
+                DiagnosticKind::UnsupportedSignal,
+                &reason,
+            ));
+        }
+        // Multiplexed signals are read based on the multiplexor switch, same as in generated code
+        let has_multiplexor = signals
+            .iter()
+            .any(|signal| signal.multiplexer == MultiplexIndicator::Multiplexor);
+        if !has_multiplexor {
+            signals.retain(|signal| {
+                let multiplexed =
+                    matches!(signal.multiplexer, MultiplexIndicator::MultiplexedSignal(_));
+                if multiplexed {
+                    diagnostics.push(skipped_signal(
+                        message,
+                        &signal.name,
+                        DiagnosticKind::MissingMultiplexorSwitch,
+                        "the message has no multiplexor switch",
+                    ));
+                }
+                !multiplexed
+            });
+        }
+
+        let transmitter = match message.transmitter() {
+            Transmitter::NodeName(node) => Some(node.clone()),
+            Transmitter::VectorXXX => None,
+        };
+        // Every signal reads 8 bytes starting at its byte offset
+        let payload_len = signals
+            .iter()
+            .map(|signal| signal.byte_offset + 8)
+            .fold(cmp::max(8, *message.message_size() as usize), cmp::max);
+        MessageDef {
+            id: message.message_id().0,
+            name: message.message_name().clone(),
+            size: *message.message_size(),
+            transmitter,
+            signals,
+            payload_len,
+        }
+    }
+
+    pub fn signal(&self, name: &str) -> Option<&SignalDef> {
+        self.signals.iter().find(|signal| signal.name == name)
+    }
+
+    fn multiplexor(&self) -> Option<&SignalDef> {
+        self.signals
+            .iter()
+            .find(|signal| signal.multiplexer == MultiplexIndicator::Multiplexor)
+    }
+
+    /// Whether a signal is present given the multiplexor switch value of the payload.
+    fn is_active(&self, signal: &SignalDef, payload: &[u8]) -> bool {
+        match (signal.multiplexer, self.multiplexor()) {
+            (MultiplexIndicator::MultiplexedSignal(switch_value), Some(multiplexor)) => {
+                multiplexor.value(multiplexor.read_raw(payload)).as_u64() == switch_value
+            }
+            _ => true,
+        }
+    }
+
+    /// Payload padded to the length every signal can be read from.
+    fn padded(&self, data: &[u8]) -> Vec<u8> {
+        let mut payload = data.to_vec();
+        if payload.len() < self.payload_len {
+            payload.resize(self.payload_len, 0);
+        }
+        payload
+    }
+}
+
+/// Signal value decoded from a frame.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DecodedSignal {
+    pub name: String,
+    pub value: Value,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub unit: String,
+    /// Value description (`VAL_`) matching the value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl fmt::Display for DecodedSignal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if !self.unit.is_empty() {
+            write!(f, " {}", self.unit)?;
+        }
+        if let Some(ref description) = self.description {
+            write!(f, " ({})", description)?;
+        }
+        Ok(())
+    }
+}
+
+/// Frame decoded into its signals.
+/// Multiplexed signals are only included if the multiplexor switch selects them.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DecodedMessage {
+    pub id: u32,
+    pub name: String,
+    pub signals: Vec<DecodedSignal>,
+}
+
+impl fmt::Display for DecodedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for signal in &self.signals {
+            write!(f, " {}", signal)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// No message with the id is defined
+    UnknownMessage(u32),
+    /// No message with the name is defined
+    UnknownMessageName(String),
+    UnknownSignal {
+        message: String,
+        signal: String,
+    },
+    /// The value can not be represented by the signal
+    OutOfRange {
+        message: String,
+        signal: String,
+        value: Value,
+    },
+    /// A multiplexed signal was encoded but the multiplexor switch does not select it
+    InactiveSignal {
+        message: String,
+        signal: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownMessage(id) => write!(f, "unknown message id {}", id),
+            Error::UnknownMessageName(name) => write!(f, "unknown message `{}`", name),
+            Error::UnknownSignal { message, signal } => {
+                write!(f, "message `{}` has no signal `{}`", message, signal)
+            }
+            Error::OutOfRange {
+                message,
+                signal,
+                value,
+            } => write!(
+                f,
+                "value {} can not be encoded in signal `{}` of message `{}`",
+                value, signal, message
+            ),
+            Error::InactiveSignal { message, signal } => write!(
+                f,
+                "multiplexed signal `{}` of message `{}` is not selected by the multiplexor switch",
+                signal, message
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Decode and encode frames of the messages of a DBC without generating code.
+///
+/// Example:
+/// ```
+/// use dbcc::runtime::{Decoder, Value};
+///
+/// # std::env::set_current_dir("..").unwrap();
+/// let buffer = std::fs::read("./examples/j1939.dbc").unwrap();
+/// let dbc = can_dbc::DBC::from_slice(&buffer).unwrap();
+/// let decoder = Decoder::new(&dbc);
+/// assert!(decoder.diagnostics().is_empty());
+///
+/// // Extended ids may be passed with or without the DBC's extended flag
+/// let oel = decoder.decode(0x0CFD_CCFE, &[0x00, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]).unwrap();
+/// assert_eq!(oel.name, "OEL");
+/// let hazard = oel.signals.iter().find(|s| s.name == "HazardLightSwitch").unwrap();
+/// assert_eq!(hazard.value, Value::Unsigned(1));
+/// assert_eq!(hazard.description.as_deref(), Some("HazardLampsToBeFlashing"));
+///
+/// let payload = decoder
+///     .encode_by_name("OEL", &[("HazardLightSwitch", Value::Unsigned(1))])
+///     .unwrap();
+/// assert_eq!(payload[1], 0x10);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    messages: BTreeMap<u32, MessageDef>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Decoder {
+    /// Signals the generated code could not read either are skipped, see `diagnostics`.
+    pub fn new(dbc: &DBC) -> Self {
+        let mut messages = BTreeMap::new();
+        let mut diagnostics = Vec::new();
+        for message in dbc.messages() {
+            let message_def = MessageDef::new(dbc, message, &mut diagnostics);
+            messages.insert(message.message_id().0, message_def);
+        }
+        Decoder {
+            messages,
+            diagnostics,
+        }
+    }
+
+    /// Warnings for the signals that were skipped
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &MessageDef> {
+        self.messages.values()
+    }
+
+    /// Message by frame id, extended ids are matched with and without the extended flag.
+    pub fn message(&self, id: u32) -> Option<&MessageDef> {
+        self.messages
+            .get(&id)
+            .or_else(|| self.messages.get(&(id | EFF_FLAG)))
+    }
+
+    pub fn message_by_name(&self, name: &str) -> Option<&MessageDef> {
+        self.messages.values().find(|message| message.name == name)
+    }
+
+    /// Decode a frame, data shorter than the message is padded with zeros.
+    pub fn decode(&self, id: u32, data: &[u8]) -> Result<DecodedMessage, Error> {
+        let message = self.message(id).ok_or(Error::UnknownMessage(id))?;
+        let payload = message.padded(data);
+        let signals = message
+            .signals
+            .iter()
+            .filter(|signal| message.is_active(signal, &payload))
+            .map(|signal| {
+                let value = signal.value(signal.read_raw(&payload));
+                DecodedSignal {
+                    name: signal.name.clone(),
+                    value,
+                    unit: signal.unit.clone(),
+                    description: signal.descriptions.get(&value.as_u64()).cloned(),
+                }
+            })
+            .collect();
+        Ok(DecodedMessage {
+            id: message.id,
+            name: message.name.clone(),
+            signals,
+        })
+    }
+
+    /// Encode signal values into a payload of the message length (DLC).
+    /// Signals that are not given are zero.
+    pub fn encode(&self, id: u32, values: &[(&str, Value)]) -> Result<Vec<u8>, Error> {
+        let message = self.message(id).ok_or(Error::UnknownMessage(id))?;
+        encode_message(message, values)
+    }
+
+    pub fn encode_by_name(&self, name: &str, values: &[(&str, Value)]) -> Result<Vec<u8>, Error> {
+        let message = self
+            .message_by_name(name)
+            .ok_or_else(|| Error::UnknownMessageName(name.to_string()))?;
+        encode_message(message, values)
+    }
+}
+
+/// Warning for a signal the `Decoder` does not read.
+fn skipped_signal(
+    message: &Message,
+    signal_name: &str,
+    kind: DiagnosticKind,
+    reason: &str,
+) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Warning,
+        kind,
+        location: Location {
+            message_id: Some(message.message_id().0),
+            signal_name: Some(signal_name.to_string()),
+            ..Default::default()
+        },
+        message: format!("signal is skipped, {}", reason),
+    }
+}
+
+fn encode_message(message: &MessageDef, values: &[(&str, Value)]) -> Result<Vec<u8>, Error> {
+    let mut payload = message.padded(&[]);
+    let mut signals = Vec::with_capacity(values.len());
+    for (name, value) in values {
+        let signal = message.signal(name).ok_or_else(|| Error::UnknownSignal {
+            message: message.name.clone(),
+            signal: name.to_string(),
+        })?;
+        let raw = signal.raw(*value).ok_or_else(|| Error::OutOfRange {
+            message: message.name.clone(),
+            signal: signal.name.clone(),
+            value: *value,
+        })?;
+        signal.write_raw(&mut payload, raw);
+        signals.push(signal);
+    }
+    if let Some(signal) = signals
+        .iter()
+        .find(|signal| !message.is_active(signal, &payload))
+    {
+        return Err(Error::InactiveSignal {
+            message: message.name.clone(),
+            signal: signal.name.clone(),
+        });
+    }
+    payload.truncate(message.size as usize);
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EFL_P5: u32 = 0x18FD_88FE;
+    const DRC: u32 = 0x18FD_70FE;
+
+    fn j1939() -> Decoder {
+        let buffer = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../examples/j1939.dbc"
+        ))
+        .unwrap();
+        let decoder = Decoder::new(&DBC::from_slice(&buffer).unwrap());
+        assert!(decoder.diagnostics().is_empty());
+        decoder
+    }
+
+    fn signal<'a>(message: &'a DecodedMessage, name: &str) -> &'a DecodedSignal {
+        message.signals.iter().find(|s| s.name == name).unwrap()
+    }
+
+    #[test]
+    fn decode_scaled() {
+        let efl_p5 = j1939()
+            .decode(EFL_P5, &[0x10, 0x27, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF])
+            .unwrap();
+        assert_eq!(efl_p5.name, "EFL_P5");
+        let values: Vec<(&str, Value)> = efl_p5
+            .signals
+            .iter()
+            .map(|s| (s.name.as_str(), s.value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("EngIntkVlvActtonOilPressForCyl4", Value::Float(6553.5)),
+                ("EngIntkVlvActtonOilPressForCyl3", Value::Float(0.0)),
+                ("EngIntkVlvActtonOilPressForCyl2", Value::Float(0.1)),
+                ("EngIntkVlvActtonOilPressForCyl1", Value::Float(1000.0)),
+            ]
+        );
+        assert_eq!(
+            signal(&efl_p5, "EngIntkVlvActtonOilPressForCyl1").unit,
+            "kPa"
+        );
+    }
+
+    #[test]
+    fn decode_descriptions() {
+        let drc = j1939()
+            .decode(DRC | EFF_FLAG, &[0x1B, 0, 0, 0, 0, 0, 0, 0])
+            .unwrap();
+        let retract = signal(&drc, "RetractStatusOfRamp1");
+        assert_eq!(retract.value, Value::Unsigned(3));
+        let enable = signal(&drc, "EnableStatusOfRamp1");
+        assert_eq!(enable.value, Value::Unsigned(2));
+        assert_eq!(enable.description.as_deref(), Some("error"));
+        let movement = signal(&drc, "MovementStatusOfRamp1");
+        assert_eq!(movement.description.as_deref(), Some("rampBeingMoved"));
+    }
+
+    #[test]
+    fn decode_short_payload() {
+        // Missing bytes read as zero
+        let efl_p5 = j1939().decode(EFL_P5, &[0x10, 0x27]).unwrap();
+        let cyl2 = signal(&efl_p5, "EngIntkVlvActtonOilPressForCyl2");
+        assert_eq!(cyl2.value, Value::Float(0.0));
+    }
+
+    #[test]
+    fn encode() {
+        let decoder = j1939();
+        let payload = decoder
+            .encode(
+                DRC,
+                &[
+                    ("MovementStatusOfRamp1", Value::Unsigned(1)),
+                    ("EnableStatusOfRamp1", Value::Unsigned(2)),
+                    ("RetractStatusOfRamp1", Value::Unsigned(3)),
+                ],
+            )
+            .unwrap();
+        assert_eq!(payload, vec![0x1B, 0, 0, 0, 0, 0, 0, 0]);
+
+        let payload = decoder
+            .encode_by_name(
+                "EFL_P5",
+                &[
+                    ("EngIntkVlvActtonOilPressForCyl1", Value::Float(1000.0)),
+                    ("EngIntkVlvActtonOilPressForCyl4", Value::Float(6553.5)),
+                ],
+            )
+            .unwrap();
+        assert_eq!(payload, vec![0x10, 0x27, 0, 0, 0, 0, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn encode_errors() {
+        let decoder = j1939();
+        assert!(matches!(
+            decoder.encode(DRC, &[("RetractStatusOfRamp1", Value::Unsigned(4))]),
+            Err(Error::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            decoder.encode(DRC, &[("Ramp1", Value::Unsigned(0))]),
+            Err(Error::UnknownSignal { .. })
+        ));
+        assert!(matches!(
+            decoder.encode(0x123, &[]),
+            Err(Error::UnknownMessage(0x123))
+        ));
+    }
+
+    #[test]
+    fn skip_unsupported_signals() {
+        let dbc = DBC::from_slice(
+            b"VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\n\
+BO_ 256 Wide: 16 ECU\n SG_ TooWide : 0|65@1+ (1,0) [0|0] \"\" ECU\n SG_ Byte : 72|8@1+ (1,0) [0|0] \"\" ECU\n\n\
+BO_ 257 NoSwitch: 8 ECU\n SG_ Muxed m1 : 0|8@1+ (1,0) [0|0] \"\" ECU\n SG_ Plain : 8|8@1+ (1,0) [0|0] \"\" ECU\n",
+        )
+        .unwrap();
+        let decoder = Decoder::new(&dbc);
+        let skipped: Vec<(DiagnosticKind, Option<u32>, Option<&str>)> = decoder
+            .diagnostics()
+            .iter()
+            .map(|d| {
+                (
+                    d.kind,
+                    d.location.message_id,
+                    d.location.signal_name.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                (
+                    DiagnosticKind::UnsupportedSignal,
+                    Some(256),
+                    Some("TooWide")
+                ),
+                (
+                    DiagnosticKind::MissingMultiplexorSwitch,
+                    Some(257),
+                    Some("Muxed")
+                ),
+            ]
+        );
+
+        // The remaining signals of both messages are still decoded
+        let mut payload = vec![0; 16];
+        payload[9] = 0x42;
+        let wide = decoder.decode(256, &payload).unwrap();
+        assert_eq!(wide.signals.len(), 1);
+        assert_eq!(signal(&wide, "Byte").value, Value::Unsigned(0x42));
+        let no_switch = decoder.decode(257, &[0x01, 0x02]).unwrap();
+        assert_eq!(no_switch.signals.len(), 1);
+        assert_eq!(signal(&no_switch, "Plain").value, Value::Unsigned(2));
+    }
+}
@@ -0,0 +1,116 @@
+//! Compile time counterpart of the `dbcc` CLI.
+//!
+//! The macro is usually invoked via the `dbcc::include_dbc!` re-export.
+use dbcc_codegen::{can_code_gen, dbc_hash, parse, DbccOpt, Runtime};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Ident, LitStr, Token};
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Macro input: `"path/to/file.dbc"` optionally followed by a list of generation flags.
+struct IncludeDbc {
+    path: LitStr,
+    flags: Vec<Ident>,
+}
+
+impl Parse for IncludeDbc {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut flags = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            flags.push(input.parse()?);
+        }
+        Ok(IncludeDbc { path, flags })
+    }
+}
+
+impl IncludeDbc {
+    fn dbcc_opt(&self) -> syn::Result<DbccOpt> {
+        let mut opt = DbccOpt::default();
+        for flag in &self.flags {
+            match flag.to_string().as_str() {
+                "with_tokio" => opt.with_tokio = true,
+                "with_socketcan" => opt.with_socketcan = true,
+                "with_cyclic" => opt.with_cyclic = true,
+                "with_embedded_can" => opt.with_embedded_can = true,
+                "tokio" => opt.runtime = Some(Runtime::Tokio),
+                "async_std" => opt.runtime = Some(Runtime::AsyncStd),
+                unknown => {
+                    return Err(syn::Error::new(
+                        flag.span(),
+                        format!(
+                            "unknown include_dbc! option `{}`, expected `with_tokio`, `tokio`, `async_std`, `with_socketcan`, `with_cyclic` or `with_embedded_can`",
+                            unknown
+                        ),
+                    ))
+                }
+            }
+        }
+        Ok(opt)
+    }
+}
+
+fn expand(input: &IncludeDbc) -> syn::Result<proc_macro2::TokenStream> {
+    let span = input.path.span();
+    let opt = input.dbcc_opt()?;
+
+    // Paths are resolved relative to the crate root, like build scripts do.
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let dbc_path = PathBuf::from(manifest_dir).join(input.path.value());
+    let buffer = fs::read(&dbc_path).map_err(|e| {
+        syn::Error::new(
+            span,
+            format!("Failed to read {}: {}", input.path.value(), e),
+        )
+    })?;
+
+    let dbc = parse::from_slice(&buffer)
+        .map_err(|e| syn::Error::new(span, e.with_file(&dbc_path).to_string()))?;
+
+    let file_name = dbc_path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or("N/A");
+    let generated = can_code_gen(&opt, &dbc, file_name, &dbc_hash(&buffer))
+        .map_err(|e| syn::Error::new(span, format!("Failed to generate rust code: {}", e)))?;
+
+    // Diagnostics can not be reported as warnings from a proc macro on stable.
+    let code = proc_macro2::TokenStream::from_str(&generated.scope.to_string())
+        .map_err(|e| syn::Error::new(span, format!("Generated code is not valid Rust: {:?}", e)))?;
+
+    // Referencing the file makes cargo rebuild the crate when the DBC changes.
+    let dbc_path = dbc_path.to_string_lossy().into_owned();
+    Ok(quote! {
+        const _: &[u8] = include_bytes!(#dbc_path);
+        #code
+    })
+}
+
+/// Generate code for a DBC file at compile time.
+///
+/// The path is relative to the `CARGO_MANIFEST_DIR` of the invoking crate.
+/// Generation flags may follow the path, `with_tokio`, `with_socketcan`, `with_cyclic` and `with_embedded_can` are supported.
+/// `tokio` or `async_std` generate streams for that runtime instead of `with_tokio`'s compat streams.
+///
+/// ```ignore
+/// mod j1939 {
+///     dbcc::include_dbc!("examples/j1939.dbc", with_tokio);
+/// }
+/// ```
+#[proc_macro]
+pub fn include_dbc(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as IncludeDbc);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
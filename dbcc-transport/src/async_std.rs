@@ -0,0 +1,155 @@
+//! Sockets for async-std or any other executor (`async-std` feature),
+//! driven by the `async-io` reactor async-std is based on.
+use crate::bcm::BcmSocket;
+use crate::frame::Frame;
+use crate::raw;
+use crate::transport::{CanSource, CanTransport};
+
+use async_io::Async;
+use futures_core::ready;
+use futures_core::stream::Stream;
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Stream of frames received by a BCM socket.
+#[derive(Debug)]
+pub struct BcmStream {
+    socket: Async<BcmSocket>,
+}
+
+impl BcmStream {
+    /// Receive frames with `can_id` (DBC message id), see `BcmSocket::rx_setup`.
+    pub fn filter_id(
+        ifname: &str,
+        can_id: u32,
+        timeout: Duration,
+        throttle: Duration,
+    ) -> io::Result<BcmStream> {
+        let socket = BcmSocket::open(ifname)?;
+        socket.rx_setup(can_id, timeout, throttle)?;
+        BcmStream::new(socket)
+    }
+
+    /// Stream the frames of a socket with RX jobs set up.
+    pub fn new(socket: BcmSocket) -> io::Result<BcmStream> {
+        Ok(BcmStream {
+            socket: Async::new(socket)?,
+        })
+    }
+
+    pub fn get_ref(&self) -> &BcmSocket {
+        self.socket.get_ref()
+    }
+}
+
+impl Stream for BcmStream {
+    type Item = io::Result<Frame>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_recv(cx)
+    }
+}
+
+impl CanSource for BcmStream {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        poll_read(&self.socket, cx, BcmSocket::read_frame).map(Some)
+    }
+}
+
+/// Sends single frames, received frames are still limited to the ids set up.
+impl CanTransport for BcmStream {
+    fn poll_send(&mut self, cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        poll_write(&self.socket, cx, |socket| socket.send_frame(frame))
+    }
+}
+
+/// Raw CAN socket.
+#[derive(Debug)]
+pub struct CanSocket {
+    socket: Async<raw::CanSocket>,
+}
+
+impl CanSocket {
+    /// Open a raw socket on a CAN interface e.g. `vcan0`.
+    pub fn open(ifname: &str) -> io::Result<CanSocket> {
+        Ok(CanSocket {
+            socket: Async::new(raw::CanSocket::open(ifname)?)?,
+        })
+    }
+
+    pub async fn write_frame(&self, frame: &Frame) -> io::Result<()> {
+        self.socket
+            .write_with(|socket| socket.write_frame(frame))
+            .await
+    }
+
+    pub async fn read_frame(&self) -> io::Result<Frame> {
+        self.socket.read_with(|socket| socket.read_frame()).await
+    }
+}
+
+impl CanSource for CanSocket {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        poll_read(&self.socket, cx, raw::CanSocket::read_frame).map(Some)
+    }
+}
+
+impl CanTransport for CanSocket {
+    fn poll_send(&mut self, cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        poll_write(&self.socket, cx, |socket| socket.write_frame(frame))
+    }
+}
+
+/// Retry `read` until it does not block.
+fn poll_read<T, R>(
+    socket: &Async<T>,
+    cx: &mut Context<'_>,
+    read: impl Fn(&T) -> io::Result<R>,
+) -> Poll<io::Result<R>> {
+    loop {
+        match read(socket.get_ref()) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => ready!(socket.poll_readable(cx))?,
+            result => return Poll::Ready(result),
+        }
+    }
+}
+
+/// Retry `write` until it does not block.
+fn poll_write<T, R>(
+    socket: &Async<T>,
+    cx: &mut Context<'_>,
+    write: impl Fn(&T) -> io::Result<R>,
+) -> Poll<io::Result<R>> {
+    loop {
+        match write(socket.get_ref()) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => ready!(socket.poll_writable(cx))?,
+            result => return Poll::Ready(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_executor::block_on;
+
+    #[test]
+    fn send_receive() {
+        let ifname = match crate::vcan_interface() {
+            Some(ifname) => ifname,
+            None => return,
+        };
+        let sender = CanSocket::open(&ifname).unwrap();
+        let receiver = CanSocket::open(&ifname).unwrap();
+
+        let frame = Frame::new(0x123, &[1, 2, 3]).unwrap();
+        block_on(async {
+            sender.write_frame(&frame).await.unwrap();
+            assert_eq!(receiver.read_frame().await.unwrap(), frame);
+        });
+    }
+}
@@ -0,0 +1,333 @@
+//! Cyclic transmission and filtered reception via the SocketCAN broadcast manager (BCM).
+//!
+//! The kernel sends the frame of a TX job every interval until the job is deleted,
+//! no user space timer is required. Jobs are bound to the BCM socket they were set up on
+//! and identified by their CAN id, closing the socket deletes all of its jobs.
+//! The `tx_*` functions take the raw file descriptor of an open BCM socket,
+//! e.g. `tokio_socketcan_bcm::BCMSocket::fd` or `BcmSocket::as_raw_fd`.
+use crate::frame::{Frame, CAN_MAX_DLEN};
+use crate::sys::{self, CanFrame, Socket};
+
+use libc::c_long;
+
+use std::io;
+use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::Duration;
+
+pub use crate::frame::CAN_EFF_FLAG;
+
+const TX_SETUP: u32 = 1;
+const TX_DELETE: u32 = 2;
+const TX_SEND: u32 = 4;
+const RX_SETUP: u32 = 5;
+const RX_DELETE: u32 = 6;
+const RX_TIMEOUT: u32 = 11;
+const RX_CHANGED: u32 = 12;
+
+const SETTIMER: u32 = 0x0001;
+const STARTTIMER: u32 = 0x0002;
+const TX_ANNOUNCE: u32 = 0x0008;
+const RX_FILTER_ID: u32 = 0x0020;
+
+/// `struct bcm_timeval`, unlike `timeval` always two `long`s
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct BcmTimeval {
+    tv_sec: c_long,
+    tv_usec: c_long,
+}
+
+impl From<Duration> for BcmTimeval {
+    fn from(duration: Duration) -> Self {
+        BcmTimeval {
+            tv_sec: duration.as_secs() as c_long,
+            tv_usec: duration.subsec_micros() as c_long,
+        }
+    }
+}
+
+/// `struct bcm_msg_head` followed by a single frame
+#[repr(C)]
+#[derive(Default)]
+struct BcmMsg {
+    opcode: u32,
+    flags: u32,
+    count: u32,
+    ival1: BcmTimeval,
+    ival2: BcmTimeval,
+    can_id: u32,
+    nframes: u32,
+    frame: CanFrame,
+}
+
+// The layout of the kernel ABI, the frame directly follows the header
+const _: () = {
+    use std::mem::{align_of, offset_of};
+
+    assert!(size_of::<BcmTimeval>() == size_of::<libc::bcm_timeval>());
+    assert!(offset_of!(BcmMsg, opcode) == offset_of!(libc::bcm_msg_head, opcode));
+    assert!(offset_of!(BcmMsg, flags) == offset_of!(libc::bcm_msg_head, flags));
+    assert!(offset_of!(BcmMsg, count) == offset_of!(libc::bcm_msg_head, count));
+    assert!(offset_of!(BcmMsg, ival1) == offset_of!(libc::bcm_msg_head, ival1));
+    assert!(offset_of!(BcmMsg, ival2) == offset_of!(libc::bcm_msg_head, ival2));
+    assert!(offset_of!(BcmMsg, can_id) == offset_of!(libc::bcm_msg_head, can_id));
+    assert!(offset_of!(BcmMsg, nframes) == offset_of!(libc::bcm_msg_head, nframes));
+    assert!(offset_of!(BcmMsg, frame) == offset_of!(libc::bcm_msg_head, frames));
+    assert!(size_of::<BcmMsg>() == size_of::<libc::bcm_msg_head>() + size_of::<libc::can_frame>());
+    assert!(align_of::<BcmMsg>() == align_of::<libc::bcm_msg_head>());
+};
+
+// SAFETY: `repr(C)` struct of integers, `BcmTimeval`s of two `long`s and a `CanFrame`
+unsafe impl sys::Plain for BcmMsg {}
+
+impl BcmMsg {
+    fn new(opcode: u32, flags: u32, can_id: u32) -> Self {
+        BcmMsg {
+            opcode,
+            flags,
+            can_id,
+            ..BcmMsg::default()
+        }
+    }
+
+    fn with_frame(mut self, data: &[u8]) -> io::Result<Self> {
+        if data.len() > CAN_MAX_DLEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "BCM transmission supports at most {} data bytes, got {}",
+                    CAN_MAX_DLEN,
+                    data.len()
+                ),
+            ));
+        }
+        self.nframes = 1;
+        self.frame.can_id = self.can_id;
+        self.frame.can_dlc = data.len() as u8;
+        self.frame.data[..data.len()].copy_from_slice(data);
+        Ok(self)
+    }
+
+    fn write(&self, fd: RawFd) -> io::Result<()> {
+        let len = if self.nframes == 0 {
+            size_of::<BcmMsg>() - size_of::<CanFrame>()
+        } else {
+            size_of::<BcmMsg>()
+        };
+        sys::write(fd, self, len)
+    }
+}
+
+/// CAN id as used by the kernel, DBC ids above the standard range without extended flag
+/// are sent as extended frames.
+fn kernel_id(can_id: u32) -> u32 {
+    if can_id > 0x7FF {
+        can_id | CAN_EFF_FLAG
+    } else {
+        can_id
+    }
+}
+
+/// Start sending `data` every `interval`, replacing a running job with the same id.
+/// `can_id` is the DBC message id, extended ids have bit 31 (`CAN_EFF_FLAG`) set.
+pub fn tx_setup(fd: RawFd, can_id: u32, data: &[u8], interval: Duration) -> io::Result<()> {
+    let mut msg = BcmMsg::new(TX_SETUP, SETTIMER | STARTTIMER, kernel_id(can_id));
+    msg.ival2 = interval.into();
+    msg.with_frame(data)?.write(fd)
+}
+
+/// Replace the data of a running job, the new data is sent immediately
+/// and the interval is kept.
+pub fn tx_update(fd: RawFd, can_id: u32, data: &[u8]) -> io::Result<()> {
+    BcmMsg::new(TX_SETUP, TX_ANNOUNCE, kernel_id(can_id))
+        .with_frame(data)?
+        .write(fd)
+}
+
+/// Stop a running job.
+pub fn tx_delete(fd: RawFd, can_id: u32) -> io::Result<()> {
+    BcmMsg::new(TX_DELETE, 0, kernel_id(can_id)).write(fd)
+}
+
+/// Blocking BCM socket, see the `tokio` and `async_std` modules for streams.
+#[derive(Debug)]
+pub struct BcmSocket {
+    socket: Socket,
+}
+
+impl BcmSocket {
+    /// Open a BCM socket on a CAN interface e.g. `vcan0`.
+    pub fn open(ifname: &str) -> io::Result<BcmSocket> {
+        Ok(BcmSocket {
+            socket: Socket::open(ifname, sys::CAN_BCM)?,
+        })
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    /// See `tx_setup`
+    pub fn tx_setup(&self, can_id: u32, data: &[u8], interval: Duration) -> io::Result<()> {
+        tx_setup(self.as_raw_fd(), can_id, data, interval)
+    }
+
+    /// See `tx_update`
+    pub fn tx_update(&self, can_id: u32, data: &[u8]) -> io::Result<()> {
+        tx_update(self.as_raw_fd(), can_id, data)
+    }
+
+    /// See `tx_delete`
+    pub fn tx_delete(&self, can_id: u32) -> io::Result<()> {
+        tx_delete(self.as_raw_fd(), can_id)
+    }
+
+    /// Send a single frame.
+    pub fn send_frame(&self, frame: &Frame) -> io::Result<()> {
+        BcmMsg::new(TX_SEND, 0, frame.raw_id())
+            .with_frame(frame.data())?
+            .write(self.as_raw_fd())
+    }
+
+    /// Receive frames with `can_id` (DBC message id) on this socket.
+    /// If `timeout` is not zero and no frame is received within it, `read_frame`
+    /// fails with `TimedOut`. If `throttle` is not zero, at most one frame per
+    /// `throttle` interval is received.
+    pub fn rx_setup(&self, can_id: u32, timeout: Duration, throttle: Duration) -> io::Result<()> {
+        let mut msg = BcmMsg::new(RX_SETUP, SETTIMER | RX_FILTER_ID, kernel_id(can_id));
+        msg.ival1 = timeout.into();
+        msg.ival2 = throttle.into();
+        msg.write(self.as_raw_fd())
+    }
+
+    /// Stop receiving frames with `can_id`.
+    pub fn rx_delete(&self, can_id: u32) -> io::Result<()> {
+        BcmMsg::new(RX_DELETE, 0, kernel_id(can_id)).write(self.as_raw_fd())
+    }
+
+    /// Read the next frame received due to `rx_setup`.
+    pub fn read_frame(&self) -> io::Result<Frame> {
+        loop {
+            let mut msg = BcmMsg::default();
+            let len = sys::read(self.as_raw_fd(), &mut msg)?;
+            match msg.opcode {
+                RX_CHANGED if len == size_of::<BcmMsg>() && msg.nframes > 0 => {
+                    return msg.frame.to_frame()
+                }
+                RX_TIMEOUT => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("no frame with id {:#X} received", msg.can_id),
+                    ))
+                }
+                // Status messages of other operations
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl AsRawFd for BcmSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.fd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sys::tests::socket_pair;
+    use crate::vcan_interface;
+
+    /// Message written by `write` to the other end of a socket pair
+    fn written(write: impl FnOnce(RawFd) -> io::Result<()>) -> (BcmMsg, usize) {
+        let (a, b) = socket_pair();
+        write(a.fd).unwrap();
+        let mut msg = BcmMsg::default();
+        let len = sys::read(b.fd, &mut msg).unwrap();
+        (msg, len)
+    }
+
+    #[test]
+    fn tx_setup_message() {
+        let (msg, len) =
+            written(|fd| tx_setup(fd, 0x18FE_F100, &[1, 2, 3], Duration::from_millis(1500)));
+        assert_eq!(len, size_of::<BcmMsg>());
+        assert_eq!((msg.opcode, msg.flags), (TX_SETUP, SETTIMER | STARTTIMER));
+        assert_eq!((msg.ival2.tv_sec, msg.ival2.tv_usec), (1, 500_000));
+        assert_eq!(msg.can_id, 0x18FE_F100 | CAN_EFF_FLAG);
+        assert_eq!(msg.nframes, 1);
+        assert_eq!(msg.frame.can_id, msg.can_id);
+        assert_eq!(msg.frame.to_frame().unwrap().data(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn tx_delete_message() {
+        let (msg, len) = written(|fd| tx_delete(fd, 0x123));
+        assert_eq!(len, size_of::<BcmMsg>() - size_of::<CanFrame>());
+        assert_eq!((msg.opcode, msg.can_id, msg.nframes), (TX_DELETE, 0x123, 0));
+    }
+
+    #[test]
+    fn tx_setup_too_long() {
+        let (a, _b) = socket_pair();
+        let error = tx_setup(a.fd, 0x123, &[0; 9], Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn cyclic_transmission() {
+        let ifname = match vcan_interface() {
+            Some(ifname) => ifname,
+            None => return,
+        };
+        let receiver = crate::raw::CanSocket::open(&ifname).unwrap();
+        let socket = BcmSocket::open(&ifname).unwrap();
+        socket
+            .tx_setup(0x123, &[1, 2], Duration::from_millis(10))
+            .unwrap();
+        let frame = Frame::new(0x123, &[1, 2]).unwrap();
+        assert_eq!(receiver.read_frame().unwrap(), frame);
+        assert_eq!(receiver.read_frame().unwrap(), frame);
+
+        socket.tx_update(0x123, &[3, 4]).unwrap();
+        let updated = Frame::new(0x123, &[3, 4]).unwrap();
+        while receiver.read_frame().unwrap() != updated {}
+        socket.tx_delete(0x123).unwrap();
+    }
+
+    #[test]
+    fn filtered_reception() {
+        let ifname = match vcan_interface() {
+            Some(ifname) => ifname,
+            None => return,
+        };
+        let sender = crate::raw::CanSocket::open(&ifname).unwrap();
+        let socket = BcmSocket::open(&ifname).unwrap();
+        socket
+            .rx_setup(0x18FE_F100, Duration::from_secs(1), Duration::ZERO)
+            .unwrap();
+
+        sender
+            .write_frame(&Frame::new(0x124, &[0]).unwrap())
+            .unwrap();
+        let frame = Frame::new(0x18FE_F100, &[5, 6, 7, 8, 9, 10, 11, 12]).unwrap();
+        sender.write_frame(&frame).unwrap();
+        assert_eq!(socket.read_frame().unwrap(), frame);
+    }
+
+    #[test]
+    fn reception_timeout() {
+        let ifname = match vcan_interface() {
+            Some(ifname) => ifname,
+            None => return,
+        };
+        let socket = BcmSocket::open(&ifname).unwrap();
+        socket
+            .rx_setup(0x7EF, Duration::from_millis(20), Duration::ZERO)
+            .unwrap();
+        let error = socket.read_frame().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
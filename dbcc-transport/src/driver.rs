@@ -0,0 +1,114 @@
+//! Drives the protocols without I/O (`j1939::Tp`, `isotp::IsoTp`) over a `CanTransport`.
+use crate::frame::Frame;
+use crate::transport::CanTransport;
+
+use futures_timer::Delay;
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+
+/// Protocol state machine that is passed frames and the current time and hands out frames to send.
+pub(crate) trait Protocol {
+    type Event;
+
+    fn handle_frame(&mut self, frame: &Frame, now: Instant);
+
+    fn handle_timeout(&mut self, now: Instant);
+
+    fn next_timeout(&self) -> Option<Instant>;
+
+    fn poll_transmit(&mut self, now: Instant) -> Option<Frame>;
+
+    fn poll_event(&mut self) -> Option<Self::Event>;
+}
+
+#[derive(Debug)]
+pub(crate) struct Driver<P, T> {
+    pub(crate) protocol: P,
+    pub(crate) transport: T,
+    /// Frame taken from `protocol` that the transport did not accept yet
+    pending: Option<Frame>,
+    delay: Option<Delay>,
+}
+
+impl<P: Protocol, T: CanTransport + Unpin> Driver<P, T> {
+    pub(crate) fn new(protocol: P, transport: T) -> Self {
+        Driver {
+            protocol,
+            transport,
+            pending: None,
+            delay: None,
+        }
+    }
+
+    /// Send frames, receive frames and handle timeouts until an event is available.
+    pub(crate) fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<P::Event>> {
+        loop {
+            let now = Instant::now();
+            self.protocol.handle_timeout(now);
+            // Responses, e.g. acknowledgements or flow control, go out before the event
+            let flushed = match self.poll_flush(cx) {
+                Poll::Ready(result) => {
+                    result?;
+                    true
+                }
+                Poll::Pending => false,
+            };
+            if let Some(event) = self.protocol.poll_event() {
+                return Poll::Ready(Ok(event));
+            }
+
+            match self.transport.poll_recv(cx) {
+                Poll::Ready(Some(Ok(frame))) => {
+                    self.protocol.handle_frame(&frame, Instant::now());
+                    continue;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                Poll::Ready(None) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the transport was closed",
+                    )))
+                }
+                Poll::Pending => {}
+            }
+
+            // The transport wakes the task once it accepts frames again, the timer otherwise
+            if flushed {
+                if let Some(timeout) = self.protocol.next_timeout() {
+                    let duration = timeout.saturating_duration_since(now);
+                    let delay = match &mut self.delay {
+                        Some(delay) => {
+                            delay.reset(duration);
+                            delay
+                        }
+                        None => self.delay.insert(Delay::new(duration)),
+                    };
+                    if Pin::new(delay).poll(cx).is_ready() {
+                        continue;
+                    }
+                }
+            }
+            return Poll::Pending;
+        }
+    }
+
+    /// Send all frames `protocol` has to send now.
+    pub(crate) fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if self.pending.is_none() {
+                self.pending = self.protocol.poll_transmit(Instant::now());
+            }
+            match self.pending {
+                Some(frame) => {
+                    ready!(self.transport.poll_send(cx, &frame))?;
+                    self.pending = None;
+                }
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
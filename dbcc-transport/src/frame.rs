@@ -0,0 +1,79 @@
+use std::io;
+
+/// Extended frame format flag, also used in DBC message ids
+pub const CAN_EFF_FLAG: u32 = 0x8000_0000;
+
+/// Mask of a 29 bit extended CAN id
+pub const CAN_EFF_MASK: u32 = 0x1FFF_FFFF;
+
+/// Maximum payload of a classic CAN frame
+pub const CAN_MAX_DLEN: usize = 8;
+
+/// `Frame::raw_id` of a frame sent with a DBC message id, see `Frame::new`.
+pub(crate) fn raw_id(id: u32) -> u32 {
+    if id & CAN_EFF_FLAG != 0 || id > 0x7FF {
+        (id & CAN_EFF_MASK) | CAN_EFF_FLAG
+    } else {
+        id
+    }
+}
+
+/// Classic CAN data frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Id including `CAN_EFF_FLAG` for extended frames
+    id: u32,
+    len: u8,
+    data: [u8; CAN_MAX_DLEN],
+}
+
+impl Frame {
+    /// Frame with a DBC message id, ids above the standard range or with bit 31
+    /// (`CAN_EFF_FLAG`) set are extended frames.
+    pub fn new(id: u32, data: &[u8]) -> io::Result<Frame> {
+        if data.len() > CAN_MAX_DLEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "CAN frames carry at most {} data bytes, got {}",
+                    CAN_MAX_DLEN,
+                    data.len()
+                ),
+            ));
+        }
+        let mut frame = Frame {
+            id: raw_id(id),
+            len: data.len() as u8,
+            data: [0; CAN_MAX_DLEN],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Ok(frame)
+    }
+
+    /// Extended frame of a 29 bit id with 8 data bytes.
+    pub(crate) fn extended(id: u32, data: [u8; CAN_MAX_DLEN]) -> Frame {
+        Frame {
+            id: (id & CAN_EFF_MASK) | CAN_EFF_FLAG,
+            len: CAN_MAX_DLEN as u8,
+            data,
+        }
+    }
+
+    /// CAN id without the extended frame flag
+    pub fn id(&self) -> u32 {
+        self.id & CAN_EFF_MASK
+    }
+
+    /// Id as used in DBC files, extended ids have bit 31 (`CAN_EFF_FLAG`) set.
+    pub fn raw_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.id & CAN_EFF_FLAG != 0
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
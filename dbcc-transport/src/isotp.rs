@@ -0,0 +1,925 @@
+//! ISO-TP (ISO 15765-2) segmentation and reassembly of messages of up to 4095 bytes over classic CAN.
+//!
+//! `IsoTp` is one end of a connection between two CAN ids (normal addressing). It segments outgoing
+//! messages into a first frame and consecutive frames, paced by the flow control (block size and STmin)
+//! of the receiver, and reassembles incoming messages, sending flow control itself. Like `j1939::Tp`
+//! it does no I/O, `IsoTpTransport` drives it over any `CanTransport`, e.g. a SocketCAN socket or `mem` channel.
+use crate::driver::{Driver, Protocol};
+use crate::frame::{self, Frame, CAN_MAX_DLEN};
+use crate::transport::CanTransport;
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::future::poll_fn;
+use std::io;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Largest message with a 12 bit first frame length
+pub const MAX_SIZE: usize = 4095;
+
+/// Protocol control information types, the high nibble of the first byte
+const SINGLE_FRAME: u8 = 0x0;
+const FIRST_FRAME: u8 = 0x1;
+const CONSECUTIVE_FRAME: u8 = 0x2;
+const FLOW_CONTROL: u8 = 0x3;
+
+/// Flow status of a flow control frame
+const FS_CONTINUE: u8 = 0;
+const FS_WAIT: u8 = 1;
+const FS_OVERFLOW: u8 = 2;
+
+/// Payload bytes of a single frame
+const SINGLE_FRAME_SIZE: usize = 7;
+/// Payload bytes of a first frame
+const FIRST_FRAME_SIZE: usize = 6;
+/// Payload bytes of a consecutive frame
+const CONSECUTIVE_FRAME_SIZE: usize = 7;
+
+/// Sender timeout waiting for flow control
+const N_BS: Duration = Duration::from_millis(1000);
+/// Receiver timeout waiting for a consecutive frame
+const N_CR: Duration = Duration::from_millis(1000);
+
+/// Reason a transfer failed (`N_Result` of ISO 15765-2).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferError {
+    /// No flow control received in time (N_Bs)
+    FlowControlTimeout,
+    /// No consecutive frame received in time (N_Cr)
+    ConsecutiveFrameTimeout,
+    /// Consecutive frame with a sequence number other than the next one
+    WrongSequenceNumber,
+    /// Flow control with a reserved flow status
+    InvalidFlowStatus,
+    /// The receiver cannot take a message of this size
+    Overflow,
+    /// Single frame or first frame received while receiving a message, it replaces the message
+    UnexpectedFrame,
+    /// A frame to send could not be built
+    InvalidFrame,
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransferError::FlowControlTimeout => write!(f, "timeout waiting for flow control"),
+            TransferError::ConsecutiveFrameTimeout => {
+                write!(f, "timeout waiting for a consecutive frame")
+            }
+            TransferError::WrongSequenceNumber => write!(f, "wrong sequence number"),
+            TransferError::InvalidFlowStatus => write!(f, "invalid flow status"),
+            TransferError::Overflow => write!(f, "message too large for the receiver"),
+            TransferError::UnexpectedFrame => write!(f, "new message received while receiving"),
+            TransferError::InvalidFrame => write!(f, "invalid frame"),
+        }
+    }
+}
+
+impl error::Error for TransferError {}
+
+/// Outcome of frames, timeouts and sending handled by `IsoTp`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Message received in a single frame or reassembled from consecutive frames
+    Received(Vec<u8>),
+    /// Message sent, `poll_transmit` handed out its last frame
+    Sent,
+    /// Sending the message failed
+    SendFailed(TransferError),
+    /// Receiving a message failed, the part received is dropped
+    ReceiveFailed(TransferError),
+}
+
+/// Message being received.
+#[derive(Debug)]
+struct RxSession {
+    size: usize,
+    data: Vec<u8>,
+    /// Sequence number of the next consecutive frame
+    next: u8,
+    /// Consecutive frames until the next flow control, unused for a block size of 0
+    remaining: u8,
+    deadline: Instant,
+}
+
+/// Message being sent.
+#[derive(Debug)]
+struct TxSession {
+    data: Vec<u8>,
+    /// Bytes sent
+    offset: usize,
+    /// Sequence number of the next consecutive frame
+    next: u8,
+    /// Minimum time between consecutive frames requested by the receiver
+    separation: Duration,
+    state: TxState,
+}
+
+#[derive(Debug)]
+enum TxState {
+    /// Waiting for flow control until the deadline
+    Waiting(Instant),
+    /// Sending the next frame not before `at`, `remaining` frames until the next
+    /// flow control or all if `None`
+    Sending { at: Instant, remaining: Option<u8> },
+}
+
+/// ISO-TP connection sending with `tx_id` and receiving with `rx_id`, both DBC message ids
+/// as taken by `Frame::new`.
+///
+/// One message can be sent and one received at a time. Frames of other ids are ignored.
+///
+/// Example:
+/// ```
+/// use dbcc_transport::isotp::{Event, IsoTp};
+/// use std::time::Instant;
+///
+/// let mut tester = IsoTp::new(0x7E0, 0x7E8);
+/// let mut ecu = IsoTp::new(0x7E8, 0x7E0);
+/// ecu.set_block_size(2);
+/// let now = Instant::now();
+///
+/// let data: Vec<u8> = (0..20).collect();
+/// tester.send(&data, now).unwrap();
+///
+/// // First frame, flow control, 2 consecutive frames, flow control and the last consecutive frame
+/// loop {
+///     let mut idle = true;
+///     while let Some(frame) = tester.poll_transmit(now) {
+///         ecu.handle_frame(&frame, now);
+///         idle = false;
+///     }
+///     while let Some(frame) = ecu.poll_transmit(now) {
+///         tester.handle_frame(&frame, now);
+///         idle = false;
+///     }
+///     if idle {
+///         break;
+///     }
+/// }
+///
+/// assert_eq!(ecu.poll_event(), Some(Event::Received(data)));
+/// assert_eq!(tester.poll_event(), Some(Event::Sent));
+/// ```
+#[derive(Debug)]
+pub struct IsoTp {
+    /// `Frame::raw_id` of frames sent
+    tx_id: u32,
+    /// `Frame::raw_id` of frames received
+    rx_id: u32,
+    block_size: u8,
+    /// STmin as sent in flow control
+    st_min: u8,
+    padding: Option<u8>,
+    rx: Option<RxSession>,
+    tx: Option<TxSession>,
+    frames: VecDeque<Frame>,
+    events: VecDeque<Event>,
+}
+
+impl IsoTp {
+    pub fn new(tx_id: u32, rx_id: u32) -> IsoTp {
+        IsoTp {
+            tx_id: frame::raw_id(tx_id),
+            rx_id: frame::raw_id(rx_id),
+            block_size: 0,
+            st_min: 0,
+            padding: None,
+            rx: None,
+            tx: None,
+            frames: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Id of the frames sent, with `CAN_EFF_FLAG` for extended ids
+    pub fn tx_id(&self) -> u32 {
+        self.tx_id
+    }
+
+    /// Id of the frames received, with `CAN_EFF_FLAG` for extended ids
+    pub fn rx_id(&self) -> u32 {
+        self.rx_id
+    }
+
+    /// Consecutive frames the sender may send per flow control when receiving, 0 (default) for all.
+    pub fn set_block_size(&mut self, block_size: u8) {
+        self.block_size = block_size;
+    }
+
+    /// Minimum time between consecutive frames requested when receiving, 0 by default.
+    /// Times below 1 ms are rounded down to 100 µs steps, times above 127 ms are limited to 127 ms.
+    pub fn set_st_min(&mut self, st_min: Duration) {
+        self.st_min = encode_st_min(st_min);
+    }
+
+    /// Pad frames to 8 bytes with `padding`, frames are as short as possible if `None` (default).
+    pub fn set_padding(&mut self, padding: Option<u8>) {
+        self.padding = padding;
+    }
+
+    /// Send a message of 1 to 4095 bytes, in a single frame if it fits.
+    /// Fails with `WouldBlock` while another message is being sent.
+    pub fn send(&mut self, data: &[u8], now: Instant) -> io::Result<()> {
+        if data.is_empty() || data.len() > MAX_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "ISO-TP messages carry 1 to {} bytes, got {}",
+                    MAX_SIZE,
+                    data.len()
+                ),
+            ));
+        }
+        if self.tx.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "an ISO-TP message is being sent",
+            ));
+        }
+        self.tx = Some(TxSession {
+            data: data.to_vec(),
+            offset: 0,
+            next: 1,
+            separation: Duration::from_millis(0),
+            state: TxState::Sending {
+                at: now,
+                remaining: None,
+            },
+        });
+        Ok(())
+    }
+
+    /// Handle a received frame, frames of other ids are ignored.
+    pub fn handle_frame(&mut self, frame: &Frame, now: Instant) {
+        let data = frame.data();
+        if frame.raw_id() != self.rx_id || data.is_empty() {
+            return;
+        }
+        match data[0] >> 4 {
+            SINGLE_FRAME => {
+                let size = usize::from(data[0] & 0xF);
+                // A size of 0 is the escape of CAN FD single frames
+                if size == 0 || size >= data.len() {
+                    return;
+                }
+                self.replace_rx();
+                self.events
+                    .push_back(Event::Received(data[1..=size].to_vec()));
+            }
+            FIRST_FRAME if data.len() == CAN_MAX_DLEN => {
+                self.replace_rx();
+                let size = usize::from(data[0] & 0xF) << 8 | usize::from(data[1]);
+                // A size of 0 is the escape of messages above 4095 bytes
+                if size == 0 {
+                    self.send_flow_control(FS_OVERFLOW);
+                    return;
+                }
+                if size <= SINGLE_FRAME_SIZE {
+                    return;
+                }
+                let mut received = Vec::with_capacity(size);
+                received.extend_from_slice(&data[2..]);
+                self.rx = Some(RxSession {
+                    size,
+                    data: received,
+                    next: 1,
+                    remaining: self.block_size,
+                    deadline: now + N_CR,
+                });
+                self.send_flow_control(FS_CONTINUE);
+            }
+            CONSECUTIVE_FRAME => self.handle_consecutive_frame(data, now),
+            FLOW_CONTROL if data.len() >= 3 => self.handle_flow_control(data, now),
+            _ => {}
+        }
+    }
+
+    /// Fail transfers whose peer did not respond in time.
+    pub fn handle_timeout(&mut self, now: Instant) {
+        if matches!(&self.rx, Some(session) if session.deadline <= now) {
+            self.rx = None;
+            self.events
+                .push_back(Event::ReceiveFailed(TransferError::ConsecutiveFrameTimeout));
+        }
+        if matches!(&self.tx, Some(TxSession { state: TxState::Waiting(deadline), .. }) if *deadline <= now)
+        {
+            self.tx = None;
+            self.events
+                .push_back(Event::SendFailed(TransferError::FlowControlTimeout));
+        }
+    }
+
+    /// Earliest time `handle_timeout` fails a transfer or `poll_transmit` has a frame to send.
+    pub fn next_timeout(&self) -> Option<Instant> {
+        let rx = self.rx.as_ref().map(|session| session.deadline);
+        let tx = self.tx.as_ref().map(|session| match session.state {
+            TxState::Waiting(deadline) => deadline,
+            TxState::Sending { at, .. } => at,
+        });
+        rx.into_iter().chain(tx).min()
+    }
+
+    /// Next frame to send at `now`.
+    pub fn poll_transmit(&mut self, now: Instant) -> Option<Frame> {
+        if let Some(frame) = self.frames.pop_front() {
+            return Some(frame);
+        }
+
+        let session = self.tx.as_mut()?;
+        let remaining = match session.state {
+            TxState::Sending { at, remaining } if at <= now => remaining,
+            _ => return None,
+        };
+        let size = session.data.len();
+        let mut data = [0; CAN_MAX_DLEN];
+        let len = if session.offset == 0 && size <= SINGLE_FRAME_SIZE {
+            data[0] = SINGLE_FRAME << 4 | size as u8;
+            data[1..=size].copy_from_slice(&session.data);
+            session.offset = size;
+            size + 1
+        } else if session.offset == 0 {
+            data[0] = FIRST_FRAME << 4 | (size >> 8) as u8;
+            data[1] = size as u8;
+            data[2..].copy_from_slice(&session.data[..FIRST_FRAME_SIZE]);
+            session.offset = FIRST_FRAME_SIZE;
+            session.state = TxState::Waiting(now + N_BS);
+            CAN_MAX_DLEN
+        } else {
+            let end = (session.offset + CONSECUTIVE_FRAME_SIZE).min(size);
+            let payload = &session.data[session.offset..end];
+            data[0] = CONSECUTIVE_FRAME << 4 | session.next;
+            data[1..=payload.len()].copy_from_slice(payload);
+            session.offset = end;
+            session.next = (session.next + 1) & 0xF;
+            session.state = match remaining {
+                Some(1) => TxState::Waiting(now + N_BS),
+                remaining => TxState::Sending {
+                    at: now + session.separation,
+                    remaining: remaining.map(|remaining| remaining - 1),
+                },
+            };
+            payload.len() + 1
+        };
+        let sent = session.offset == size;
+        match self.frame(&data[..len]) {
+            Ok(frame) => {
+                if sent {
+                    self.tx = None;
+                    self.events.push_back(Event::Sent);
+                }
+                Some(frame)
+            }
+            Err(_) => {
+                self.tx = None;
+                self.events
+                    .push_back(Event::SendFailed(TransferError::InvalidFrame));
+                None
+            }
+        }
+    }
+
+    /// Next received message or outcome of sending one.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    fn handle_consecutive_frame(&mut self, data: &[u8], now: Instant) {
+        let session = match &mut self.rx {
+            Some(session) => session,
+            // Consecutive frames of messages not received by this end
+            None => return,
+        };
+        if data[0] & 0xF != session.next {
+            self.rx = None;
+            self.events
+                .push_back(Event::ReceiveFailed(TransferError::WrongSequenceNumber));
+            return;
+        }
+
+        let payload = &data[1..];
+        let remaining = session.size - session.data.len();
+        session
+            .data
+            .extend_from_slice(&payload[..payload.len().min(remaining)]);
+        if session.data.len() == session.size {
+            if let Some(session) = self.rx.take() {
+                self.events.push_back(Event::Received(session.data));
+            }
+            return;
+        }
+
+        session.next = (session.next + 1) & 0xF;
+        session.deadline = now + N_CR;
+        if self.block_size != 0 {
+            session.remaining = session.remaining.saturating_sub(1);
+            if session.remaining == 0 {
+                session.remaining = self.block_size;
+                self.send_flow_control(FS_CONTINUE);
+            }
+        }
+    }
+
+    fn handle_flow_control(&mut self, data: &[u8], now: Instant) {
+        let session = match &mut self.tx {
+            Some(session) if matches!(session.state, TxState::Waiting(_)) => session,
+            // Flow control while not waiting for it is ignored
+            _ => return,
+        };
+        match data[0] & 0xF {
+            FS_CONTINUE => {
+                session.separation = decode_st_min(data[2]);
+                session.state = TxState::Sending {
+                    at: now,
+                    remaining: if data[1] == 0 { None } else { Some(data[1]) },
+                };
+            }
+            FS_WAIT => session.state = TxState::Waiting(now + N_BS),
+            flow_status => {
+                self.tx = None;
+                let error = if flow_status == FS_OVERFLOW {
+                    TransferError::Overflow
+                } else {
+                    TransferError::InvalidFlowStatus
+                };
+                self.events.push_back(Event::SendFailed(error));
+            }
+        }
+    }
+
+    /// Drop the message being received for a new one.
+    fn replace_rx(&mut self) {
+        if self.rx.take().is_some() {
+            self.events
+                .push_back(Event::ReceiveFailed(TransferError::UnexpectedFrame));
+        }
+    }
+
+    fn send_flow_control(&mut self, flow_status: u8) {
+        let data = [
+            FLOW_CONTROL << 4 | flow_status,
+            self.block_size,
+            self.st_min,
+        ];
+        match self.frame(&data) {
+            Ok(frame) => self.frames.push_back(frame),
+            Err(_) => {
+                self.rx = None;
+                self.events
+                    .push_back(Event::ReceiveFailed(TransferError::InvalidFrame));
+            }
+        }
+    }
+
+    /// Frame with `tx_id`, padded if padding is enabled.
+    fn frame(&self, data: &[u8]) -> io::Result<Frame> {
+        match self.padding {
+            Some(padding) if data.len() < CAN_MAX_DLEN => {
+                let mut padded = [padding; CAN_MAX_DLEN];
+                padded[..data.len()].copy_from_slice(data);
+                Frame::new(self.tx_id, &padded)
+            }
+            // Frame::new fails for more than 8 bytes
+            _ => Frame::new(self.tx_id, data),
+        }
+    }
+}
+
+impl Protocol for IsoTp {
+    type Event = Event;
+
+    fn handle_frame(&mut self, frame: &Frame, now: Instant) {
+        IsoTp::handle_frame(self, frame, now)
+    }
+
+    fn handle_timeout(&mut self, now: Instant) {
+        IsoTp::handle_timeout(self, now)
+    }
+
+    fn next_timeout(&self) -> Option<Instant> {
+        IsoTp::next_timeout(self)
+    }
+
+    fn poll_transmit(&mut self, now: Instant) -> Option<Frame> {
+        IsoTp::poll_transmit(self, now)
+    }
+
+    fn poll_event(&mut self) -> Option<Event> {
+        IsoTp::poll_event(self)
+    }
+}
+
+/// STmin byte of a flow control frame: 0 to 127 ms, or 100 to 900 µs as `0xF1` to `0xF9`.
+fn encode_st_min(st_min: Duration) -> u8 {
+    if st_min >= Duration::from_millis(1) {
+        st_min.as_millis().min(0x7F) as u8
+    } else if st_min >= Duration::from_micros(100) {
+        0xF0 + (st_min.as_micros() / 100) as u8
+    } else {
+        0
+    }
+}
+
+fn decode_st_min(st_min: u8) -> Duration {
+    match st_min {
+        0x00..=0x7F => Duration::from_millis(u64::from(st_min)),
+        0xF1..=0xF9 => Duration::from_micros(u64::from(st_min - 0xF0) * 100),
+        // Reserved values mean the longest time
+        _ => Duration::from_millis(0x7F),
+    }
+}
+
+/// `IsoTp` driven over a transport, timeouts work with any executor.
+///
+/// Example:
+/// ```
+/// use dbcc_transport::isotp::{IsoTp, IsoTpTransport};
+/// use dbcc_transport::mem;
+///
+/// let (a, b) = mem::channel();
+/// let mut tester = IsoTpTransport::new(a, IsoTp::new(0x7E0, 0x7E8));
+/// let mut ecu = IsoTpTransport::new(b, IsoTp::new(0x7E8, 0x7E0));
+/// ecu.isotp_mut().set_block_size(4);
+///
+/// let receiver = std::thread::spawn(move || futures_executor::block_on(ecu.recv()));
+/// let data: Vec<u8> = (0..100).collect();
+/// futures_executor::block_on(tester.send(&data)).unwrap();
+/// assert_eq!(receiver.join().unwrap().unwrap(), data);
+/// ```
+#[derive(Debug)]
+pub struct IsoTpTransport<T> {
+    driver: Driver<IsoTp, T>,
+    /// Events `send` received while waiting for its message to be sent
+    deferred: VecDeque<Event>,
+}
+
+impl<T: CanTransport + Unpin> IsoTpTransport<T> {
+    pub fn new(transport: T, isotp: IsoTp) -> Self {
+        IsoTpTransport {
+            driver: Driver::new(isotp, transport),
+            deferred: VecDeque::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.driver.transport
+    }
+
+    pub fn isotp(&self) -> &IsoTp {
+        &self.driver.protocol
+    }
+
+    pub fn isotp_mut(&mut self) -> &mut IsoTp {
+        &mut self.driver.protocol
+    }
+
+    pub fn into_inner(self) -> T {
+        self.driver.transport
+    }
+
+    /// Send a message and wait until its last frame is sent.
+    /// Fails with `ConnectionAborted` and the `TransferError` as inner error if the transfer fails.
+    pub async fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        self.driver.protocol.send(data, Instant::now())?;
+        loop {
+            match poll_fn(|cx| self.driver.poll_event(cx)).await? {
+                Event::Sent => return poll_fn(|cx| self.driver.poll_flush(cx)).await,
+                Event::SendFailed(error) => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionAborted, error))
+                }
+                event => self.deferred.push_back(event),
+            }
+        }
+    }
+
+    /// Receive the next message, failed receptions are skipped.
+    pub async fn recv(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Event::Received(data) = self.event().await? {
+                return Ok(data);
+            }
+        }
+    }
+
+    /// Next event, fails with `UnexpectedEof` once the transport is closed.
+    pub async fn event(&mut self) -> io::Result<Event> {
+        poll_fn(|cx| self.poll_event(cx)).await
+    }
+
+    /// Send frames, receive frames and handle timeouts until an event is available.
+    pub fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Event>> {
+        if let Some(event) = self.deferred.pop_front() {
+            return Poll::Ready(Ok(event));
+        }
+        self.driver.poll_event(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem;
+
+    use futures_executor::block_on;
+
+    const TESTER: u32 = 0x7E0;
+    const ECU: u32 = 0x7E8;
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    /// Flow control sent by the ECU
+    fn flow_control(data: &[u8]) -> Frame {
+        Frame::new(ECU, data).unwrap()
+    }
+
+    /// Pass frames between both ends until neither has one to send at `now`,
+    /// returns the frames sent by `a`
+    fn exchange(a: &mut IsoTp, b: &mut IsoTp, now: Instant) -> Vec<Frame> {
+        let mut sent = Vec::new();
+        loop {
+            let mut idle = true;
+            while let Some(frame) = a.poll_transmit(now) {
+                b.handle_frame(&frame, now);
+                sent.push(frame);
+                idle = false;
+            }
+            while let Some(frame) = b.poll_transmit(now) {
+                a.handle_frame(&frame, now);
+                idle = false;
+            }
+            if idle {
+                return sent;
+            }
+        }
+    }
+
+    #[test]
+    fn single_frame() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let mut ecu = IsoTp::new(ECU, TESTER);
+        let now = Instant::now();
+        tester.send(&[0x22, 0xF1, 0x90], now).unwrap();
+        let sent = exchange(&mut tester, &mut ecu, now);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].data(), &[0x03, 0x22, 0xF1, 0x90]);
+        assert_eq!(
+            ecu.poll_event(),
+            Some(Event::Received(vec![0x22, 0xF1, 0x90]))
+        );
+        assert_eq!(tester.poll_event(), Some(Event::Sent));
+    }
+
+    #[test]
+    fn message_size() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let now = Instant::now();
+        for len in &[0, MAX_SIZE + 1] {
+            let error = tester.send(&payload(*len), now).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        tester.send(&payload(20), now).unwrap();
+        let error = tester.send(&payload(20), now).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn padding() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        tester.set_padding(Some(0xCC));
+        let now = Instant::now();
+        tester.send(&[0x3E], now).unwrap();
+        let frame = tester.poll_transmit(now).unwrap();
+        assert_eq!(
+            frame.data(),
+            &[0x01, 0x3E, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC]
+        );
+    }
+
+    #[test]
+    fn block_size() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let mut ecu = IsoTp::new(ECU, TESTER);
+        ecu.set_block_size(2);
+        let now = Instant::now();
+        let data = payload(50);
+        tester.send(&data, now).unwrap();
+
+        // The first frame carries 6 bytes, 7 consecutive frames with flow control after every 2nd
+        let mut flow_controls = 0;
+        loop {
+            let mut idle = true;
+            while let Some(frame) = tester.poll_transmit(now) {
+                ecu.handle_frame(&frame, now);
+                idle = false;
+            }
+            while let Some(frame) = ecu.poll_transmit(now) {
+                assert_eq!(frame.data(), &[0x30, 2, 0]);
+                tester.handle_frame(&frame, now);
+                flow_controls += 1;
+                idle = false;
+            }
+            if idle {
+                break;
+            }
+        }
+        assert_eq!(flow_controls, 4);
+        assert_eq!(ecu.poll_event(), Some(Event::Received(data)));
+        assert_eq!(tester.poll_event(), Some(Event::Sent));
+    }
+
+    #[test]
+    fn st_min_encoding() {
+        let encoded: Vec<u8> = (1..=9)
+            .map(|steps| encode_st_min(Duration::from_micros(steps * 100)))
+            .collect();
+        assert_eq!(
+            encoded,
+            [0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9]
+        );
+        for st_min in 0xF1..=0xF9 {
+            assert_eq!(encode_st_min(decode_st_min(st_min)), st_min);
+        }
+        assert_eq!(encode_st_min(Duration::from_micros(99)), 0);
+        assert_eq!(encode_st_min(Duration::from_micros(150)), 0xF1);
+        assert_eq!(encode_st_min(Duration::from_millis(5)), 5);
+        assert_eq!(encode_st_min(Duration::from_millis(500)), 0x7F);
+        // Reserved values
+        assert_eq!(decode_st_min(0x80), Duration::from_millis(0x7F));
+        assert_eq!(decode_st_min(0xF0), Duration::from_millis(0x7F));
+        assert_eq!(decode_st_min(0xFA), Duration::from_millis(0x7F));
+    }
+
+    #[test]
+    fn st_min_separation() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let now = Instant::now();
+        tester.send(&payload(20), now).unwrap();
+        tester.poll_transmit(now).unwrap();
+
+        // 300 µs between consecutive frames
+        tester.handle_frame(&flow_control(&[0x30, 0, 0xF3]), now);
+        assert_eq!(tester.poll_transmit(now).unwrap().data()[0], 0x21);
+        assert_eq!(tester.poll_transmit(now), None);
+        let next = now + Duration::from_micros(300);
+        assert_eq!(tester.next_timeout(), Some(next));
+        assert_eq!(tester.poll_transmit(next).unwrap().data()[0], 0x22);
+    }
+
+    #[test]
+    fn sequence_wrap() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let mut ecu = IsoTp::new(ECU, TESTER);
+        let now = Instant::now();
+        // First frame and 20 consecutive frames
+        let data = payload(FIRST_FRAME_SIZE + 20 * CONSECUTIVE_FRAME_SIZE);
+        tester.send(&data, now).unwrap();
+        let sequences: Vec<u8> = exchange(&mut tester, &mut ecu, now)[1..]
+            .iter()
+            .map(|frame| frame.data()[0])
+            .collect();
+        let expected: Vec<u8> = (1..=20).map(|i| 0x20 | (i & 0xF)).collect();
+        assert_eq!(sequences, expected);
+        assert_eq!(sequences[14..17], [0x2F, 0x20, 0x21]);
+        assert_eq!(ecu.poll_event(), Some(Event::Received(data)));
+    }
+
+    #[test]
+    fn wrong_sequence_number() {
+        let mut ecu = IsoTp::new(ECU, TESTER);
+        let now = Instant::now();
+        ecu.handle_frame(
+            &Frame::new(TESTER, &[0x10, 20, 0, 1, 2, 3, 4, 5]).unwrap(),
+            now,
+        );
+        assert_eq!(ecu.poll_transmit(now).unwrap().data(), &[0x30, 0, 0]);
+        ecu.handle_frame(
+            &Frame::new(TESTER, &[0x22, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+            now,
+        );
+        assert_eq!(
+            ecu.poll_event(),
+            Some(Event::ReceiveFailed(TransferError::WrongSequenceNumber))
+        );
+        assert_eq!(ecu.next_timeout(), None);
+    }
+
+    #[test]
+    fn flow_control_timeout() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let now = Instant::now();
+        tester.send(&payload(20), now).unwrap();
+        tester.poll_transmit(now).unwrap();
+        assert_eq!(tester.next_timeout(), Some(now + N_BS));
+
+        tester.handle_timeout(now + N_BS - Duration::from_millis(1));
+        assert_eq!(tester.poll_event(), None);
+        tester.handle_timeout(now + N_BS);
+        assert_eq!(
+            tester.poll_event(),
+            Some(Event::SendFailed(TransferError::FlowControlTimeout))
+        );
+        // A new message can be sent
+        tester.send(&payload(20), now + N_BS).unwrap();
+    }
+
+    #[test]
+    fn consecutive_frame_timeout() {
+        let mut ecu = IsoTp::new(ECU, TESTER);
+        let now = Instant::now();
+        ecu.handle_frame(
+            &Frame::new(TESTER, &[0x10, 20, 0, 1, 2, 3, 4, 5]).unwrap(),
+            now,
+        );
+        let later = now + Duration::from_millis(500);
+        ecu.handle_frame(
+            &Frame::new(TESTER, &[0x21, 6, 7, 8, 9, 10, 11, 12]).unwrap(),
+            later,
+        );
+        assert_eq!(ecu.next_timeout(), Some(later + N_CR));
+
+        ecu.handle_timeout(later + N_CR - Duration::from_millis(1));
+        assert_eq!(ecu.poll_event(), None);
+        ecu.handle_timeout(later + N_CR);
+        assert_eq!(
+            ecu.poll_event(),
+            Some(Event::ReceiveFailed(TransferError::ConsecutiveFrameTimeout))
+        );
+    }
+
+    #[test]
+    fn flow_control_wait() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let now = Instant::now();
+        tester.send(&payload(20), now).unwrap();
+        tester.poll_transmit(now).unwrap();
+
+        // Each wait restarts N_Bs
+        let wait = now + Duration::from_millis(900);
+        tester.handle_frame(&flow_control(&[0x31, 0, 0]), wait);
+        assert_eq!(tester.poll_transmit(wait), None);
+        tester.handle_timeout(now + N_BS);
+        assert_eq!(tester.poll_event(), None);
+        assert_eq!(tester.next_timeout(), Some(wait + N_BS));
+
+        let resume = wait + Duration::from_millis(900);
+        tester.handle_frame(&flow_control(&[0x30, 0, 0]), resume);
+        let sequences: Vec<u8> = std::iter::from_fn(|| tester.poll_transmit(resume))
+            .map(|frame| frame.data()[0])
+            .collect();
+        assert_eq!(sequences, [0x21, 0x22]);
+        assert_eq!(tester.poll_event(), Some(Event::Sent));
+    }
+
+    #[test]
+    fn flow_control_overflow() {
+        let mut tester = IsoTp::new(TESTER, ECU);
+        let now = Instant::now();
+        tester.send(&payload(20), now).unwrap();
+        tester.poll_transmit(now).unwrap();
+        tester.handle_frame(&flow_control(&[0x32, 0, 0]), now);
+        assert_eq!(
+            tester.poll_event(),
+            Some(Event::SendFailed(TransferError::Overflow))
+        );
+        assert_eq!(tester.poll_transmit(now), None);
+
+        tester.send(&payload(20), now).unwrap();
+        tester.poll_transmit(now).unwrap();
+        tester.handle_frame(&flow_control(&[0x33, 0, 0]), now);
+        assert_eq!(
+            tester.poll_event(),
+            Some(Event::SendFailed(TransferError::InvalidFlowStatus))
+        );
+    }
+
+    #[test]
+    fn first_frame_escape() {
+        // A length of 0 announces a message above 4095 bytes
+        let mut ecu = IsoTp::new(ECU, TESTER);
+        let now = Instant::now();
+        ecu.handle_frame(
+            &Frame::new(TESTER, &[0x10, 0, 0, 0, 0x20, 0, 0, 0]).unwrap(),
+            now,
+        );
+        assert_eq!(ecu.poll_transmit(now).unwrap().data(), &[0x32, 0, 0]);
+        assert_eq!(ecu.poll_event(), None);
+    }
+
+    #[test]
+    fn over_channel() {
+        let (a, b) = mem::channel();
+        let mut tester = IsoTpTransport::new(a, IsoTp::new(TESTER, ECU));
+        let mut ecu = IsoTpTransport::new(b, IsoTp::new(ECU, TESTER));
+        ecu.isotp_mut().set_block_size(3);
+        ecu.isotp_mut().set_st_min(Duration::from_micros(100));
+
+        let receiver = std::thread::spawn(move || block_on(ecu.recv()));
+        let data = payload(MAX_SIZE);
+        block_on(tester.send(&data)).unwrap();
+        assert_eq!(receiver.join().unwrap().unwrap(), data);
+    }
+}
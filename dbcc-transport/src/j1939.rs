@@ -0,0 +1,1279 @@
+//! J1939 identifiers and the transport protocol of J1939-21 for parameter groups of up to 1785 bytes.
+//!
+//! `Tp` reassembles broadcast (BAM) and connection mode (RTS/CTS) sessions and segments outgoing
+//! parameter groups. It does no I/O itself, frames and the current time are passed in and frames to send
+//! are taken out. `TpTransport` drives it over any `CanTransport`, e.g. a SocketCAN socket or `vbus` endpoint.
+use crate::driver::{Driver, Protocol};
+use crate::frame::{Frame, CAN_EFF_FLAG, CAN_EFF_MASK, CAN_MAX_DLEN};
+use crate::transport::CanTransport;
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::future::poll_fn;
+use std::io;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// PGN of transport protocol connection management frames (TP.CM)
+pub const PGN_TP_CM: u32 = 0xEC00;
+
+/// PGN of transport protocol data transfer frames (TP.DT)
+pub const PGN_TP_DT: u32 = 0xEB00;
+
+/// Largest payload of the transport protocol
+pub const TP_MAX_SIZE: usize = 1785;
+
+/// Destination address of all nodes
+pub const GLOBAL_ADDRESS: u8 = 0xFF;
+
+/// Default priority of TP.CM and TP.DT frames
+const TP_PRIORITY: u8 = 7;
+
+/// Payload bytes per TP.DT frame
+const PACKET_SIZE: usize = 7;
+
+const CM_RTS: u8 = 16;
+const CM_CTS: u8 = 17;
+const CM_EOMA: u8 = 19;
+const CM_BAM: u8 = 32;
+const CM_ABORT: u8 = 255;
+
+/// Receiver timeout between data packets
+const T1: Duration = Duration::from_millis(750);
+/// Receiver timeout after sending CTS
+const T2: Duration = Duration::from_millis(1250);
+/// Originator timeout after RTS or the last packet requested by a CTS
+const T3: Duration = Duration::from_millis(1250);
+/// Originator timeout after a CTS holding the connection open
+const T4: Duration = Duration::from_millis(1050);
+/// Time between BAM frames, J1939-21 allows 50 to 200 ms
+const BAM_PACKET_INTERVAL: Duration = Duration::from_millis(50);
+
+/// J1939 identifier (29 bit) of priority, parameter group number (PGN) and addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Id(u32);
+
+impl Id {
+    /// Identifier of a parameter group, `destination_address` is ignored for broadcast (PDU2) PGNs.
+    pub fn new(priority: u8, pgn: u32, source_address: u8, destination_address: u8) -> Id {
+        let mut id =
+            u32::from(priority & 0x7) << 26 | (pgn & 0x3FFFF) << 8 | u32::from(source_address);
+        if is_pdu1(pgn) {
+            id = (id & !0xFF00) | u32::from(destination_address) << 8;
+        }
+        Id(id)
+    }
+
+    /// Identifier of a frame id with or without `CAN_EFF_FLAG`.
+    pub fn from_raw(id: u32) -> Id {
+        Id(id & CAN_EFF_MASK)
+    }
+
+    pub fn as_raw(self) -> u32 {
+        self.0
+    }
+
+    /// Id with `CAN_EFF_FLAG` as used by `Frame::raw_id` and generated `Message::decode`
+    pub fn frame_id(self) -> u32 {
+        self.0 | CAN_EFF_FLAG
+    }
+
+    /// Priority, 0 is the highest
+    pub fn priority(self) -> u8 {
+        (self.0 >> 26) as u8 & 0x7
+    }
+
+    /// Parameter group number, without the destination address of PDU1 PGNs
+    pub fn pgn(self) -> u32 {
+        let pgn = (self.0 >> 8) & 0x3FFFF;
+        if is_pdu1(pgn) {
+            pgn & 0x3FF00
+        } else {
+            pgn
+        }
+    }
+
+    pub fn source_address(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Address of the receiver, `GLOBAL_ADDRESS` for broadcast (PDU2) PGNs
+    pub fn destination_address(self) -> u8 {
+        if is_pdu1(self.0 >> 8) {
+            (self.0 >> 8) as u8
+        } else {
+            GLOBAL_ADDRESS
+        }
+    }
+}
+
+/// PDU1 PGNs (PDU format below 240) are sent to a destination address, PDU2 PGNs are broadcast.
+fn is_pdu1(pgn: u32) -> bool {
+    (pgn >> 8) & 0xFF < 240
+}
+
+/// Parameter group of any length, e.g. decoded by generated code via
+/// `Message::decode(group.id.frame_id(), &group.data)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterGroup {
+    pub id: Id,
+    pub data: Vec<u8>,
+}
+
+/// Reason of a connection abort (TP.Conn_Abort).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbortReason {
+    /// Already in one or more connection mode sessions and cannot support another
+    AlreadyInSession,
+    /// System resources were needed for another task
+    ResourcesNeeded,
+    /// No response within the protocol's time limits
+    Timeout,
+    /// CTS received while a data transfer is in progress
+    CtsWhileTransferring,
+    /// Maximum number of retransmission requests reached
+    MaxRetransmit,
+    /// Data packet received outside of a transfer
+    UnexpectedDataTransfer,
+    /// Data packet with a sequence number other than the next one
+    BadSequenceNumber,
+    /// Data packet received twice
+    DuplicateSequenceNumber,
+    /// Announced message size above 1785 bytes
+    MessageTooLarge,
+    Other(u8),
+}
+
+impl From<u8> for AbortReason {
+    fn from(reason: u8) -> Self {
+        match reason {
+            1 => AbortReason::AlreadyInSession,
+            2 => AbortReason::ResourcesNeeded,
+            3 => AbortReason::Timeout,
+            4 => AbortReason::CtsWhileTransferring,
+            5 => AbortReason::MaxRetransmit,
+            6 => AbortReason::UnexpectedDataTransfer,
+            7 => AbortReason::BadSequenceNumber,
+            8 => AbortReason::DuplicateSequenceNumber,
+            9 => AbortReason::MessageTooLarge,
+            reason => AbortReason::Other(reason),
+        }
+    }
+}
+
+impl From<AbortReason> for u8 {
+    fn from(reason: AbortReason) -> Self {
+        match reason {
+            AbortReason::AlreadyInSession => 1,
+            AbortReason::ResourcesNeeded => 2,
+            AbortReason::Timeout => 3,
+            AbortReason::CtsWhileTransferring => 4,
+            AbortReason::MaxRetransmit => 5,
+            AbortReason::UnexpectedDataTransfer => 6,
+            AbortReason::BadSequenceNumber => 7,
+            AbortReason::DuplicateSequenceNumber => 8,
+            AbortReason::MessageTooLarge => 9,
+            AbortReason::Other(reason) => reason,
+        }
+    }
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbortReason::AlreadyInSession => write!(f, "already in a connection mode session"),
+            AbortReason::ResourcesNeeded => write!(f, "system resources needed for another task"),
+            AbortReason::Timeout => write!(f, "timeout"),
+            AbortReason::CtsWhileTransferring => {
+                write!(f, "CTS received while a data transfer is in progress")
+            }
+            AbortReason::MaxRetransmit => write!(f, "maximum retransmit requests reached"),
+            AbortReason::UnexpectedDataTransfer => write!(f, "unexpected data transfer packet"),
+            AbortReason::BadSequenceNumber => write!(f, "bad sequence number"),
+            AbortReason::DuplicateSequenceNumber => write!(f, "duplicate sequence number"),
+            AbortReason::MessageTooLarge => write!(f, "message larger than 1785 bytes"),
+            AbortReason::Other(reason) => write!(f, "abort reason {}", reason),
+        }
+    }
+}
+
+impl error::Error for AbortReason {}
+
+/// Outcome of frames, timeouts and sending handled by `Tp`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Parameter group received in a single frame or reassembled from a transport protocol session
+    Received(ParameterGroup),
+    /// Parameter group sent via the transport protocol, acknowledged by the receiver for RTS/CTS
+    Sent { pgn: u32, destination_address: u8 },
+    /// Transport protocol session aborted
+    Aborted {
+        pgn: u32,
+        /// Address of the other node, `GLOBAL_ADDRESS` for a BAM sent by this node
+        peer: u8,
+        reason: AbortReason,
+        /// Aborted by the other node, otherwise by this node
+        by_peer: bool,
+    },
+}
+
+/// Session receiving a parameter group.
+#[derive(Debug)]
+struct RxSession {
+    source: u8,
+    /// `GLOBAL_ADDRESS` for BAM
+    destination: u8,
+    priority: u8,
+    pgn: u32,
+    size: usize,
+    packets: u8,
+    /// Packets the originator sends in response to one CTS at most
+    max_packets: u8,
+    data: Vec<u8>,
+    /// Sequence number of the next packet
+    next: u8,
+    /// Last packet requested by the current CTS, all packets for BAM
+    last: u8,
+    deadline: Instant,
+}
+
+/// Session sending a parameter group.
+#[derive(Debug)]
+struct TxSession {
+    /// `GLOBAL_ADDRESS` for BAM
+    destination: u8,
+    pgn: u32,
+    data: Vec<u8>,
+    packets: u8,
+    state: TxState,
+}
+
+#[derive(Debug)]
+enum TxState {
+    /// Waiting for CTS or EoMA until the deadline
+    Waiting(Instant),
+    /// Sending the packets `next..=last`, the next one not before `at`
+    Sending { next: u8, last: u8, at: Instant },
+}
+
+impl TxSession {
+    fn is_bam(&self) -> bool {
+        self.destination == GLOBAL_ADDRESS
+    }
+}
+
+/// J1939 transport protocol of the node with `address`.
+///
+/// BAM sessions of all nodes and RTS/CTS sessions addressed to this node are reassembled,
+/// all other frames except TP.CM and TP.DT are passed on as `Event::Received`.
+/// Frames are sent with the address of this node, also when sending single frame parameter groups.
+///
+/// Example:
+/// ```
+/// use dbcc_transport::j1939::{Event, Id, ParameterGroup, Tp};
+/// use std::time::Instant;
+///
+/// let mut ecu = Tp::new(0x00);
+/// let mut tester = Tp::new(0xF9);
+/// let now = Instant::now();
+///
+/// let data: Vec<u8> = (0..20).collect();
+/// let id = Id::new(6, 0xEF00, 0x00, 0xF9);
+/// ecu.send(ParameterGroup { id, data: data.clone() }, now).unwrap();
+///
+/// // RTS, CTS, 3 data packets and the end of message acknowledgement
+/// loop {
+///     let mut idle = true;
+///     while let Some(frame) = ecu.poll_transmit(now) {
+///         tester.handle_frame(&frame, now);
+///         idle = false;
+///     }
+///     while let Some(frame) = tester.poll_transmit(now) {
+///         ecu.handle_frame(&frame, now);
+///         idle = false;
+///     }
+///     if idle {
+///         break;
+///     }
+/// }
+///
+/// match tester.poll_event() {
+///     Some(Event::Received(group)) => {
+///         assert_eq!(group.id.pgn(), 0xEF00);
+///         assert_eq!(group.id.source_address(), 0x00);
+///         assert_eq!(group.data, data);
+///     }
+///     event => panic!("{:?}", event),
+/// }
+/// assert_eq!(ecu.poll_event(), Some(Event::Sent { pgn: 0xEF00, destination_address: 0xF9 }));
+/// ```
+#[derive(Debug)]
+pub struct Tp {
+    address: u8,
+    max_packets_per_cts: u8,
+    rx: Vec<RxSession>,
+    tx: Vec<TxSession>,
+    frames: VecDeque<Frame>,
+    events: VecDeque<Event>,
+}
+
+impl Tp {
+    pub fn new(address: u8) -> Tp {
+        Tp {
+            address,
+            max_packets_per_cts: 0xFF,
+            rx: Vec::new(),
+            tx: Vec::new(),
+            frames: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Packets requested per CTS at most when receiving, 255 (default) requests as many as the originator allows.
+    pub fn set_max_packets_per_cts(&mut self, max_packets: u8) {
+        self.max_packets_per_cts = max_packets.max(1);
+    }
+
+    /// Send a parameter group, longer ones via BAM if the destination is `GLOBAL_ADDRESS`
+    /// and via RTS/CTS otherwise. Only one session per destination can be in progress.
+    pub fn send(&mut self, group: ParameterGroup, now: Instant) -> io::Result<()> {
+        let pgn = group.id.pgn();
+        let destination = group.id.destination_address();
+        let size = group.data.len();
+        if size <= CAN_MAX_DLEN {
+            let id = Id::new(group.id.priority(), pgn, self.address, destination);
+            self.frames
+                .push_back(Frame::new(id.frame_id(), &group.data)?);
+            return Ok(());
+        }
+        if size > TP_MAX_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the transport protocol carries at most {} bytes, got {}",
+                    TP_MAX_SIZE, size
+                ),
+            ));
+        }
+        if self
+            .tx
+            .iter()
+            .any(|session| session.destination == destination)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!(
+                    "a transport protocol session to {:#X} is in progress",
+                    destination
+                ),
+            ));
+        }
+
+        let packets = size.div_ceil(PACKET_SIZE) as u8;
+        let (control, state) = if destination == GLOBAL_ADDRESS {
+            let state = TxState::Sending {
+                next: 1,
+                last: packets,
+                at: now + BAM_PACKET_INTERVAL,
+            };
+            (CM_BAM, state)
+        } else {
+            (CM_RTS, TxState::Waiting(now + T3))
+        };
+        let [size_low, size_high] = (size as u16).to_le_bytes();
+        self.send_cm(
+            destination,
+            control,
+            [size_low, size_high, packets, 0xFF],
+            pgn,
+        );
+        self.tx.push(TxSession {
+            destination,
+            pgn,
+            data: group.data,
+            packets,
+            state,
+        });
+        Ok(())
+    }
+
+    /// Handle a received frame, frames of other protocols than J1939 are ignored.
+    pub fn handle_frame(&mut self, frame: &Frame, now: Instant) {
+        if !frame.is_extended() {
+            return;
+        }
+        let id = Id::from_raw(frame.raw_id());
+        match id.pgn() {
+            PGN_TP_CM => self.handle_cm(id, frame.data(), now),
+            PGN_TP_DT => self.handle_dt(id, frame.data(), now),
+            _ => self.events.push_back(Event::Received(ParameterGroup {
+                id,
+                data: frame.data().to_vec(),
+            })),
+        }
+    }
+
+    /// Abort sessions whose peer did not respond in time.
+    pub fn handle_timeout(&mut self, now: Instant) {
+        let mut index = 0;
+        while index < self.rx.len() {
+            if self.rx[index].deadline <= now {
+                let session = self.rx.remove(index);
+                self.abort_rx(session, AbortReason::Timeout);
+            } else {
+                index += 1;
+            }
+        }
+        let mut index = 0;
+        while index < self.tx.len() {
+            match self.tx[index].state {
+                TxState::Waiting(deadline) if deadline <= now => {
+                    let session = self.tx.remove(index);
+                    self.abort_tx(session, AbortReason::Timeout);
+                }
+                _ => index += 1,
+            }
+        }
+    }
+
+    /// Earliest time `handle_timeout` aborts a session or `poll_transmit` has a data packet to send.
+    pub fn next_timeout(&self) -> Option<Instant> {
+        let rx = self.rx.iter().map(|session| session.deadline);
+        let tx = self.tx.iter().map(|session| match session.state {
+            TxState::Waiting(deadline) => deadline,
+            TxState::Sending { at, .. } => at,
+        });
+        rx.chain(tx).min()
+    }
+
+    /// Next frame to send at `now`.
+    pub fn poll_transmit(&mut self, now: Instant) -> Option<Frame> {
+        if let Some(frame) = self.frames.pop_front() {
+            return Some(frame);
+        }
+
+        let index = self.tx.iter().position(
+            |session| matches!(session.state, TxState::Sending { at, .. } if at <= now),
+        )?;
+        let session = &mut self.tx[index];
+        let (next, last) = match session.state {
+            TxState::Sending { next, last, .. } => (next, last),
+            TxState::Waiting(_) => return None,
+        };
+        let frame = packet(self.address, session, next);
+        if next < last {
+            let at = if session.is_bam() {
+                now + BAM_PACKET_INTERVAL
+            } else {
+                now
+            };
+            session.state = TxState::Sending {
+                next: next + 1,
+                last,
+                at,
+            };
+        } else if session.is_bam() {
+            let session = self.tx.remove(index);
+            self.events.push_back(Event::Sent {
+                pgn: session.pgn,
+                destination_address: GLOBAL_ADDRESS,
+            });
+        } else {
+            session.state = TxState::Waiting(now + T3);
+        }
+        Some(frame)
+    }
+
+    /// Next received parameter group or outcome of a session.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    fn handle_cm(&mut self, id: Id, data: &[u8], now: Instant) {
+        let destination = id.destination_address();
+        if data.len() < CAN_MAX_DLEN
+            || (destination != self.address && destination != GLOBAL_ADDRESS)
+        {
+            return;
+        }
+        let peer = id.source_address();
+        let pgn = u32::from_le_bytes([data[5], data[6], data[7], 0]);
+        let size = usize::from(u16::from_le_bytes([data[1], data[2]]));
+        match data[0] {
+            CM_BAM if destination == GLOBAL_ADDRESS => {
+                self.start_rx(id, pgn, size, data[3], 0xFF, now)
+            }
+            CM_RTS if destination == self.address => {
+                self.start_rx(id, pgn, size, data[3], data[4], now)
+            }
+            CM_CTS if destination == self.address => {
+                self.handle_cts(peer, pgn, data[1], data[2], now)
+            }
+            CM_EOMA if destination == self.address => {
+                let index = self.tx.iter().position(|session| {
+                    session.destination == peer
+                        && session.pgn == pgn
+                        && matches!(session.state, TxState::Waiting(_))
+                });
+                if let Some(index) = index {
+                    self.tx.remove(index);
+                    self.events.push_back(Event::Sent {
+                        pgn,
+                        destination_address: peer,
+                    });
+                }
+            }
+            CM_ABORT if destination == self.address => {
+                let tx = self
+                    .tx
+                    .iter()
+                    .position(|session| session.destination == peer && session.pgn == pgn);
+                let rx = self.rx.iter().position(|session| {
+                    session.source == peer
+                        && session.destination == self.address
+                        && session.pgn == pgn
+                });
+                match (tx, rx) {
+                    (Some(index), _) => {
+                        self.tx.remove(index);
+                    }
+                    (None, Some(index)) => {
+                        self.rx.remove(index);
+                    }
+                    (None, None) => return,
+                }
+                self.events.push_back(Event::Aborted {
+                    pgn,
+                    peer,
+                    reason: AbortReason::from(data[1]),
+                    by_peer: true,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Start receiving a BAM or RTS/CTS session, replacing one of the same originator.
+    fn start_rx(
+        &mut self,
+        id: Id,
+        pgn: u32,
+        size: usize,
+        packets: u8,
+        max_packets: u8,
+        now: Instant,
+    ) {
+        let source = id.source_address();
+        let destination = id.destination_address();
+        let bam = destination == GLOBAL_ADDRESS;
+        self.rx
+            .retain(|session| session.source != source || session.destination != destination);
+
+        if size > TP_MAX_SIZE {
+            if !bam {
+                self.send_abort(source, pgn, AbortReason::MessageTooLarge);
+            }
+            return;
+        }
+        if packets == 0 || usize::from(packets) * PACKET_SIZE < size {
+            return;
+        }
+
+        let max_packets = max_packets.max(1).min(self.max_packets_per_cts);
+        let last = if bam {
+            packets
+        } else {
+            max_packets.min(packets)
+        };
+        let deadline = if bam {
+            now + T1
+        } else {
+            self.send_cm(source, CM_CTS, [last, 1, 0xFF, 0xFF], pgn);
+            now + T2
+        };
+        self.rx.push(RxSession {
+            source,
+            destination,
+            priority: id.priority(),
+            pgn,
+            size,
+            packets,
+            max_packets,
+            data: Vec::with_capacity(size),
+            next: 1,
+            last,
+            deadline,
+        });
+    }
+
+    fn handle_cts(&mut self, peer: u8, pgn: u32, count: u8, next: u8, now: Instant) {
+        let index = match self
+            .tx
+            .iter()
+            .position(|session| session.destination == peer && session.pgn == pgn)
+        {
+            Some(index) => index,
+            None => return,
+        };
+        let session = &mut self.tx[index];
+        match session.state {
+            TxState::Sending { .. } => {
+                let session = self.tx.remove(index);
+                self.abort_tx(session, AbortReason::CtsWhileTransferring);
+            }
+            // Hold the connection open
+            TxState::Waiting(_) if count == 0 => session.state = TxState::Waiting(now + T4),
+            TxState::Waiting(_) if next == 0 || next > session.packets => {}
+            TxState::Waiting(_) => {
+                let last =
+                    (usize::from(next) + usize::from(count) - 1).min(usize::from(session.packets));
+                session.state = TxState::Sending {
+                    next,
+                    last: last as u8,
+                    at: now,
+                };
+            }
+        }
+    }
+
+    fn handle_dt(&mut self, id: Id, data: &[u8], now: Instant) {
+        let source = id.source_address();
+        let destination = id.destination_address();
+        let index = match self
+            .rx
+            .iter()
+            .position(|session| session.source == source && session.destination == destination)
+        {
+            Some(index) if !data.is_empty() => index,
+            // Packets of sessions not received by this node
+            _ => return,
+        };
+
+        let session = &mut self.rx[index];
+        let sequence = data[0];
+        if sequence != session.next {
+            let reason = if sequence < session.next {
+                AbortReason::DuplicateSequenceNumber
+            } else {
+                AbortReason::BadSequenceNumber
+            };
+            let session = self.rx.remove(index);
+            self.abort_rx(session, reason);
+            return;
+        }
+
+        let remaining = session.size - session.data.len();
+        let payload = &data[1..];
+        session
+            .data
+            .extend_from_slice(&payload[..payload.len().min(remaining)]);
+        if sequence == session.packets {
+            let session = self.rx.remove(index);
+            if session.data.len() < session.size {
+                self.abort_rx(session, AbortReason::Other(0xFF));
+                return;
+            }
+            if session.destination != GLOBAL_ADDRESS {
+                let [size_low, size_high] = (session.size as u16).to_le_bytes();
+                self.send_cm(
+                    session.source,
+                    CM_EOMA,
+                    [size_low, size_high, session.packets, 0xFF],
+                    session.pgn,
+                );
+            }
+            self.events.push_back(Event::Received(ParameterGroup {
+                id: Id::new(
+                    session.priority,
+                    session.pgn,
+                    session.source,
+                    session.destination,
+                ),
+                data: session.data,
+            }));
+        } else if sequence == session.last {
+            // Request the next packets
+            session.next = sequence + 1;
+            let count = session.max_packets.min(session.packets - sequence);
+            session.last = sequence + count;
+            session.deadline = now + T2;
+            let (source, next, pgn) = (session.source, session.next, session.pgn);
+            self.send_cm(source, CM_CTS, [count, next, 0xFF, 0xFF], pgn);
+        } else {
+            session.next = sequence + 1;
+            session.deadline = now + T1;
+        }
+    }
+
+    /// Abort a receive session, the originator of a BAM is not notified.
+    fn abort_rx(&mut self, session: RxSession, reason: AbortReason) {
+        if session.destination != GLOBAL_ADDRESS {
+            self.send_abort(session.source, session.pgn, reason);
+        }
+        self.events.push_back(Event::Aborted {
+            pgn: session.pgn,
+            peer: session.source,
+            reason,
+            by_peer: false,
+        });
+    }
+
+    fn abort_tx(&mut self, session: TxSession, reason: AbortReason) {
+        if !session.is_bam() {
+            self.send_abort(session.destination, session.pgn, reason);
+        }
+        self.events.push_back(Event::Aborted {
+            pgn: session.pgn,
+            peer: session.destination,
+            reason,
+            by_peer: false,
+        });
+    }
+
+    fn send_abort(&mut self, destination: u8, pgn: u32, reason: AbortReason) {
+        self.send_cm(
+            destination,
+            CM_ABORT,
+            [reason.into(), 0xFF, 0xFF, 0xFF],
+            pgn,
+        );
+    }
+
+    /// Queue a TP.CM frame of `control`, `parameters` and the PGN of the session.
+    fn send_cm(&mut self, destination: u8, control: u8, parameters: [u8; 4], pgn: u32) {
+        let [pgn_low, pgn_mid, pgn_high, _] = pgn.to_le_bytes();
+        let [a, b, c, d] = parameters;
+        let data = [control, a, b, c, d, pgn_low, pgn_mid, pgn_high];
+        let id = Id::new(TP_PRIORITY, PGN_TP_CM, self.address, destination);
+        self.frames.push_back(Frame::extended(id.as_raw(), data));
+    }
+}
+
+/// TP.DT frame of packet `sequence`, the last one padded with `0xFF`.
+fn packet(address: u8, session: &TxSession, sequence: u8) -> Frame {
+    let start = (usize::from(sequence) - 1) * PACKET_SIZE;
+    let end = (start + PACKET_SIZE).min(session.data.len());
+    let mut data = [0xFF; CAN_MAX_DLEN];
+    data[0] = sequence;
+    data[1..=end - start].copy_from_slice(&session.data[start..end]);
+    let id = Id::new(TP_PRIORITY, PGN_TP_DT, address, session.destination);
+    Frame::extended(id.as_raw(), data)
+}
+
+impl Protocol for Tp {
+    type Event = Event;
+
+    fn handle_frame(&mut self, frame: &Frame, now: Instant) {
+        Tp::handle_frame(self, frame, now)
+    }
+
+    fn handle_timeout(&mut self, now: Instant) {
+        Tp::handle_timeout(self, now)
+    }
+
+    fn next_timeout(&self) -> Option<Instant> {
+        Tp::next_timeout(self)
+    }
+
+    fn poll_transmit(&mut self, now: Instant) -> Option<Frame> {
+        Tp::poll_transmit(self, now)
+    }
+
+    fn poll_event(&mut self) -> Option<Event> {
+        Tp::poll_event(self)
+    }
+}
+
+/// `Tp` driven over a transport, timeouts work with any executor.
+///
+/// Example:
+/// ```
+/// use dbcc_transport::j1939::{Id, ParameterGroup, Tp, TpTransport};
+/// use dbcc_transport::vbus::VirtualBus;
+///
+/// let bus = VirtualBus::new();
+/// let mut ecu = TpTransport::new(bus.endpoint(), Tp::new(0x00));
+/// let mut tester = TpTransport::new(bus.endpoint(), Tp::new(0xF9));
+///
+/// let receiver = std::thread::spawn(move || futures_executor::block_on(tester.recv()));
+/// let data: Vec<u8> = (0..100).collect();
+/// let id = Id::new(6, 0xEF00, 0x00, 0xF9);
+/// futures_executor::block_on(ecu.send(ParameterGroup { id, data: data.clone() })).unwrap();
+/// assert_eq!(receiver.join().unwrap().unwrap().data, data);
+/// ```
+#[derive(Debug)]
+pub struct TpTransport<T> {
+    driver: Driver<Tp, T>,
+    /// Events `send` received while waiting for its session
+    deferred: VecDeque<Event>,
+}
+
+impl<T: CanTransport + Unpin> TpTransport<T> {
+    pub fn new(transport: T, tp: Tp) -> Self {
+        TpTransport {
+            driver: Driver::new(tp, transport),
+            deferred: VecDeque::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.driver.transport
+    }
+
+    pub fn tp(&self) -> &Tp {
+        &self.driver.protocol
+    }
+
+    pub fn tp_mut(&mut self) -> &mut Tp {
+        &mut self.driver.protocol
+    }
+
+    pub fn into_inner(self) -> T {
+        self.driver.transport
+    }
+
+    /// Send a parameter group and wait until it is sent, acknowledged for RTS/CTS.
+    /// Fails with `ConnectionAborted` and the `AbortReason` as inner error if the session is aborted.
+    pub async fn send(&mut self, group: ParameterGroup) -> io::Result<()> {
+        let pgn = group.id.pgn();
+        let destination = group.id.destination_address();
+        let single_frame = group.data.len() <= CAN_MAX_DLEN;
+        self.driver.protocol.send(group, Instant::now())?;
+        if single_frame {
+            return poll_fn(|cx| self.driver.poll_flush(cx)).await;
+        }
+        loop {
+            match poll_fn(|cx| self.driver.poll_event(cx)).await? {
+                Event::Sent {
+                    pgn: sent_pgn,
+                    destination_address,
+                } if sent_pgn == pgn && destination_address == destination => return Ok(()),
+                Event::Aborted {
+                    pgn: aborted_pgn,
+                    peer,
+                    reason,
+                    ..
+                } if aborted_pgn == pgn && peer == destination => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionAborted, reason))
+                }
+                event => self.deferred.push_back(event),
+            }
+        }
+    }
+
+    /// Receive the next parameter group, other events are skipped.
+    pub async fn recv(&mut self) -> io::Result<ParameterGroup> {
+        loop {
+            if let Event::Received(group) = self.event().await? {
+                return Ok(group);
+            }
+        }
+    }
+
+    /// Next event, fails with `UnexpectedEof` once the transport is closed.
+    pub async fn event(&mut self) -> io::Result<Event> {
+        poll_fn(|cx| self.poll_event(cx)).await
+    }
+
+    /// Send frames, receive frames and handle timeouts until an event is available.
+    pub fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Event>> {
+        if let Some(event) = self.deferred.pop_front() {
+            return Poll::Ready(Ok(event));
+        }
+        self.driver.poll_event(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem;
+
+    use futures_executor::block_on;
+
+    const ECU: u8 = 0x00;
+    const TESTER: u8 = 0xF9;
+    const PGN: u32 = 0xEF00;
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    fn cm(source: u8, destination: u8, data: [u8; 8]) -> Frame {
+        let id = Id::new(TP_PRIORITY, PGN_TP_CM, source, destination);
+        Frame::new(id.frame_id(), &data).unwrap()
+    }
+
+    fn dt(source: u8, destination: u8, sequence: u8) -> Frame {
+        let id = Id::new(TP_PRIORITY, PGN_TP_DT, source, destination);
+        Frame::new(id.frame_id(), &[sequence, 0, 0, 0, 0, 0, 0, 0]).unwrap()
+    }
+
+    fn cts(count: u8, next: u8) -> Frame {
+        cm(
+            TESTER,
+            ECU,
+            [CM_CTS, count, next, 0xFF, 0xFF, 0x00, 0xEF, 0x00],
+        )
+    }
+
+    /// Control byte of the next TP.CM frame sent by `tp`
+    fn next_cm(tp: &mut Tp, now: Instant) -> Option<u8> {
+        let frame = tp.poll_transmit(now)?;
+        assert_eq!(Id::from_raw(frame.raw_id()).pgn(), PGN_TP_CM);
+        Some(frame.data()[0])
+    }
+
+    /// Pass frames between both nodes until neither has one to send, returns the CTS frames
+    fn exchange(a: &mut Tp, b: &mut Tp, now: Instant) -> Vec<Frame> {
+        let mut cts = Vec::new();
+        loop {
+            let mut idle = true;
+            while let Some(frame) = a.poll_transmit(now) {
+                b.handle_frame(&frame, now);
+                idle = false;
+            }
+            while let Some(frame) = b.poll_transmit(now) {
+                if Id::from_raw(frame.raw_id()).pgn() == PGN_TP_CM && frame.data()[0] == CM_CTS {
+                    cts.push(frame);
+                }
+                a.handle_frame(&frame, now);
+                idle = false;
+            }
+            if idle {
+                return cts;
+            }
+        }
+    }
+
+    /// Broadcast `data` from the ECU, stepping the time to each packet
+    fn bam(data: Vec<u8>) {
+        let mut ecu = Tp::new(ECU);
+        let mut tester = Tp::new(TESTER);
+        let start = Instant::now();
+        let mut now = start;
+        let id = Id::new(6, 0xFECA, ECU, GLOBAL_ADDRESS);
+        ecu.send(
+            ParameterGroup {
+                id,
+                data: data.clone(),
+            },
+            now,
+        )
+        .unwrap();
+
+        let mut frames = 0;
+        loop {
+            while let Some(frame) = ecu.poll_transmit(now) {
+                tester.handle_frame(&frame, now);
+                frames += 1;
+            }
+            tester.handle_timeout(now);
+            match ecu.next_timeout() {
+                Some(next) => now = next,
+                None => break,
+            }
+        }
+
+        let packets = data.len().div_ceil(PACKET_SIZE);
+        assert_eq!(frames, 1 + packets);
+        assert_eq!(now - start, BAM_PACKET_INTERVAL * packets as u32);
+        // The priority is not transmitted, reassembled groups have the one of the TP.CM frame
+        let id = Id::new(TP_PRIORITY, 0xFECA, ECU, GLOBAL_ADDRESS);
+        assert_eq!(
+            tester.poll_event(),
+            Some(Event::Received(ParameterGroup { id, data }))
+        );
+        assert_eq!(
+            ecu.poll_event(),
+            Some(Event::Sent {
+                pgn: 0xFECA,
+                destination_address: GLOBAL_ADDRESS
+            })
+        );
+        assert_eq!(tester.next_timeout(), None);
+    }
+
+    #[test]
+    fn bam_smallest() {
+        bam(payload(9));
+    }
+
+    #[test]
+    fn bam_largest() {
+        bam(payload(TP_MAX_SIZE));
+    }
+
+    #[test]
+    fn too_large() {
+        let mut ecu = Tp::new(ECU);
+        let id = Id::new(6, 0xFECA, ECU, GLOBAL_ADDRESS);
+        let error = ecu
+            .send(
+                ParameterGroup {
+                    id,
+                    data: payload(TP_MAX_SIZE + 1),
+                },
+                Instant::now(),
+            )
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn cts_window() {
+        let mut ecu = Tp::new(ECU);
+        let mut tester = Tp::new(TESTER);
+        tester.set_max_packets_per_cts(3);
+        let now = Instant::now();
+        let id = Id::new(TP_PRIORITY, PGN, ECU, TESTER);
+        let data = payload(50);
+        ecu.send(
+            ParameterGroup {
+                id,
+                data: data.clone(),
+            },
+            now,
+        )
+        .unwrap();
+
+        // 8 packets requested 3, 3 and 2 at a time
+        let cts: Vec<(u8, u8)> = exchange(&mut ecu, &mut tester, now)
+            .iter()
+            .map(|frame| (frame.data()[1], frame.data()[2]))
+            .collect();
+        assert_eq!(cts, [(3, 1), (3, 4), (2, 7)]);
+        assert_eq!(
+            tester.poll_event(),
+            Some(Event::Received(ParameterGroup { id, data }))
+        );
+        assert_eq!(
+            ecu.poll_event(),
+            Some(Event::Sent {
+                pgn: PGN,
+                destination_address: TESTER
+            })
+        );
+    }
+
+    #[test]
+    fn cts_window_over_channel() {
+        let (a, b) = mem::channel();
+        let mut ecu = TpTransport::new(a, Tp::new(ECU));
+        let mut tester = TpTransport::new(b, Tp::new(TESTER));
+        tester.tp_mut().set_max_packets_per_cts(2);
+
+        let receiver = std::thread::spawn(move || block_on(tester.recv()));
+        let id = Id::new(TP_PRIORITY, PGN, ECU, TESTER);
+        let data = payload(100);
+        block_on(ecu.send(ParameterGroup {
+            id,
+            data: data.clone(),
+        }))
+        .unwrap();
+        let group = receiver.join().unwrap().unwrap();
+        assert_eq!(group, ParameterGroup { id, data });
+    }
+
+    #[test]
+    fn bam_over_channel() {
+        let (a, b) = mem::channel();
+        let mut ecu = TpTransport::new(a, Tp::new(ECU));
+        let mut tester = TpTransport::new(b, Tp::new(TESTER));
+
+        let receiver = std::thread::spawn(move || block_on(tester.recv()));
+        let id = Id::new(6, 0xFECA, ECU, GLOBAL_ADDRESS);
+        block_on(ecu.send(ParameterGroup {
+            id,
+            data: payload(9),
+        }))
+        .unwrap();
+        assert_eq!(receiver.join().unwrap().unwrap().data, payload(9));
+    }
+
+    #[test]
+    fn out_of_order_packet() {
+        let mut ecu = Tp::new(ECU);
+        let mut tester = Tp::new(TESTER);
+        let now = Instant::now();
+        let id = Id::new(6, PGN, ECU, TESTER);
+        ecu.send(
+            ParameterGroup {
+                id,
+                data: payload(20),
+            },
+            now,
+        )
+        .unwrap();
+        tester.handle_frame(&ecu.poll_transmit(now).unwrap(), now);
+        assert_eq!(next_cm(&mut tester, now), Some(CM_CTS));
+
+        tester.handle_frame(&dt(ECU, TESTER, 2), now);
+        let abort = tester.poll_transmit(now).unwrap();
+        assert_eq!(abort.data()[..2], [CM_ABORT, 7]);
+        assert_eq!(
+            tester.poll_event(),
+            Some(Event::Aborted {
+                pgn: PGN,
+                peer: ECU,
+                reason: AbortReason::BadSequenceNumber,
+                by_peer: false
+            })
+        );
+
+        ecu.handle_frame(&abort, now);
+        assert_eq!(
+            ecu.poll_event(),
+            Some(Event::Aborted {
+                pgn: PGN,
+                peer: TESTER,
+                reason: AbortReason::BadSequenceNumber,
+                by_peer: true
+            })
+        );
+        assert_eq!(ecu.next_timeout(), None);
+    }
+
+    #[test]
+    fn duplicate_packet() {
+        let mut tester = Tp::new(TESTER);
+        let now = Instant::now();
+        tester.handle_frame(
+            &cm(ECU, TESTER, [CM_RTS, 20, 0, 3, 0xFF, 0x00, 0xEF, 0x00]),
+            now,
+        );
+        assert_eq!(next_cm(&mut tester, now), Some(CM_CTS));
+        tester.handle_frame(&dt(ECU, TESTER, 1), now);
+        tester.handle_frame(&dt(ECU, TESTER, 1), now);
+        assert_eq!(next_cm(&mut tester, now), Some(CM_ABORT));
+        assert!(matches!(
+            tester.poll_event(),
+            Some(Event::Aborted {
+                reason: AbortReason::DuplicateSequenceNumber,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn bam_t1_timeout() {
+        let mut tester = Tp::new(TESTER);
+        let now = Instant::now();
+        tester.handle_frame(
+            &cm(
+                ECU,
+                GLOBAL_ADDRESS,
+                [CM_BAM, 20, 0, 3, 0xFF, 0xCA, 0xFE, 0x00],
+            ),
+            now,
+        );
+        let later = now + Duration::from_millis(100);
+        tester.handle_frame(&dt(ECU, GLOBAL_ADDRESS, 1), later);
+        assert_eq!(tester.next_timeout(), Some(later + T1));
+
+        tester.handle_timeout(later + T1 - Duration::from_millis(1));
+        assert_eq!(tester.poll_event(), None);
+        tester.handle_timeout(later + T1);
+        assert_eq!(
+            tester.poll_event(),
+            Some(Event::Aborted {
+                pgn: 0xFECA,
+                peer: ECU,
+                reason: AbortReason::Timeout,
+                by_peer: false
+            })
+        );
+        // The originator of a BAM is not notified
+        assert_eq!(tester.poll_transmit(later + T1), None);
+    }
+
+    #[test]
+    fn rts_t2_timeout() {
+        let mut tester = Tp::new(TESTER);
+        let now = Instant::now();
+        tester.handle_frame(
+            &cm(ECU, TESTER, [CM_RTS, 20, 0, 3, 0xFF, 0x00, 0xEF, 0x00]),
+            now,
+        );
+        assert_eq!(next_cm(&mut tester, now), Some(CM_CTS));
+        assert_eq!(tester.next_timeout(), Some(now + T2));
+
+        tester.handle_timeout(now + T2);
+        let abort = tester.poll_transmit(now + T2).unwrap();
+        assert_eq!(abort.data()[..2], [CM_ABORT, 3]);
+        assert_eq!(Id::from_raw(abort.raw_id()).destination_address(), ECU);
+        assert!(matches!(
+            tester.poll_event(),
+            Some(Event::Aborted {
+                reason: AbortReason::Timeout,
+                by_peer: false,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn cts_hold() {
+        let mut ecu = Tp::new(ECU);
+        let now = Instant::now();
+        let id = Id::new(6, PGN, ECU, TESTER);
+        ecu.send(
+            ParameterGroup {
+                id,
+                data: payload(20),
+            },
+            now,
+        )
+        .unwrap();
+        assert_eq!(next_cm(&mut ecu, now), Some(CM_RTS));
+
+        // Hold the connection open, nothing is sent until the next CTS
+        let hold = now + Duration::from_millis(500);
+        ecu.handle_frame(&cts(0, 0xFF), hold);
+        assert_eq!(ecu.poll_transmit(hold), None);
+        assert_eq!(ecu.next_timeout(), Some(hold + T4));
+        ecu.handle_timeout(hold + T4 - Duration::from_millis(1));
+        assert_eq!(ecu.poll_event(), None);
+
+        let resume = hold + Duration::from_millis(1000);
+        ecu.handle_frame(&cts(3, 1), resume);
+        let sequences: Vec<u8> = std::iter::from_fn(|| ecu.poll_transmit(resume))
+            .map(|frame| frame.data()[0])
+            .collect();
+        assert_eq!(sequences, [1, 2, 3]);
+    }
+
+    #[test]
+    fn cts_hold_timeout() {
+        let mut ecu = Tp::new(ECU);
+        let now = Instant::now();
+        let id = Id::new(6, PGN, ECU, TESTER);
+        ecu.send(
+            ParameterGroup {
+                id,
+                data: payload(20),
+            },
+            now,
+        )
+        .unwrap();
+        assert_eq!(next_cm(&mut ecu, now), Some(CM_RTS));
+
+        ecu.handle_frame(&cts(0, 0xFF), now);
+        ecu.handle_timeout(now + T4);
+        assert_eq!(next_cm(&mut ecu, now + T4), Some(CM_ABORT));
+        assert_eq!(
+            ecu.poll_event(),
+            Some(Event::Aborted {
+                pgn: PGN,
+                peer: TESTER,
+                reason: AbortReason::Timeout,
+                by_peer: false
+            })
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Runtime support for code generated by [dbcc](https://docs.rs/dbcc).
+//!
+//! Generated code calls into this crate for functionality that requires
+//! system calls, e.g. cyclic transmission via the SocketCAN broadcast manager (`bcm`).
+//! Async sockets and streams are available for tokio (`tokio` feature)
+//! and async-std (`async-std` feature), all of them implement the `CanSource` and
+//! `CanTransport` traits, as do the in-memory `mem::channel` and virtual bus `vbus::VirtualBus`.
+//! `j1939` implements the J1939 transport protocol and `isotp` ISO-TP (ISO 15765-2)
+//! on top of any `CanTransport`.
+
+#[cfg(feature = "async-std")]
+pub mod async_std;
+pub mod bcm;
+mod driver;
+mod frame;
+pub mod isotp;
+pub mod j1939;
+pub mod mem;
+pub mod raw;
+mod sys;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+mod transport;
+pub mod vbus;
+
+pub use crate::frame::{Frame, CAN_EFF_FLAG, CAN_EFF_MASK, CAN_MAX_DLEN};
+pub use crate::transport::{
+    frames, CanSource, CanTransport, CanTransportExt, Frames, RecvFrame, SendFrame,
+};
+
+/// CAN interface of the SocketCAN tests, e.g. `DBCC_VCAN=vcan0`, the tests are skipped if unset.
+///
+/// ```sh
+/// ip link add dev vcan0 type vcan && ip link set up vcan0
+/// DBCC_VCAN=vcan0 cargo test -p dbcc-transport --all-features
+/// ```
+#[cfg(test)]
+pub(crate) fn vcan_interface() -> Option<String> {
+    let ifname = std::env::var("DBCC_VCAN").ok();
+    if ifname.is_none() {
+        eprintln!("DBCC_VCAN is not set, skipping SocketCAN test");
+    }
+    ifname
+}
@@ -0,0 +1,109 @@
+//! In-memory transport, e.g. for testing code using generated streams without CAN hardware.
+use crate::frame::Frame;
+use crate::transport::{CanSource, CanTransport};
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Default)]
+struct Queue {
+    frames: VecDeque<Frame>,
+    waker: Option<Waker>,
+    /// The sending end was dropped
+    closed: bool,
+}
+
+/// One end of an in-memory channel, frames sent are received by the other end.
+/// Sending never blocks, frames are queued until they are received.
+#[derive(Debug)]
+pub struct MemTransport {
+    rx: Arc<Mutex<Queue>>,
+    tx: Arc<Mutex<Queue>>,
+}
+
+/// Two connected ends of an in-memory channel.
+///
+/// Example:
+/// ```
+/// use dbcc_transport::{mem, CanTransportExt, Frame};
+///
+/// let (mut a, mut b) = mem::channel();
+/// let frame = Frame::new(0x123, &[0x01, 0x02]).unwrap();
+/// futures_executor::block_on(async {
+///     a.send(frame).await.unwrap();
+///     drop(a);
+///     assert_eq!(b.recv().await.unwrap().unwrap(), frame);
+///     assert!(b.recv().await.is_none());
+/// });
+/// ```
+pub fn channel() -> (MemTransport, MemTransport) {
+    let a = Arc::new(Mutex::new(Queue::default()));
+    let b = Arc::new(Mutex::new(Queue::default()));
+    (
+        MemTransport {
+            rx: a.clone(),
+            tx: b.clone(),
+        },
+        MemTransport { rx: b, tx: a },
+    )
+}
+
+impl MemTransport {
+    /// Queue a frame for the other end.
+    pub fn send_now(&self, frame: Frame) -> io::Result<()> {
+        let mut queue = self.tx.lock().unwrap_or_else(|e| e.into_inner());
+        // Only the other end holds a second reference to its receive queue
+        if Arc::strong_count(&self.tx) < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the other end of the channel was dropped",
+            ));
+        }
+        queue.frames.push_back(frame);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Take a received frame without waiting.
+    pub fn try_recv(&self) -> Option<Frame> {
+        self.rx
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .frames
+            .pop_front()
+    }
+}
+
+impl CanSource for MemTransport {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        let mut queue = self.rx.lock().unwrap_or_else(|e| e.into_inner());
+        match queue.frames.pop_front() {
+            Some(frame) => Poll::Ready(Some(Ok(frame))),
+            None if queue.closed => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl CanTransport for MemTransport {
+    fn poll_send(&mut self, _cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        Poll::Ready(self.send_now(*frame))
+    }
+}
+
+impl Drop for MemTransport {
+    fn drop(&mut self) {
+        let mut queue = self.tx.lock().unwrap_or_else(|e| e.into_inner());
+        queue.closed = true;
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+}
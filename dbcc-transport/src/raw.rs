@@ -0,0 +1,87 @@
+//! Raw SocketCAN sockets sending and receiving single frames.
+use crate::frame::Frame;
+use crate::sys::{self, CanFrame, Socket};
+
+use std::io;
+use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// Blocking raw CAN socket, see the `tokio` and `async_std` modules for async sockets.
+#[derive(Debug)]
+pub struct CanSocket {
+    socket: Socket,
+}
+
+impl CanSocket {
+    /// Open a raw socket on a CAN interface e.g. `vcan0`.
+    pub fn open(ifname: &str) -> io::Result<CanSocket> {
+        Ok(CanSocket {
+            socket: Socket::open(ifname, sys::CAN_RAW)?,
+        })
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    pub fn write_frame(&self, frame: &Frame) -> io::Result<()> {
+        sys::write(
+            self.as_raw_fd(),
+            &CanFrame::from(frame),
+            size_of::<CanFrame>(),
+        )
+    }
+
+    pub fn read_frame(&self) -> io::Result<Frame> {
+        let mut can_frame = CanFrame::default();
+        let len = sys::read(self.as_raw_fd(), &mut can_frame)?;
+        if len != size_of::<CanFrame>() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("incomplete CAN frame of {} bytes", len),
+            ));
+        }
+        can_frame.to_frame()
+    }
+}
+
+impl AsRawFd for CanSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.fd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_receive() {
+        let ifname = match crate::vcan_interface() {
+            Some(ifname) => ifname,
+            None => return,
+        };
+        let sender = CanSocket::open(&ifname).unwrap();
+        let receiver = CanSocket::open(&ifname).unwrap();
+
+        for frame in &[
+            Frame::new(0x123, &[1, 2, 3]).unwrap(),
+            Frame::new(0x18FE_F100, &[0xFF; 8]).unwrap(),
+        ] {
+            sender.write_frame(frame).unwrap();
+            assert_eq!(receiver.read_frame().unwrap(), *frame);
+        }
+    }
+
+    #[test]
+    fn nonblocking_read() {
+        let ifname = match crate::vcan_interface() {
+            Some(ifname) => ifname,
+            None => return,
+        };
+        let socket = CanSocket::open(&ifname).unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let error = socket.read_frame().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+    }
+}
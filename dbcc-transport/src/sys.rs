@@ -0,0 +1,258 @@
+//! SocketCAN definitions of `<linux/can.h>` not available in libc.
+use crate::frame::{Frame, CAN_MAX_DLEN};
+
+use libc::{c_int, c_void};
+
+use std::ffi::CString;
+use std::io;
+use std::mem::size_of;
+use std::os::unix::io::RawFd;
+
+pub(crate) const PF_CAN: c_int = 29;
+pub(crate) const AF_CAN: libc::sa_family_t = 29;
+pub(crate) const CAN_RAW: c_int = 1;
+pub(crate) const CAN_BCM: c_int = 2;
+
+/// `struct can_frame`, the data is 8 byte aligned in C
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CanFrame {
+    pub(crate) can_id: u32,
+    pub(crate) can_dlc: u8,
+    _pad: u8,
+    _res0: u8,
+    _res1: u8,
+    pub(crate) data: [u8; CAN_MAX_DLEN],
+}
+
+// The layout of the kernel ABI
+const _: () = {
+    use std::mem::{align_of, offset_of};
+
+    assert!(offset_of!(CanFrame, can_id) == offset_of!(libc::can_frame, can_id));
+    assert!(offset_of!(CanFrame, can_dlc) == offset_of!(libc::can_frame, can_dlc));
+    assert!(offset_of!(CanFrame, data) == offset_of!(libc::can_frame, data));
+    assert!(size_of::<CanFrame>() == size_of::<libc::can_frame>());
+    assert!(align_of::<CanFrame>() == align_of::<libc::can_frame>());
+};
+
+impl From<&Frame> for CanFrame {
+    fn from(frame: &Frame) -> Self {
+        let mut can_frame = CanFrame {
+            can_id: frame.raw_id(),
+            can_dlc: frame.data().len() as u8,
+            ..CanFrame::default()
+        };
+        can_frame.data[..frame.data().len()].copy_from_slice(frame.data());
+        can_frame
+    }
+}
+
+impl CanFrame {
+    pub(crate) fn to_frame(self) -> io::Result<Frame> {
+        let len = (self.can_dlc as usize).min(CAN_MAX_DLEN);
+        Frame::new(self.can_id, &self.data[..len])
+    }
+}
+
+/// `repr(C)` struct of integers that is valid for any bit pattern, read and written as bytes.
+///
+/// # Safety
+///
+/// Implementors must not contain pointers, references, enums or `bool`s.
+pub(crate) unsafe trait Plain {}
+
+// SAFETY: `repr(C)` struct of integers and a byte array
+unsafe impl Plain for CanFrame {}
+
+/// `struct sockaddr_can`, the address union is unused by raw and BCM sockets
+#[repr(C)]
+struct SockaddrCan {
+    can_family: libc::sa_family_t,
+    can_ifindex: c_int,
+    can_addr: [u32; 4],
+}
+
+// Only the family and the interface index are used, the address union is zeroed
+const _: () = {
+    use std::mem::offset_of;
+
+    assert!(offset_of!(SockaddrCan, can_family) == offset_of!(libc::sockaddr_can, can_family));
+    assert!(offset_of!(SockaddrCan, can_ifindex) == offset_of!(libc::sockaddr_can, can_ifindex));
+    assert!(size_of::<SockaddrCan>() <= size_of::<libc::sockaddr_can>());
+};
+
+/// Owned SocketCAN file descriptor, closed on drop.
+#[derive(Debug)]
+pub(crate) struct Socket {
+    pub(crate) fd: RawFd,
+}
+
+impl Socket {
+    /// Open a socket of `protocol` and bind (raw) or connect (BCM) it to `ifname`.
+    pub(crate) fn open(ifname: &str, protocol: c_int) -> io::Result<Socket> {
+        let name = CString::new(ifname)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name"))?;
+        // SAFETY: `name` is a NUL terminated string that outlives the call
+        let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if ifindex == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let kind = if protocol == CAN_BCM {
+            libc::SOCK_DGRAM
+        } else {
+            libc::SOCK_RAW
+        };
+        // SAFETY: no pointers are passed, the returned descriptor is owned by `Socket` below
+        let fd = unsafe { libc::socket(PF_CAN, kind, protocol) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // Closes the descriptor if binding fails
+        let socket = Socket { fd };
+
+        let addr = SockaddrCan {
+            can_family: AF_CAN,
+            can_ifindex: ifindex as c_int,
+            can_addr: [0; 4],
+        };
+        let addr_ptr = &addr as *const SockaddrCan as *const libc::sockaddr;
+        let addr_len = size_of::<SockaddrCan>() as libc::socklen_t;
+        // SAFETY: `fd` is open and owned by `socket`, `addr_ptr` points to `addr` which lives
+        // until the end of the function and is `addr_len` bytes long
+        let rv = unsafe {
+            if protocol == CAN_BCM {
+                libc::connect(fd, addr_ptr, addr_len)
+            } else {
+                libc::bind(fd, addr_ptr, addr_len)
+            }
+        };
+        if rv == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        // SAFETY: `self.fd` is open until `self` is dropped, F_GETFL takes no argument
+        let flags = unsafe { libc::fcntl(self.fd, libc::F_GETFL) };
+        if flags == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+        // SAFETY: `self.fd` is open until `self` is dropped, F_SETFL takes an int argument
+        if unsafe { libc::fcntl(self.fd, libc::F_SETFL, flags) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        // SAFETY: `Socket` owns `fd`, it is closed exactly once and never used afterwards
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// Write `value` in a single call, `len` may be less than the size of `T`.
+pub(crate) fn write<T: Plain>(fd: RawFd, value: &T, len: usize) -> io::Result<()> {
+    assert!(
+        len <= size_of::<T>(),
+        "write of {} bytes exceeds the value",
+        len
+    );
+    // SAFETY: `value` is a valid reference of at least `len` bytes (checked above) and is only
+    // read for the duration of the call, an invalid `fd` fails with EBADF
+    let written = unsafe { libc::write(fd, value as *const T as *const c_void, len) };
+    if written < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if written as usize != len {
+        return Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            format!("wrote {} of {} bytes", written, len),
+        ));
+    }
+    Ok(())
+}
+
+/// Read into `value`, returns the number of bytes read.
+pub(crate) fn read<T: Plain>(fd: RawFd, value: &mut T) -> io::Result<usize> {
+    // SAFETY: `value` is an exclusive reference of `size_of::<T>()` bytes and `T: Plain` is
+    // valid for any bytes the kernel writes, an invalid `fd` fails with EBADF
+    let read = unsafe { libc::read(fd, value as *mut T as *mut c_void, size_of::<T>()) };
+    if read < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(read as usize)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Connected datagram sockets, preserving the boundaries of written values like CAN sockets
+    pub(crate) fn socket_pair() -> (Socket, Socket) {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` has room for the two descriptors, which are owned by the returned sockets
+        let rv =
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_SEQPACKET, 0, fds.as_mut_ptr()) };
+        assert_eq!(rv, 0, "{}", io::Error::last_os_error());
+        (Socket { fd: fds[0] }, Socket { fd: fds[1] })
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let (a, b) = socket_pair();
+        for frame in &[
+            Frame::new(0x123, &[]).unwrap(),
+            Frame::new(0x7FF, &[1, 2, 3]).unwrap(),
+            Frame::new(0x18FE_F100, &[0xFF; CAN_MAX_DLEN]).unwrap(),
+        ] {
+            write(a.fd, &CanFrame::from(frame), size_of::<CanFrame>()).unwrap();
+            let mut can_frame = CanFrame::default();
+            assert_eq!(read(b.fd, &mut can_frame).unwrap(), size_of::<CanFrame>());
+            assert_eq!(can_frame.to_frame().unwrap(), *frame);
+        }
+    }
+
+    #[test]
+    fn partial_write() {
+        let (a, b) = socket_pair();
+        let frame = Frame::new(0x123, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        write(a.fd, &CanFrame::from(&frame), 12).unwrap();
+        let mut can_frame = CanFrame::default();
+        assert_eq!(read(b.fd, &mut can_frame).unwrap(), 12);
+        assert_eq!(
+            can_frame.to_frame().unwrap().data(),
+            &[1, 2, 3, 4, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the value")]
+    fn write_past_value() {
+        let (a, _b) = socket_pair();
+        write(a.fd, &CanFrame::default(), size_of::<CanFrame>() + 1).unwrap();
+    }
+
+    #[test]
+    fn closed_socket() {
+        let (a, b) = socket_pair();
+        drop(b);
+        let error = write(a.fd, &CanFrame::default(), size_of::<CanFrame>()).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::EPIPE));
+    }
+
+    #[test]
+    fn unknown_interface() {
+        let error = Socket::open("dbcc-missing0", CAN_RAW).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(libc::ENODEV));
+    }
+}
@@ -0,0 +1,155 @@
+//! Sockets for the tokio runtime (`tokio` feature).
+use crate::bcm::BcmSocket;
+use crate::frame::Frame;
+use crate::raw;
+use crate::transport::{CanSource, CanTransport};
+
+use futures_core::ready;
+use futures_core::stream::Stream;
+use tokio::io::unix::AsyncFd;
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Stream of frames received by a BCM socket.
+#[derive(Debug)]
+pub struct BcmStream {
+    socket: AsyncFd<BcmSocket>,
+}
+
+impl BcmStream {
+    /// Receive frames with `can_id` (DBC message id), see `BcmSocket::rx_setup`.
+    pub fn filter_id(
+        ifname: &str,
+        can_id: u32,
+        timeout: Duration,
+        throttle: Duration,
+    ) -> io::Result<BcmStream> {
+        let socket = BcmSocket::open(ifname)?;
+        socket.rx_setup(can_id, timeout, throttle)?;
+        BcmStream::new(socket)
+    }
+
+    /// Stream the frames of a socket with RX jobs set up.
+    pub fn new(socket: BcmSocket) -> io::Result<BcmStream> {
+        socket.set_nonblocking(true)?;
+        // SAFETY: the socket owns its open descriptor, `as_raw_fd` always returns it and it is
+        // only closed when the socket is dropped together with the `AsyncFd`
+        let socket = unsafe { AsyncFd::register(socket)? };
+        Ok(BcmStream { socket })
+    }
+
+    pub fn get_ref(&self) -> &BcmSocket {
+        self.socket.get_ref()
+    }
+}
+
+impl Stream for BcmStream {
+    type Item = io::Result<Frame>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_recv(cx)
+    }
+}
+
+impl CanSource for BcmStream {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        loop {
+            let mut guard = ready!(self.socket.poll_read_ready(cx))?;
+            if let Ok(result) = guard.try_io(|socket| socket.get_ref().read_frame()) {
+                return Poll::Ready(Some(result));
+            }
+        }
+    }
+}
+
+/// Sends single frames, received frames are still limited to the ids set up.
+impl CanTransport for BcmStream {
+    fn poll_send(&mut self, cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.socket.poll_write_ready(cx))?;
+            if let Ok(result) = guard.try_io(|socket| socket.get_ref().send_frame(frame)) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+}
+
+/// Raw CAN socket.
+#[derive(Debug)]
+pub struct CanSocket {
+    socket: AsyncFd<raw::CanSocket>,
+}
+
+impl CanSocket {
+    /// Open a raw socket on a CAN interface e.g. `vcan0`.
+    pub fn open(ifname: &str) -> io::Result<CanSocket> {
+        let socket = raw::CanSocket::open(ifname)?;
+        socket.set_nonblocking(true)?;
+        // SAFETY: the socket owns its open descriptor, `as_raw_fd` always returns it and it is
+        // only closed when the socket is dropped together with the `AsyncFd`
+        let socket = unsafe { AsyncFd::register(socket)? };
+        Ok(CanSocket { socket })
+    }
+
+    pub async fn write_frame(&self, frame: &Frame) -> io::Result<()> {
+        loop {
+            let mut guard = self.socket.writable().await?;
+            if let Ok(result) = guard.try_io(|socket| socket.get_ref().write_frame(frame)) {
+                return result;
+            }
+        }
+    }
+
+    pub async fn read_frame(&self) -> io::Result<Frame> {
+        loop {
+            let mut guard = self.socket.readable().await?;
+            if let Ok(result) = guard.try_io(|socket| socket.get_ref().read_frame()) {
+                return result;
+            }
+        }
+    }
+}
+
+impl CanSource for CanSocket {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        loop {
+            let mut guard = ready!(self.socket.poll_read_ready(cx))?;
+            if let Ok(result) = guard.try_io(|socket| socket.get_ref().read_frame()) {
+                return Poll::Ready(Some(result));
+            }
+        }
+    }
+}
+
+impl CanTransport for CanSocket {
+    fn poll_send(&mut self, cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.socket.poll_write_ready(cx))?;
+            if let Ok(result) = guard.try_io(|socket| socket.get_ref().write_frame(frame)) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn send_receive() {
+        let ifname = match crate::vcan_interface() {
+            Some(ifname) => ifname,
+            None => return,
+        };
+        let sender = CanSocket::open(&ifname).unwrap();
+        let receiver = CanSocket::open(&ifname).unwrap();
+
+        let frame = Frame::new(0x18FE_F100, &[1, 2, 3, 4, 5]).unwrap();
+        sender.write_frame(&frame).await.unwrap();
+        assert_eq!(receiver.read_frame().await.unwrap(), frame);
+    }
+}
@@ -0,0 +1,127 @@
+//! Transport abstraction generated `stream_from` functions and protocol helpers are written against.
+//!
+//! Implement `CanSource` and `CanTransport` for an adapter (e.g. slcan or PCAN) to use it
+//! with generated code, or use the implementations for BCM and raw SocketCAN sockets
+//! (`tokio` and `async-std` features), the in-memory `mem::channel` and `vbus::VirtualBus`.
+use crate::frame::Frame;
+
+use futures_core::stream::Stream;
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Receives CAN frames.
+pub trait CanSource {
+    /// Poll for the next frame, `None` once the source is closed.
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>>;
+}
+
+/// Receives and sends CAN frames.
+pub trait CanTransport: CanSource {
+    /// Poll until `frame` is sent.
+    fn poll_send(&mut self, cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>>;
+}
+
+impl<S: CanSource + ?Sized> CanSource for &mut S {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        (**self).poll_recv(cx)
+    }
+}
+
+impl<S: CanSource + ?Sized> CanSource for Box<S> {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        (**self).poll_recv(cx)
+    }
+}
+
+impl<T: CanTransport + ?Sized> CanTransport for &mut T {
+    fn poll_send(&mut self, cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        (**self).poll_send(cx, frame)
+    }
+}
+
+impl<T: CanTransport + ?Sized> CanTransport for Box<T> {
+    fn poll_send(&mut self, cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        (**self).poll_send(cx, frame)
+    }
+}
+
+/// `async` receive and send functions of sources and transports.
+pub trait CanTransportExt {
+    /// Receive the next frame, `None` once the source is closed.
+    fn recv(&mut self) -> RecvFrame<'_, Self>
+    where
+        Self: CanSource,
+    {
+        RecvFrame { source: self }
+    }
+
+    fn send(&mut self, frame: Frame) -> SendFrame<'_, Self>
+    where
+        Self: CanTransport,
+    {
+        SendFrame {
+            transport: self,
+            frame,
+        }
+    }
+}
+
+impl<S: CanSource + ?Sized> CanTransportExt for S {}
+
+/// Future of `CanTransportExt::recv`
+#[derive(Debug)]
+pub struct RecvFrame<'a, S: ?Sized> {
+    source: &'a mut S,
+}
+
+impl<S: CanSource + ?Sized> Future for RecvFrame<'_, S> {
+    type Output = Option<io::Result<Frame>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.source.poll_recv(cx)
+    }
+}
+
+/// Future of `CanTransportExt::send`
+#[derive(Debug)]
+pub struct SendFrame<'a, T: ?Sized> {
+    transport: &'a mut T,
+    frame: Frame,
+}
+
+impl<T: CanTransport + ?Sized> Future for SendFrame<'_, T> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let frame = self.frame;
+        self.transport.poll_send(cx, &frame)
+    }
+}
+
+/// Stream of the frames received by a source.
+#[derive(Debug)]
+pub struct Frames<S> {
+    source: S,
+}
+
+/// Stream the frames received by `source`.
+pub fn frames<S: CanSource>(source: S) -> Frames<S> {
+    Frames { source }
+}
+
+impl<S> Frames<S> {
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: CanSource + Unpin> Stream for Frames<S> {
+    type Item = io::Result<Frame>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.source.poll_recv(cx)
+    }
+}
@@ -0,0 +1,469 @@
+//! In-process virtual CAN bus, e.g. for integration tests without `vcan` or root rights.
+//!
+//! Like a SocketCAN interface with its default settings, a frame sent by an endpoint is
+//! received by all other endpoints whose filters match, but not by the sender itself.
+//! Closed endpoints, e.g. after `VirtualBus::close`, end their streams like a closed socket.
+use crate::frame::{raw_id, Frame, CAN_EFF_FLAG, CAN_EFF_MASK};
+use crate::transport::{CanSource, CanTransport};
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// Receive filter, a frame matches if `frame.raw_id() & mask == id & mask`
+/// (`struct can_filter` of SocketCAN).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CanFilter {
+    pub id: u32,
+    pub mask: u32,
+}
+
+impl CanFilter {
+    pub fn new(id: u32, mask: u32) -> Self {
+        CanFilter { id, mask }
+    }
+
+    /// Only frames with exactly this DBC message id, see `Frame::new`.
+    pub fn exact(id: u32) -> Self {
+        CanFilter {
+            id: raw_id(id),
+            mask: CAN_EFF_FLAG | CAN_EFF_MASK,
+        }
+    }
+
+    pub fn matches(&self, frame: &Frame) -> bool {
+        frame.raw_id() & self.mask == self.id & self.mask
+    }
+}
+
+#[derive(Debug)]
+struct Queue {
+    frames: VecDeque<(Frame, Option<Duration>)>,
+    waker: Option<Waker>,
+    /// `None` receives all frames
+    filters: Option<Vec<CanFilter>>,
+    recv_own: bool,
+    /// Disconnected from the bus, frames already received can still be read
+    closed: bool,
+}
+
+impl Queue {
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bus {
+    /// Reference of the timestamps, `None` if frames are not timestamped
+    start: Option<Instant>,
+    endpoints: Vec<(usize, Arc<Mutex<Queue>>)>,
+    next_endpoint: usize,
+    closed: bool,
+}
+
+/// Handle of a virtual bus, clones refer to the same bus.
+///
+/// Example:
+/// ```
+/// use dbcc_transport::vbus::{CanFilter, VirtualBus};
+/// use dbcc_transport::Frame;
+///
+/// let bus = VirtualBus::new();
+/// let ecu = bus.endpoint();
+/// let tester = bus.endpoint();
+/// let logger = bus.endpoint();
+/// tester.set_filters(&[CanFilter::exact(0x7E8)]);
+///
+/// ecu.send_now(&Frame::new(0x7E8, &[0x02, 0x41, 0x0D]).unwrap()).unwrap();
+/// ecu.send_now(&Frame::new(0x123, &[0x00]).unwrap()).unwrap();
+/// assert_eq!(tester.try_recv().unwrap().id(), 0x7E8);
+/// assert!(tester.try_recv().is_none());
+/// assert_eq!(logger.try_recv().unwrap().id(), 0x7E8);
+/// assert_eq!(logger.try_recv().unwrap().id(), 0x123);
+/// assert!(ecu.try_recv().is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct VirtualBus {
+    bus: Arc<Mutex<Bus>>,
+}
+
+impl Default for VirtualBus {
+    fn default() -> Self {
+        VirtualBus::new()
+    }
+}
+
+impl VirtualBus {
+    pub fn new() -> Self {
+        VirtualBus::with_start(None)
+    }
+
+    /// Bus stamping every frame with the time since the bus was created,
+    /// see `Endpoint::recv_timestamped`.
+    pub fn with_timestamps() -> Self {
+        VirtualBus::with_start(Some(Instant::now()))
+    }
+
+    fn with_start(start: Option<Instant>) -> Self {
+        VirtualBus {
+            bus: Arc::new(Mutex::new(Bus {
+                start,
+                endpoints: Vec::new(),
+                next_endpoint: 0,
+                closed: false,
+            })),
+        }
+    }
+
+    /// Connect a new endpoint receiving all frames sent from now on,
+    /// the endpoint is closed if the bus is.
+    pub fn endpoint(&self) -> Endpoint {
+        let mut bus = lock(&self.bus);
+        let queue = Arc::new(Mutex::new(Queue {
+            frames: VecDeque::new(),
+            waker: None,
+            filters: None,
+            recv_own: false,
+            closed: bus.closed,
+        }));
+        let id = bus.next_endpoint;
+        bus.next_endpoint += 1;
+        if !bus.closed {
+            bus.endpoints.push((id, queue.clone()));
+        }
+        Endpoint {
+            id,
+            queue,
+            bus: self.bus.clone(),
+        }
+    }
+
+    /// Close all endpoints, e.g. to end the streams of a test.
+    pub fn close(&self) {
+        let mut bus = lock(&self.bus);
+        bus.closed = true;
+        for (_, queue) in bus.endpoints.drain(..) {
+            lock(&queue).close();
+        }
+    }
+}
+
+/// Endpoint of a virtual bus, disconnected when dropped.
+/// Sending never blocks, received frames are queued until they are read.
+#[derive(Debug)]
+pub struct Endpoint {
+    id: usize,
+    queue: Arc<Mutex<Queue>>,
+    bus: Arc<Mutex<Bus>>,
+}
+
+impl Endpoint {
+    /// Only receive frames matching any of `filters`, none if empty.
+    /// Frames already received are kept.
+    pub fn set_filters(&self, filters: &[CanFilter]) {
+        lock(&self.queue).filters = Some(filters.to_vec());
+    }
+
+    /// Receive all frames again.
+    pub fn clear_filters(&self) {
+        lock(&self.queue).filters = None;
+    }
+
+    /// Also receive the frames sent by this endpoint, off by default.
+    pub fn set_recv_own(&self, recv_own: bool) {
+        lock(&self.queue).recv_own = recv_own;
+    }
+
+    /// Disconnect from the bus, frames already received can still be read.
+    pub fn close(&self) {
+        let id = self.id;
+        lock(&self.bus)
+            .endpoints
+            .retain(|(endpoint, _)| *endpoint != id);
+        lock(&self.queue).close();
+    }
+
+    /// Send a frame to all other endpoints, fails with `NotConnected` once this endpoint is closed.
+    pub fn send_now(&self, frame: &Frame) -> io::Result<()> {
+        let bus = lock(&self.bus);
+        if lock(&self.queue).closed {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "the endpoint was closed",
+            ));
+        }
+        let timestamp = bus.start.map(|start| start.elapsed());
+        for (id, queue) in &bus.endpoints {
+            let mut queue = lock(queue);
+            if *id == self.id && !queue.recv_own {
+                continue;
+            }
+            let accepted = match &queue.filters {
+                Some(filters) => filters.iter().any(|filter| filter.matches(frame)),
+                None => true,
+            };
+            if accepted {
+                queue.frames.push_back((*frame, timestamp));
+                if let Some(waker) = queue.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Take a received frame without waiting.
+    pub fn try_recv(&self) -> Option<Frame> {
+        lock(&self.queue).frames.pop_front().map(|(frame, _)| frame)
+    }
+
+    /// Poll for the next frame and the time since the bus was created it was sent at,
+    /// `None` once the endpoint is closed and all received frames are read.
+    /// The time is `None` unless the bus was created `with_timestamps`.
+    pub fn poll_recv_timestamped(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<(Frame, Option<Duration>)>> {
+        let mut queue = lock(&self.queue);
+        match queue.frames.pop_front() {
+            Some(received) => Poll::Ready(Some(received)),
+            None if queue.closed => Poll::Ready(None),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Receive the next frame with its timestamp, see `poll_recv_timestamped`.
+    pub fn recv_timestamped(&self) -> RecvTimestamped<'_> {
+        RecvTimestamped { endpoint: self }
+    }
+}
+
+impl CanSource for Endpoint {
+    /// `None` once the endpoint is closed and all received frames are read.
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Frame>>> {
+        self.poll_recv_timestamped(cx)
+            .map(|received| received.map(|(frame, _)| Ok(frame)))
+    }
+}
+
+impl CanTransport for Endpoint {
+    fn poll_send(&mut self, _cx: &mut Context<'_>, frame: &Frame) -> Poll<io::Result<()>> {
+        Poll::Ready(self.send_now(frame))
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Future of `Endpoint::recv_timestamped`
+#[derive(Debug)]
+pub struct RecvTimestamped<'a> {
+    endpoint: &'a Endpoint,
+}
+
+impl Future for RecvTimestamped<'_> {
+    type Output = Option<(Frame, Option<Duration>)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.endpoint.poll_recv_timestamped(cx)
+    }
+}
+
+/// A panic while holding the lock leaves the queues consistent, ignore poisoning.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::j1939::{Tp, TpTransport};
+    use crate::transport::CanTransportExt;
+
+    use futures_executor::block_on;
+
+    use std::thread;
+
+    fn frame(id: u32) -> Frame {
+        Frame::new(id, &[id as u8]).unwrap()
+    }
+
+    fn ids(endpoint: &Endpoint) -> Vec<u32> {
+        std::iter::from_fn(|| endpoint.try_recv())
+            .map(|frame| frame.id())
+            .collect()
+    }
+
+    #[test]
+    fn broadcast_to_other_endpoints() {
+        let bus = VirtualBus::new();
+        let endpoints: Vec<Endpoint> = (0..3).map(|_| bus.endpoint()).collect();
+        endpoints[0].send_now(&frame(0x100)).unwrap();
+        endpoints[1].send_now(&frame(0x200)).unwrap();
+
+        assert_eq!(ids(&endpoints[0]), vec![0x200]);
+        assert_eq!(ids(&endpoints[1]), vec![0x100]);
+        assert_eq!(ids(&endpoints[2]), vec![0x100, 0x200]);
+
+        // Endpoints only receive frames sent after they connected
+        let late = bus.endpoint();
+        endpoints[2].send_now(&frame(0x300)).unwrap();
+        assert_eq!(ids(&late), vec![0x300]);
+        assert_eq!(ids(&endpoints[0]), vec![0x300]);
+    }
+
+    #[test]
+    fn filters() {
+        let standard = CanFilter::exact(0x123);
+        assert!(standard.matches(&Frame::new(0x123, &[]).unwrap()));
+        assert!(!standard.matches(&Frame::new(0x124, &[]).unwrap()));
+        // Same id in the extended format
+        assert!(!standard.matches(&Frame::new(0x8000_0123, &[]).unwrap()));
+
+        let extended = CanFilter::exact(0x9800_FE00);
+        assert!(extended.matches(&Frame::new(0x9800_FE00, &[]).unwrap()));
+        assert!(!extended.matches(&Frame::new(0x0800_FE00, &[]).unwrap()));
+
+        // Any standard id 0x7E0 to 0x7EF
+        let range = CanFilter::new(0x7E0, CAN_EFF_FLAG | 0x7F0);
+        assert!(range.matches(&Frame::new(0x7E8, &[]).unwrap()));
+        assert!(!range.matches(&Frame::new(0x7F0, &[]).unwrap()));
+
+        let bus = VirtualBus::new();
+        let sender = bus.endpoint();
+        let receiver = bus.endpoint();
+        receiver.set_filters(&[CanFilter::exact(0x100), range]);
+        for id in &[0x100, 0x101, 0x7E0, 0x7EF, 0x8000_0100] {
+            sender.send_now(&frame(*id)).unwrap();
+        }
+        assert_eq!(ids(&receiver), vec![0x100, 0x7E0, 0x7EF]);
+
+        receiver.set_filters(&[]);
+        sender.send_now(&frame(0x100)).unwrap();
+        assert!(receiver.try_recv().is_none());
+
+        receiver.clear_filters();
+        sender.send_now(&frame(0x101)).unwrap();
+        assert_eq!(ids(&receiver), vec![0x101]);
+    }
+
+    #[test]
+    fn monotonic_timestamps() {
+        let bus = VirtualBus::with_timestamps();
+        let sender = bus.endpoint();
+        let receiver = bus.endpoint();
+        for id in 0..10 {
+            sender.send_now(&frame(id)).unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let mut last = Duration::from_secs(0);
+        for id in 0..10 {
+            let (frame, timestamp) = block_on(receiver.recv_timestamped()).unwrap();
+            let timestamp = timestamp.unwrap();
+            assert_eq!(frame.id(), id);
+            assert!(timestamp > last, "{:?} <= {:?}", timestamp, last);
+            last = timestamp;
+        }
+
+        let bus = VirtualBus::new();
+        let sender = bus.endpoint();
+        let receiver = bus.endpoint();
+        sender.send_now(&frame(0x100)).unwrap();
+        assert_eq!(block_on(receiver.recv_timestamped()).unwrap().1, None);
+    }
+
+    #[test]
+    fn recv_own() {
+        let bus = VirtualBus::new();
+        let sender = bus.endpoint();
+        let receiver = bus.endpoint();
+
+        sender.send_now(&frame(0x100)).unwrap();
+        assert!(sender.try_recv().is_none());
+
+        sender.set_recv_own(true);
+        sender.send_now(&frame(0x200)).unwrap();
+        assert_eq!(ids(&sender), vec![0x200]);
+
+        // Own frames pass the filters too
+        sender.set_filters(&[CanFilter::exact(0x300)]);
+        sender.send_now(&frame(0x200)).unwrap();
+        sender.send_now(&frame(0x300)).unwrap();
+        assert_eq!(ids(&sender), vec![0x300]);
+
+        sender.set_recv_own(false);
+        sender.send_now(&frame(0x300)).unwrap();
+        assert!(sender.try_recv().is_none());
+
+        assert_eq!(ids(&receiver), vec![0x100, 0x200, 0x200, 0x300, 0x300]);
+    }
+
+    #[test]
+    fn close_endpoint() {
+        let bus = VirtualBus::new();
+        let mut endpoint = bus.endpoint();
+        let other = bus.endpoint();
+        other.send_now(&frame(0x100)).unwrap();
+
+        endpoint.close();
+        other.send_now(&frame(0x200)).unwrap();
+        // Frames received before closing are still read
+        assert_eq!(block_on(endpoint.recv()).unwrap().unwrap().id(), 0x100);
+        assert!(block_on(endpoint.recv()).is_none());
+        assert_eq!(
+            block_on(endpoint.send(frame(0x300))).unwrap_err().kind(),
+            io::ErrorKind::NotConnected
+        );
+        assert!(other.try_recv().is_none());
+
+        let mut tp = TpTransport::new(bus.endpoint(), Tp::new(0x00));
+        tp.get_ref().close();
+        assert_eq!(
+            block_on(tp.recv()).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn drop_endpoint() {
+        let bus = VirtualBus::new();
+        let sender = bus.endpoint();
+        let receiver = bus.endpoint();
+        drop(receiver);
+        sender.send_now(&frame(0x100)).unwrap();
+        assert_eq!(lock(&bus.bus).endpoints.len(), 1);
+    }
+
+    #[test]
+    fn close_bus() {
+        let bus = VirtualBus::new();
+        let mut tp = TpTransport::new(bus.endpoint(), Tp::new(0x00));
+        let receiver = thread::spawn(move || block_on(tp.recv()));
+        // The receiver is woken up and fails like on a closed socket
+        thread::sleep(Duration::from_millis(10));
+        bus.close();
+        assert_eq!(
+            receiver.join().unwrap().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        let mut late = bus.endpoint();
+        assert!(block_on(late.recv()).is_none());
+        assert!(late.send_now(&frame(0x100)).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+//! Send the DRC message every `GenMsgCycleTime` (1000 ms) via the kernel broadcast manager.
+//! The code in `gen` is generated with `--with-tokio --with-socketcan --with-cyclic`.
+//!
+//! Watch the frames via
+//! ```
+//! candump -t d vcan0
+//! ```
+
+mod gen;
+
+/// Generated module
+use crate::gen::j1939;
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use dbcc_transport::bcm::BcmSocket;
+
+fn main() -> io::Result<()> {
+    let socket = BcmSocket::open("vcan0")?;
+
+    let drc = j1939::Drc::new(vec![0x00; 8]);
+    drc.start_cyclic(&socket)?;
+    thread::sleep(Duration::from_secs(5));
+
+    // The kernel keeps the interval and sends the new payload from now on
+    let drc = j1939::Drc::new(vec![0xFF; 8]);
+    drc.update_cyclic(&socket)?;
+    thread::sleep(Duration::from_secs(5));
+
+    drc.stop_cyclic(&socket)
+}
@@ -0,0 +1,29 @@
+//! Send the OEL message via a raw SocketCAN socket, blocking and asynchronously.
+//! The code in `gen` is generated with `--with-tokio --with-socketcan`.
+//!
+//! Watch the frames via
+//! ```
+//! candump vcan0
+//! ```
+
+mod gen;
+
+/// Generated module
+use crate::gen::j1939;
+
+use socketcan::CANSocket;
+use std::io;
+
+#[async_std::main]
+async fn main() -> io::Result<()> {
+    // Hazard lamps flashing
+    let oel = j1939::Oel::new(vec![0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    let socket = CANSocket::open("vcan0").map_err(io::Error::other)?;
+    oel.send(&socket)?;
+
+    let socket = tokio_socketcan::CANSocket::open("vcan0").map_err(io::Error::other)?;
+    oel.send_async(&socket).await?;
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Same as the `demo` example but the code is generated at compile time.
+
+mod j1939 {
+    dbcc::include_dbc!("examples/j1939.dbc");
+}
+
+fn main() {
+    // J1939 - Operators External Light Controls Message Id
+    let can_message_id = 2365443326u32;
+    // can frame data field (0-8 bytes)
+    let can_frame_data: Vec<u8> = vec![0x00, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+    // CAN Message ID constant from generated code
+    if can_message_id == j1939::MESSAGE_ID_OEL {
+        // J1939 - Operators External Light Controls Message
+        let oel = j1939::Oel::new(can_frame_data);
+
+        match oel.hazard_light_switch() {
+            j1939::HazardLightSwitch2365443326::HazardLampsToBeFlashing => {
+                println!("Hazard Lamps To Be Flashing")
+            }
+            j1939::HazardLightSwitch2365443326::HazardLampsToBeOff => {
+                println!("Hazard Lamps To Be Off")
+            }
+            j1939::HazardLightSwitch2365443326::NotAvailable => println!("Not available"),
+            j1939::HazardLightSwitch2365443326::Error => println!("Error"),
+            j1939::HazardLightSwitch2365443326::XValue(_) => unreachable!(),
+        }
+    }
+}
@@ -1,72 +1,644 @@
-use blake2::{Blake2b, Digest};
-use dbcc::{can_code_gen, DbccOpt};
-use generic_array::GenericArray;
-use nom;
-use nom::verbose_errors;
-use pretty_env_logger;
+use dbcc::candump::{self, CandumpFrame};
+use dbcc::parse;
+use dbcc::runtime::{DecodedMessage, Decoder, Value};
+use dbcc::{
+    can_code_gen, can_code_gen_files, cargo_features, check, dbc_hash, Config, DbccOpt, Diagnostic,
+    Runtime, Severity, SplitBy,
+};
+use serde::Serialize;
+use structopt::clap::{Error, ErrorKind};
 use structopt::StructOpt;
-use typenum::U64;
 
-use std::cmp;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::io::{self, BufReader};
 use std::path::{Path, PathBuf};
-use std::{fs, io};
+
+/// Extended frame format flag of DBC message ids
+const EFF_FLAG: u32 = 0x8000_0000;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "dbcc", about = "DBC to rust code compiler")]
 pub struct Opt {
     /// File input
     #[structopt(short = "i", long = "input", parse(from_os_str), help = "DBC file")]
-    pub input: PathBuf,
+    pub input: Option<PathBuf>,
 
     /// Should tokio SocketCan BCM streams be generated.
     /// This requires the `tokio-socketcan-bcm` crate.
-    #[structopt(long = "with-tokio", help = "Generate Tokio streams.")]
-    pub with_tokio: bool,
+    /// Flags like this one override the configuration file, `=false` turns them off.
+    #[structopt(
+        long = "with-tokio",
+        require_equals = true,
+        value_name = "bool",
+        help = "Generate Tokio streams."
+    )]
+    pub with_tokio: Option<Option<bool>>,
+
+    /// Runtime SocketCan BCM streams are generated for: `compat` (same as `--with-tokio`),
+    /// `tokio` or `async-std`. The latter two require `dbcc-transport` with the feature
+    /// of the same name.
+    #[structopt(
+        long = "runtime",
+        help = "Generate streams for a runtime: compat, tokio or async-std."
+    )]
+    pub runtime: Option<Runtime>,
+
+    /// Should `send` functions for raw SocketCAN sockets be generated.
+    /// This requires the `socketcan` crate, with a runtime also `send_async`.
+    #[structopt(
+        long = "with-socketcan",
+        require_equals = true,
+        value_name = "bool",
+        help = "Generate SocketCAN send functions."
+    )]
+    pub with_socketcan: Option<Option<bool>>,
+
+    /// Should functions for cyclic transmission via the kernel broadcast manager be generated.
+    /// This requires the `tokio-socketcan-bcm` and `dbcc-transport` crates.
+    #[structopt(
+        long = "with-cyclic",
+        require_equals = true,
+        value_name = "bool",
+        help = "Generate BCM cyclic transmission functions."
+    )]
+    pub with_cyclic: Option<Option<bool>>,
+
+    /// Should `try_from_frame` and `into_frame` conversions for `embedded_can::Frame`s be generated.
+    /// This requires the `embedded-can` crate.
+    #[structopt(
+        long = "with-embedded-can",
+        require_equals = true,
+        value_name = "bool",
+        help = "Generate embedded-can try_from_frame and into_frame conversions."
+    )]
+    pub with_embedded_can: Option<Option<bool>>,
+
+    /// Project configuration e.g. `dbcc.toml`
+    #[structopt(
+        short = "c",
+        long = "config",
+        parse(from_os_str),
+        help = "Configuration file"
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Write a module directory with a `mod.rs` and one file per message or node
+    #[structopt(long = "out-dir", parse(from_os_str), help = "Output module directory")]
+    pub out_dir: Option<PathBuf>,
+
+    /// How messages are split over the files of the output directory
+    #[structopt(
+        long = "split-by",
+        possible_values = &["message", "node"],
+        help = "Split the output directory by `message` or `node`"
+    )]
+    pub split_by: Option<SplitBy>,
+
+    /// Gate each message behind a `msg_<message name>` cargo feature
+    #[structopt(
+        long = "message-features",
+        require_equals = true,
+        value_name = "bool",
+        help = "Gate each message behind a `msg_<name>` cargo feature"
+    )]
+    pub message_features: Option<Option<bool>>,
+
+    /// Write the `[features]` table declaring the message features for `Cargo.toml`
+    #[structopt(
+        long = "features-file",
+        parse(from_os_str),
+        help = "Write the cargo `[features]` table to this file"
+    )]
+    pub features_file: Option<PathBuf>,
+
+    /// Fail if any warnings were emitted
+    #[structopt(
+        long = "deny-warnings",
+        global = true,
+        help = "Treat warnings as errors"
+    )]
+    pub deny_warnings: bool,
+
+    /// Diagnostics output format, `human` or `json` (one object per line on stderr)
+    #[structopt(
+        long = "message-format",
+        global = true,
+        default_value = "human",
+        possible_values = &["human", "json"],
+        help = "Diagnostics output format"
+    )]
+    pub message_format: String,
+
+    /// Use everything parsed before the DBC parser stopped instead of failing
+    #[structopt(
+        long = "allow-partial",
+        global = true,
+        help = "Continue with the partially parsed DBC if parsing stops early"
+    )]
+    pub allow_partial: bool,
+
+    #[structopt(subcommand)]
+    pub cmd: Option<Command>,
 }
 
-fn dbc_file_hash(dbc_path: &Path) -> io::Result<GenericArray<u8, U64>> {
-    let mut file = fs::File::open(&dbc_path)?;
-    let mut hasher = Blake2b::new();
-    let _n = io::copy(&mut file, &mut hasher)?;
-    Ok(hasher.result())
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Check a DBC for semantic errors e.g. overlapping signals.
+    /// Exits with a non-zero code if errors were found.
+    #[structopt(name = "check")]
+    Check {
+        #[structopt(long = "dbc", parse(from_os_str), help = "DBC file")]
+        dbc: PathBuf,
+    },
+    /// Decode a `candump` log, e.g. `candump -L vcan0 | dbcc decode --dbc j1939.dbc`.
+    /// Frames of messages that are not defined in the DBC are skipped.
+    #[structopt(name = "decode")]
+    Decode {
+        #[structopt(long = "dbc", parse(from_os_str), help = "DBC file")]
+        dbc: PathBuf,
+
+        /// candump log file, `-` or no file reads stdin
+        #[structopt(parse(from_os_str), help = "candump log file or - for stdin")]
+        log: Option<PathBuf>,
+
+        /// Output one JSON object per frame instead of text
+        #[structopt(
+            long = "format",
+            default_value = "text",
+            possible_values = &["text", "json"],
+            help = "Output format"
+        )]
+        format: String,
+
+        /// Only decode messages with these names
+        #[structopt(long = "message", number_of_values = 1, help = "Message name filter")]
+        messages: Vec<String>,
+
+        /// Only decode messages with these ids, decimal or hex with `0x` prefix
+        #[structopt(
+            long = "id",
+            number_of_values = 1,
+            parse(try_from_str = parse_id),
+            help = "Message id filter"
+        )]
+        ids: Vec<u32>,
+
+        /// Only decode messages transmitted by these nodes
+        #[structopt(long = "node", number_of_values = 1, help = "Transmitter node filter")]
+        nodes: Vec<String>,
+    },
+    /// Encode signal values into a payload, e.g.
+    /// `dbcc encode --dbc j1939.dbc OEL HazardLightSwitch=HazardLampsToBeFlashing`.
+    /// Signals that are not given are zero.
+    #[structopt(name = "encode")]
+    Encode {
+        #[structopt(long = "dbc", parse(from_os_str), help = "DBC file")]
+        dbc: PathBuf,
+
+        /// Print a `cansend <interface> <id>#<data>` command
+        #[structopt(long = "cansend", help = "Print as cansend command for this interface")]
+        cansend: Option<String>,
+
+        /// Message name or id
+        #[structopt(help = "Message name or id")]
+        message: String,
+
+        /// Signal values as `<signal>=<value>`, the value is a number, `true`, `false`
+        /// or a value description
+        #[structopt(help = "Signal values <signal>=<value>")]
+        signals: Vec<String>,
+    },
 }
 
 fn main() -> io::Result<()> {
     pretty_env_logger::init();
     let opt = Opt::from_args();
 
-    let file_name = opt
-        .input
-        .as_path()
-        .file_name()
-        .and_then(|f| f.to_str())
-        .unwrap_or_else(|| "N/A");
-    let file_hash = dbc_file_hash(opt.input.as_path())?;
-    let file_hash = format!("Blake2b: {:X}", file_hash);
-    let mut f = File::open(opt.input.clone()).expect("Failed to open input file");
-    let mut buffer = Vec::new();
-    f.read_to_end(&mut buffer).expect("Failed to read file");
-    match can_dbc::DBC::from_slice(&buffer) {
-        Ok(dbc_content) => {
-            let opt = DbccOpt { with_tokio: opt.with_tokio };
-            let code = can_code_gen(&opt, &dbc_content, file_name, &file_hash).expect("Failed to generate rust code");
-            println!("{}", code.to_string());
+    match opt.cmd {
+        Some(Command::Check { ref dbc }) => run_check(&opt, dbc),
+        Some(Command::Decode { .. }) => run_decode(&opt),
+        Some(Command::Encode { .. }) => run_encode(&opt),
+        None => match opt.input {
+            Some(ref input) => generate(&opt, input),
+            None => Error::with_description(
+                "The following required arguments were not provided:\n    --input <input>",
+                ErrorKind::MissingRequiredArgument,
+            )
+            .exit(),
         },
+    }
+}
+
+fn generate(opt: &Opt, input: &Path) -> io::Result<()> {
+    let config = match opt.config {
+        Some(ref config_path) => match Config::from_file(config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+
+    let file_name = input.file_name().and_then(|f| f.to_str()).unwrap_or("N/A");
+    let buffer = read_dbc(input);
+    let file_hash = dbc_hash(&buffer);
+    let dbc_content = parse_dbc(opt, input, &buffer)?;
+
+    let dbcc_opt = DbccOpt {
+        with_tokio: flag(opt.with_tokio, config.opt.with_tokio),
+        runtime: opt.runtime.or(config.opt.runtime),
+        with_socketcan: flag(opt.with_socketcan, config.opt.with_socketcan),
+        with_cyclic: flag(opt.with_cyclic, config.opt.with_cyclic),
+        with_embedded_can: flag(opt.with_embedded_can, config.opt.with_embedded_can),
+        message_features: flag(opt.message_features, config.opt.message_features),
+        ..config.opt
+    };
+    let output = config.output;
+    let features_file = opt.features_file.as_ref().or(output.features_file.as_ref());
+    let out_dir = opt.out_dir.as_ref().or(output.dir.as_ref());
+    // Nothing is written before the diagnostics passed `--deny-warnings`
+    if let Some(out_dir) = out_dir {
+        let split_by = opt.split_by.unwrap_or(output.split_by);
+        let generated = unwrap_codegen(can_code_gen_files(
+            &dbcc_opt,
+            &dbc_content,
+            file_name,
+            &file_hash,
+            split_by,
+        ));
+        deny_warnings(opt, &generated.diagnostics)?;
+        write_features(features_file, &dbcc_opt, &dbc_content)?;
+        return generated.write_to(out_dir);
+    }
+
+    let generated = unwrap_codegen(can_code_gen(&dbcc_opt, &dbc_content, file_name, &file_hash));
+    deny_warnings(opt, &generated.diagnostics)?;
+    write_features(features_file, &dbcc_opt, &dbc_content)?;
+    match output.file {
+        Some(ref output_file) => fs::write(output_file, generated.scope.to_string())?,
+        None => println!("{}", generated.scope.to_string()),
+    }
+    Ok(())
+}
+
+/// Flag given on the command line, `--flag` alone enables it, otherwise the configuration applies.
+fn flag(cli: Option<Option<bool>>, config: bool) -> bool {
+    cli.map_or(config, |value| value.unwrap_or(true))
+}
+
+fn write_features(
+    features_file: Option<&PathBuf>,
+    opt: &DbccOpt,
+    dbc: &can_dbc::DBC,
+) -> io::Result<()> {
+    match features_file {
+        Some(features_file) => fs::write(features_file, cargo_features(opt, dbc)),
+        None => Ok(()),
+    }
+}
+
+fn unwrap_codegen<T>(result: Result<T, dbcc::CodegenError>) -> T {
+    match result {
+        Ok(generated) => generated,
         Err(e) => {
-            match e {
-                can_dbc::Error::NomError(nom::Err::Incomplete(needed)) => eprintln!("Error incomplete input, needed: {:?}", needed),
-                can_dbc::Error::NomError(nom::Err::Error(ctx)) => {
-                    match ctx {
-                        verbose_errors::Context::Code(i, kind) => eprintln!("Error Kind: {:?}, Code: {:?}", kind, String::from_utf8(i.to_vec())),
-                        verbose_errors::Context::List(l)=> eprintln!("Error List: {:?}", l),
+            eprintln!("Failed to generate rust code: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Report code generation diagnostics and exit if `--deny-warnings` is set.
+fn deny_warnings(opt: &Opt, diagnostics: &[Diagnostic]) -> io::Result<()> {
+    report(opt, diagnostics)?;
+    if opt.deny_warnings && !diagnostics.is_empty() {
+        eprintln!(
+            "Code generation emitted {} warning(s), denied by --deny-warnings",
+            diagnostics.len()
+        );
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn run_check(opt: &Opt, dbc_path: &Path) -> io::Result<()> {
+    let buffer = read_dbc(dbc_path);
+    let dbc_content = parse_dbc(opt, dbc_path, &buffer)?;
+
+    let diagnostics = check::check(&dbc_content);
+    report(opt, &diagnostics)?;
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    if opt.message_format == "human" {
+        eprintln!("{} error(s), {} warning(s)", errors, warnings);
+    }
+    if errors > 0 || (opt.deny_warnings && warnings > 0) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// CAN id as decimal or hex with `0x` prefix. The extended frame flag of DBC ids is ignored.
+fn parse_id(id: &str) -> Result<u32, std::num::ParseIntError> {
+    let id = match id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16)?,
+        None => id.parse()?,
+    };
+    Ok(id & socketcan::EFF_MASK)
+}
+
+/// Decoded frame as printed by `dbcc decode --format json`.
+#[derive(Serialize)]
+struct DecodedFrame<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<f64>,
+    interface: &'a str,
+    #[serde(flatten)]
+    message: DecodedMessage,
+}
+
+fn run_decode(opt: &Opt) -> io::Result<()> {
+    let (dbc_path, log, format, messages, ids, nodes) = match opt.cmd {
+        Some(Command::Decode {
+            ref dbc,
+            ref log,
+            ref format,
+            ref messages,
+            ref ids,
+            ref nodes,
+        }) => (dbc, log, format, messages, ids, nodes),
+        _ => unreachable!(),
+    };
+    let buffer = read_dbc(dbc_path);
+    let dbc_content = parse_dbc(opt, dbc_path, &buffer)?;
+    let decoder = Decoder::new(&dbc_content);
+    report(opt, decoder.diagnostics())?;
+
+    let input: Box<dyn BufRead> = match log {
+        Some(path) if path.as_os_str() != "-" => Box::new(BufReader::new(File::open(path)?)),
+        _ => Box::new(BufReader::new(io::stdin())),
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for (line_number, line) in input.lines().enumerate() {
+        let line = line?;
+        let frame: CandumpFrame = match candump::parse_line(&line) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("warning: line {}: {}", line_number + 1, e);
+                continue;
+            }
+        };
+        if frame.remote || frame.error {
+            continue;
+        }
+        let id = if frame.extended {
+            frame.id | EFF_FLAG
+        } else {
+            frame.id
+        };
+        let message = match decoder.message(id) {
+            Some(message) => message,
+            None => continue,
+        };
+        let selected = (messages.is_empty() || messages.contains(&message.name))
+            && (ids.is_empty() || ids.contains(&(message.id & socketcan::EFF_MASK)))
+            && (nodes.is_empty()
+                || message
+                    .transmitter
+                    .as_ref()
+                    .is_some_and(|node| nodes.contains(node)));
+        if !selected {
+            continue;
+        }
+        let decoded = match decoder.decode(id, &frame.data) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                eprintln!("warning: line {}: {}", line_number + 1, e);
+                continue;
+            }
+        };
+
+        if format == "json" {
+            let decoded_frame = DecodedFrame {
+                timestamp: frame.timestamp,
+                interface: &frame.interface,
+                message: decoded,
+            };
+            writeln!(out, "{}", serde_json::to_string(&decoded_frame)?)?;
+        } else {
+            if let Some(timestamp) = frame.timestamp {
+                write!(out, "({:.6}) ", timestamp)?;
+            }
+            let width = if frame.extended { 8 } else { 3 };
+            writeln!(
+                out,
+                "{} {:0width$X} {}",
+                frame.interface,
+                frame.id,
+                decoded,
+                width = width
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn run_encode(opt: &Opt) -> io::Result<()> {
+    let (dbc_path, cansend, message_name, signals) = match opt.cmd {
+        Some(Command::Encode {
+            ref dbc,
+            ref cansend,
+            ref message,
+            ref signals,
+        }) => (dbc, cansend, message, signals),
+        _ => unreachable!(),
+    };
+    let buffer = read_dbc(dbc_path);
+    let dbc_content = parse_dbc(opt, dbc_path, &buffer)?;
+    let decoder = Decoder::new(&dbc_content);
+    report(opt, decoder.diagnostics())?;
+
+    let message = decoder
+        .message_by_name(message_name)
+        .or_else(|| {
+            parse_id(message_name)
+                .ok()
+                .and_then(|id| decoder.message(id))
+        })
+        .unwrap_or_else(|| exit_error(format!("unknown message `{}`", message_name)));
+
+    let mut values = Vec::with_capacity(signals.len());
+    for signal_value in signals {
+        let (name, value) = match signal_value.find('=') {
+            Some(separator) => (
+                &signal_value[..separator],
+                signal_value[separator + 1..].trim(),
+            ),
+            None => exit_error(format!(
+                "expected `<signal>=<value>` but got `{}`",
+                signal_value
+            )),
+        };
+        let signal = message.signal(name).unwrap_or_else(|| {
+            exit_error(format!(
+                "message `{}` has no signal `{}`",
+                message.name, name
+            ))
+        });
+        let value = parse_value(value)
+            .or_else(|| signal.description_value(value))
+            .unwrap_or_else(|| {
+                let descriptions: Vec<&str> =
+                    signal.descriptions.values().map(|d| d.as_str()).collect();
+                exit_error(format!(
+                    "invalid value `{}` for signal `{}`, expected a number{}",
+                    value,
+                    name,
+                    if descriptions.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" or one of {}", descriptions.join(", "))
                     }
-                }
-                can_dbc::Error::NomError(nom::Err::Failure(ctx)) => eprintln!("Failure {:?}", ctx),
-                can_dbc::Error::Incomplete(dbc, remaining) => eprintln!("Not all data in buffer was read {:#?}, remaining unparsed (length: {}): {}\n...(truncated)", dbc, remaining.len(), String::from_utf8_lossy(&remaining[0..cmp::min(100, remaining.len())]).to_string())
+                ))
+            });
+        if !signal.in_range(value) {
+            exit_error(format!(
+                "value {} of signal `{}` is outside of its range [{}|{}]",
+                value, name, signal.min, signal.max
+            ));
+        }
+        values.push((name, value));
+    }
+
+    let payload = decoder
+        .encode(message.id, &values)
+        .unwrap_or_else(|e| exit_error(e.to_string()));
+    let extended = message.id & EFF_FLAG != 0;
+    let id = if extended {
+        format!("{:08X}", message.id & socketcan::EFF_MASK)
+    } else {
+        format!("{:03X}", message.id)
+    };
+    let data: Vec<String> = payload.iter().map(|byte| format!("{:02X}", byte)).collect();
+    match cansend {
+        Some(interface) => println!("cansend {} {}#{}", interface, id, data.concat()),
+        None => println!("{} [{}] {}", id, payload.len(), data.join(" ")),
+    }
+    Ok(())
+}
+
+/// Signal value given on the command line, value descriptions are resolved by the caller.
+fn parse_value(value: &str) -> Option<Value> {
+    match value {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => match value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+        {
+            // `from_str_radix` would accept a sign
+            Some(hex) if !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                u64::from_str_radix(hex, 16).ok().map(Value::Unsigned)
             }
+            Some(_) => None,
+            None => value
+                .parse()
+                .map(Value::Unsigned)
+                .or_else(|_| value.parse().map(Value::Signed))
+                .or_else(|_| value.parse().map(Value::Float))
+                .ok(),
+        },
+    }
+}
+
+fn exit_error(message: String) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
+}
+
+/// Print diagnostics to stderr in the selected `--message-format`.
+fn report(opt: &Opt, diagnostics: &[Diagnostic]) -> io::Result<()> {
+    for diagnostic in diagnostics {
+        if opt.message_format == "json" {
+            eprintln!("{}", serde_json::to_string(diagnostic)?);
+        } else {
+            eprintln!("{}", diagnostic);
         }
     }
     Ok(())
 }
+
+fn read_dbc(path: &Path) -> Vec<u8> {
+    let mut f = File::open(path).expect("Failed to open input file");
+    let mut buffer = Vec::new();
+    f.read_to_end(&mut buffer).expect("Failed to read file");
+    buffer
+}
+
+/// Parse the DBC, print the parser error and exit if that fails.
+fn parse_dbc(opt: &Opt, path: &Path, buffer: &[u8]) -> io::Result<can_dbc::DBC> {
+    let parsed = if opt.allow_partial {
+        parse::from_slice_partial(buffer)
+    } else {
+        parse::from_slice(buffer).map(|dbc_content| (dbc_content, None))
+    };
+    match parsed {
+        Ok((dbc_content, incomplete)) => {
+            if let Some(incomplete) = incomplete {
+                report(opt, &[incomplete])?;
+                if opt.deny_warnings {
+                    eprintln!("Incomplete DBC, denied by --deny-warnings");
+                    std::process::exit(1);
+                }
+            }
+            Ok(dbc_content)
+        }
+        Err(error) => {
+            let mut error = error.with_file(path);
+            if error.incomplete {
+                error.notes.push(
+                    "use `--allow-partial` to continue with the sections parsed before".to_string(),
+                );
+            }
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_value_hex() {
+        assert_eq!(parse_value("0x1F"), Some(Value::Unsigned(0x1F)));
+        assert_eq!(parse_value("0X1f"), Some(Value::Unsigned(0x1F)));
+        assert_eq!(
+            parse_value("0xFFFFFFFFFFFFFFFF"),
+            Some(Value::Unsigned(u64::MAX))
+        );
+        assert_eq!(parse_value("0x"), None);
+        assert_eq!(parse_value("0X"), None);
+        assert_eq!(parse_value("0x+1"), None);
+        assert_eq!(parse_value("0xG"), None);
+    }
+
+    #[test]
+    fn parse_value_numbers() {
+        assert_eq!(parse_value("true"), Some(Value::Bool(true)));
+        assert_eq!(parse_value("42"), Some(Value::Unsigned(42)));
+        assert_eq!(parse_value("-42"), Some(Value::Signed(-42)));
+        assert_eq!(parse_value("-0.5"), Some(Value::Float(-0.5)));
+        assert_eq!(parse_value("HazardLampsToBeFlashing"), None);
+    }
+}
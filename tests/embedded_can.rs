@@ -0,0 +1,143 @@
+//! Messages convert from and into any `embedded_can::Frame` implementation.
+
+mod embedded {
+    dbcc::include_dbc!("tests/dbc/embedded_can.dbc", with_embedded_can);
+}
+
+use embedded::{Ccvs1, ExtendedLow, Standard};
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+/// Minimal frame as implemented by a CAN driver
+#[derive(Debug, PartialEq)]
+struct TestFrame {
+    id: Id,
+    remote: bool,
+    dlc: usize,
+    data: Vec<u8>,
+}
+
+impl Frame for TestFrame {
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+        Some(TestFrame {
+            id: id.into(),
+            remote: false,
+            dlc: data.len(),
+            data: data.to_vec(),
+        })
+    }
+
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 {
+            return None;
+        }
+        Some(TestFrame {
+            id: id.into(),
+            remote: true,
+            dlc,
+            data: Vec::new(),
+        })
+    }
+
+    fn is_extended(&self) -> bool {
+        matches!(self.id, Id::Extended(_))
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.remote
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        self.dlc
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+fn standard_id(id: u16) -> Id {
+    Id::Standard(StandardId::new(id).unwrap())
+}
+
+fn extended_id(id: u32) -> Id {
+    Id::Extended(ExtendedId::new(id).unwrap())
+}
+
+#[test]
+fn standard_round_trip() {
+    let mut standard = Standard::new(Vec::new());
+    standard.set_value_raw_value(0xBEEF);
+
+    let frame: TestFrame = standard.into_frame().unwrap();
+    assert_eq!(frame.id, standard_id(0x123));
+    assert_eq!(frame.data, vec![0xEF, 0xBE, 0, 0]);
+
+    let decoded = Standard::try_from_frame(&frame).unwrap();
+    assert_eq!(decoded.value_raw_value(), 0xBEEF);
+}
+
+#[test]
+fn extended_round_trip() {
+    let mut extended = ExtendedLow::new(Vec::new());
+    extended.set_value_raw_value(42);
+
+    // The id fits a standard id but the message is sent in the extended format
+    let frame: TestFrame = extended.into_frame().unwrap();
+    assert_eq!(frame.id, extended_id(0x200));
+    assert_eq!(
+        ExtendedLow::try_from_frame(&frame)
+            .unwrap()
+            .value_raw_value(),
+        42
+    );
+
+    let standard = TestFrame::new(StandardId::new(0x200).unwrap(), &[42; 8]).unwrap();
+    assert_eq!(
+        ExtendedLow::try_from_frame(&standard).unwrap_err(),
+        standard_id(0x200)
+    );
+}
+
+#[test]
+fn j1939_round_trip() {
+    let mut ccvs1 = Ccvs1::new(Vec::new())
+        .with_priority(3)
+        .with_source_address(0x2A);
+    ccvs1.set_speed_raw_value(88.5);
+
+    let frame: TestFrame = ccvs1.into_frame().unwrap();
+    assert_eq!(frame.id, extended_id(0x0CFE_F12A));
+
+    let decoded = Ccvs1::try_from_frame(&frame).unwrap();
+    assert_eq!(decoded.priority(), 3);
+    assert_eq!(decoded.source_address(), 0x2A);
+    assert_eq!(decoded.speed_raw_value(), 88.5);
+}
+
+#[test]
+fn reject_other_frames() {
+    let remote = TestFrame::new_remote(StandardId::new(0x123).unwrap(), 4).unwrap();
+    assert_eq!(
+        Standard::try_from_frame(&remote).unwrap_err(),
+        standard_id(0x123)
+    );
+
+    let other = TestFrame::new(StandardId::new(0x124).unwrap(), &[0; 4]).unwrap();
+    assert_eq!(
+        Standard::try_from_frame(&other).unwrap_err(),
+        standard_id(0x124)
+    );
+
+    let other_pgn = TestFrame::new(ExtendedId::new(0x18FE_F200).unwrap(), &[0; 8]).unwrap();
+    assert_eq!(
+        Ccvs1::try_from_frame(&other_pgn).unwrap_err(),
+        extended_id(0x18FE_F200)
+    );
+}
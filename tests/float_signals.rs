@@ -0,0 +1,121 @@
+//! IEEE float and double signals (`SIG_VALTYPE_`) decode and encode bit-exactly.
+
+mod float {
+    dbcc::include_dbc!("tests/dbc/float.dbc");
+}
+
+use float::{DoubleBe, DoubleLe, DoubleLong, FloatBe, FloatLe};
+
+const SINGLES: &[f32] = &[
+    0.0,
+    -0.0,
+    1.5,
+    -123.456,
+    f32::MIN_POSITIVE,
+    f32::MAX,
+    f32::INFINITY,
+    f32::NEG_INFINITY,
+    1e-45,
+];
+
+const DOUBLES: &[f64] = &[
+    0.0,
+    -0.0,
+    1.5,
+    -123.456_789,
+    f64::MIN_POSITIVE,
+    f64::MAX,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    5e-324,
+];
+
+/// NaN with a payload, only preserved if the bits are copied unchanged
+const NAN_32: u32 = 0x7FC0_1234;
+const NAN_64: u64 = 0x7FF8_0000_0012_3456;
+
+#[test]
+fn float_le() {
+    for &value in SINGLES.iter().chain(&[f32::from_bits(NAN_32)]) {
+        let mut payload = vec![0; 8];
+        payload[..4].copy_from_slice(&value.to_le_bytes());
+        let message = FloatLe::new(payload.clone());
+        assert_eq!(message.single_raw_value().to_bits(), value.to_bits());
+
+        let mut message = FloatLe::new(vec![0xAA; 8]);
+        message.set_single_raw_value(value);
+        assert_eq!(&message.frame_payload()[..4], &value.to_le_bytes());
+        assert_eq!(&message.frame_payload()[4..], &[0xAA; 4]);
+        assert_eq!(message.single_raw_value().to_bits(), value.to_bits());
+    }
+}
+
+#[test]
+fn float_be() {
+    for &value in SINGLES.iter().chain(&[f32::from_bits(NAN_32)]) {
+        let mut payload = vec![0; 8];
+        payload[..4].copy_from_slice(&value.to_be_bytes());
+        let message = FloatBe::new(payload);
+        assert_eq!(message.single_raw_value().to_bits(), value.to_bits());
+
+        let mut message = FloatBe::new(vec![0xAA; 8]);
+        message.set_single_raw_value(value);
+        assert_eq!(&message.frame_payload()[..4], &value.to_be_bytes());
+        assert_eq!(&message.frame_payload()[4..], &[0xAA; 4]);
+        assert_eq!(message.single_raw_value().to_bits(), value.to_bits());
+    }
+}
+
+#[test]
+fn double_le() {
+    for &value in DOUBLES.iter().chain(&[f64::from_bits(NAN_64)]) {
+        let message = DoubleLe::new(value.to_le_bytes().to_vec());
+        assert_eq!(message.double_raw_value().to_bits(), value.to_bits());
+
+        let mut message = DoubleLe::new(vec![0xAA; 8]);
+        message.set_double_raw_value(value);
+        assert_eq!(message.frame_payload(), &value.to_le_bytes());
+        assert_eq!(message.double_raw_value().to_bits(), value.to_bits());
+    }
+}
+
+#[test]
+fn double_be() {
+    for &value in DOUBLES.iter().chain(&[f64::from_bits(NAN_64)]) {
+        let message = DoubleBe::new(value.to_be_bytes().to_vec());
+        assert_eq!(message.double_raw_value().to_bits(), value.to_bits());
+
+        let mut message = DoubleBe::new(vec![0xAA; 8]);
+        message.set_double_raw_value(value);
+        assert_eq!(message.frame_payload(), &value.to_be_bytes());
+        assert_eq!(message.double_raw_value().to_bits(), value.to_bits());
+    }
+}
+
+#[test]
+fn double_after_first_window() {
+    let value = -123.456_789_f64;
+    let mut message = DoubleLong::new(vec![0xAA; 12]);
+    message.set_tail_raw_value(value);
+    assert_eq!(&message.frame_payload()[..4], &[0xAA; 4]);
+    assert_eq!(&message.frame_payload()[4..], &value.to_le_bytes());
+    assert_eq!(message.tail_raw_value().to_bits(), value.to_bits());
+    assert_eq!(message.head_raw_value(), 0xAAAA_AAAA);
+}
+
+#[test]
+fn scaled_float() {
+    // Factor 0.5 and offset -40 are applied on top of the IEEE value
+    let mut payload = vec![0; 8];
+    payload[4..].copy_from_slice(&100.0_f32.to_le_bytes());
+    assert_eq!(FloatLe::new(payload).scaled_raw_value(), 10.0);
+
+    let mut message = FloatLe::new(vec![0; 8]);
+    message.set_scaled_raw_value(10.0);
+    assert_eq!(&message.frame_payload()[4..], &100.0_f32.to_le_bytes());
+
+    let mut message = FloatBe::new(vec![0; 8]);
+    message.set_scaled_raw_value(10.0);
+    assert_eq!(&message.frame_payload()[4..], &100.0_f32.to_be_bytes());
+    assert_eq!(message.scaled_raw_value(), 10.0);
+}
@@ -0,0 +1,109 @@
+use byteorder::{ByteOrder, BE, LE};
+
+// Generated based on
+// File Name: signal_types.dbc
+// DBC Version: 
+// Blake2b: 63FACDEB49C0FE2E3590B690C226BFA720A41DE3D3540684CFB9710B32857DE709ACD6C212FF4AF6F3478015A98985C4DC657E9FAD5F5397AA19996CC8545F29
+
+#[allow(dead_code)]
+pub const MESSAGE_ID_OVERRIDES: u32 = 300;
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct Overrides {
+    frame_payload: Vec<u8>,
+}
+
+impl Overrides {
+    #[allow(dead_code)]
+    pub fn new(mut frame_payload: Vec<u8>) -> Overrides {
+        frame_payload.resize(8, 0);
+        Overrides { frame_payload }
+    }
+
+    /// Payload of the message length (DLC), e.g. after setting signals
+    #[allow(dead_code)]
+    pub fn frame_payload(&self) -> &[u8] {
+        &self.frame_payload[..8]
+    }
+
+    /// Read Scaled signal from can frame
+    #[allow(dead_code)]
+    pub fn scaled_raw_value(&self) -> f32 {
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        ((frame_payload & 0xFFFF) as f64 * 0.5 + -40f64) as f32
+    }
+
+    /// Write Scaled signal into the can frame
+    #[allow(dead_code)]
+    pub fn set_scaled_raw_value(&mut self, value: f32) {
+        let value = value as f64;
+        let raw: u64 = ((value + 40f64) / 0.5).round() as u64;
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        LE::write_u64(&mut self.frame_payload, (frame_payload & 0xFFFFFFFFFFFF0000) | (raw & 0xFFFF));
+    }
+
+    /// Read Small signal from can frame
+    #[allow(dead_code)]
+    pub fn small_raw_value(&self) -> u32 {
+        let frame_payload: u64 = BE::read_u64(&self.frame_payload);
+        (((frame_payload >> 40) & 0xFF) as u8) as u32
+    }
+
+    /// Write Small signal into the can frame
+    #[allow(dead_code)]
+    pub fn set_small_raw_value(&mut self, value: u32) {
+        let value = value as u8;
+        let raw: u64 = u64::from(value);
+        let frame_payload: u64 = BE::read_u64(&self.frame_payload);
+        BE::write_u64(&mut self.frame_payload, (frame_payload & 0xFFFF00FFFFFFFFFF) | ((raw & 0xFF) << 40));
+    }
+
+    /// Read Flag signal from can frame
+    #[allow(dead_code)]
+    pub fn flag_raw_value(&self) -> u8 {
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        ((frame_payload >> 24) & 0x1) as u8
+    }
+
+    /// Write Flag signal into the can frame
+    #[allow(dead_code)]
+    pub fn set_flag_raw_value(&mut self, value: u8) {
+        let value = value != 0;
+        let raw: u64 = u64::from(value);
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        LE::write_u64(&mut self.frame_payload, (frame_payload & 0xFFFFFFFFFEFFFFFF) | ((raw & 0x1) << 24));
+    }
+
+    /// Read Level signal from can frame
+    #[allow(dead_code)]
+    pub fn level_raw_value(&self) -> bool {
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        (((frame_payload >> 25) & 0xF) as u8) != 0
+    }
+
+    /// Write Level signal into the can frame
+    #[allow(dead_code)]
+    pub fn set_level_raw_value(&mut self, value: bool) {
+        let value = u8::from(value);
+        let raw: u64 = u64::from(value);
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        LE::write_u64(&mut self.frame_payload, (frame_payload & 0xFFFFFFFFE1FFFFFF) | ((raw & 0xF) << 25));
+    }
+
+    /// Read Single signal from can frame
+    #[allow(dead_code)]
+    pub fn single_raw_value(&self) -> f64 {
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        (f32::from_bits(((frame_payload >> 32) & 0xFFFFFFFF) as u32)) as f64
+    }
+
+    /// Write Single signal into the can frame
+    #[allow(dead_code)]
+    pub fn set_single_raw_value(&mut self, value: f64) {
+        let value = value as f32;
+        let raw: u64 = u64::from(value.to_bits());
+        let frame_payload: u64 = LE::read_u64(&self.frame_payload);
+        LE::write_u64(&mut self.frame_payload, (frame_payload & 0xFFFFFFFF) | ((raw & 0xFFFFFFFF) << 32));
+    }
+}
\ No newline at end of file
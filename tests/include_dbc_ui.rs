@@ -0,0 +1,7 @@
+//! `include_dbc!` reports invalid input as compile errors at the offending token.
+
+#[test]
+fn include_dbc_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/include_dbc_*.rs");
+}
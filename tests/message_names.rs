@@ -0,0 +1,29 @@
+//! Message names that shadow paths used by the generated code.
+
+mod names {
+    dbcc::include_dbc!("tests/dbc/names.dbc", with_tokio);
+}
+
+use names::{Message, Std};
+
+#[test]
+fn std_message() {
+    let std = Std::new(vec![42, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(std.io_raw_value(), 42);
+    match Message::decode(names::MESSAGE_ID_STD, std.frame_payload()) {
+        Message::Std(decoded) => assert_eq!(decoded.io_raw_value(), 42),
+        _ => panic!("expected Std"),
+    }
+}
+
+#[test]
+fn messages_message() {
+    match Message::decode(names::MESSAGE_ID_MESSAGES, &[7; 8]) {
+        Message::Messages(messages) => assert_eq!(messages.value_raw_value(), 7),
+        _ => panic!("expected Messages"),
+    }
+    assert!(matches!(
+        Message::decode(0x7FF, &[]),
+        Message::Unknown { id: 0x7FF, .. }
+    ));
+}
@@ -0,0 +1,238 @@
+//! The runtime `Decoder` reads and writes signals exactly like the generated code.
+
+mod parity {
+    dbcc::include_dbc!("tests/dbc/parity.dbc");
+}
+
+use dbcc::runtime::{DecodedMessage, Decoder, Value};
+
+/// Signal value as returned by a generated `<signal>_raw_value` getter
+trait Generated: Sized {
+    /// Type of the setter argument
+    type Raw;
+
+    fn raw(self) -> Option<Self::Raw>;
+
+    fn value(self) -> Option<Value>;
+}
+
+macro_rules! generated {
+    ($variant:ident, $($ty:ty),+) => {
+        $(
+            impl Generated for $ty {
+                type Raw = $ty;
+
+                fn raw(self) -> Option<$ty> {
+                    Some(self)
+                }
+
+                fn value(self) -> Option<Value> {
+                    Some(Value::$variant(self.into()))
+                }
+            }
+        )+
+    };
+}
+
+generated!(Unsigned, u8, u16, u32, u64);
+generated!(Signed, i8, i16, i32, i64);
+generated!(Float, f32, f64);
+generated!(Bool, bool);
+
+/// Multiplexed signals are `None` if the multiplexor does not select them
+impl<T: Generated> Generated for Option<T> {
+    type Raw = T::Raw;
+
+    fn raw(self) -> Option<T::Raw> {
+        self.and_then(Generated::raw)
+    }
+
+    fn value(self) -> Option<Value> {
+        self.and_then(Generated::value)
+    }
+}
+
+fn decoder() -> Decoder {
+    let buffer = std::fs::read("tests/dbc/parity.dbc").unwrap();
+    let decoder = Decoder::new(&can_dbc::DBC::from_slice(&buffer).unwrap());
+    assert!(decoder.diagnostics().is_empty());
+    decoder
+}
+
+/// Fixed bit patterns followed by pseudo random payloads
+fn payloads(len: usize) -> Vec<Vec<u8>> {
+    let mut payloads = vec![
+        vec![0; len],
+        vec![0xFF; len],
+        vec![0xAA; len],
+        vec![0x55; len],
+    ];
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    for _ in 0..500 {
+        let payload = (0..len)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        payloads.push(payload);
+    }
+    payloads
+}
+
+fn is_nan(value: Option<Value>) -> bool {
+    matches!(value, Some(Value::Float(value)) if value.is_nan())
+}
+
+/// Compare the decoded signal with the value of the generated getter
+fn assert_signal(decoded: &DecodedMessage, name: &str, generated: Option<Value>, payload: &[u8]) {
+    let runtime = decoded
+        .signals
+        .iter()
+        .find(|signal| signal.name == name)
+        .map(|signal| signal.value);
+    if is_nan(runtime) && is_nan(generated) {
+        return;
+    }
+    assert_eq!(
+        runtime, generated,
+        "{} signal {}, payload {:02X?}",
+        decoded.name, name, payload
+    );
+}
+
+/// Decode every payload with the generated `$message` and the `Decoder`, then encode the
+/// decoded values with both and compare the payloads.
+macro_rules! assert_parity {
+    ($message:ident, $id:expr, $len:expr, [$(($name:literal, $get:ident, $set:ident)),+ $(,)?]) => {{
+        let decoder = decoder();
+        for payload in payloads($len) {
+            let generated = parity::$message::new(payload.clone());
+            let decoded = decoder.decode($id, &payload).unwrap();
+            $(assert_signal(&decoded, $name, generated.$get().value(), &payload);)+
+
+            let values: Vec<(&str, Value)> = decoded
+                .signals
+                .iter()
+                .map(|signal| (signal.name.as_str(), signal.value))
+                .collect();
+            // NaN payloads are not preserved when widened to the f64 of `Value`
+            if values.iter().any(|(_, value)| is_nan(Some(*value))) {
+                continue;
+            }
+            let mut encoded = parity::$message::new(vec![0; $len]);
+            $(
+                if let Some(value) = generated.$get().raw() {
+                    encoded.$set(value);
+                }
+            )+
+            let runtime = decoder.encode($id, &values).unwrap();
+            assert_eq!(
+                encoded.frame_payload(),
+                &runtime[..],
+                "{} payload {:02X?}",
+                stringify!($message),
+                payload
+            );
+        }
+    }};
+}
+
+#[test]
+fn multiplexed() {
+    assert_parity!(
+        Multiplexed,
+        300,
+        8,
+        [
+            ("Mux", mux_raw_value, set_mux_raw_value),
+            ("Always", always_raw_value, set_always_raw_value),
+            ("Speed", speed_raw_value, set_speed_raw_value),
+            ("Level", level_raw_value, set_level_raw_value),
+            ("Counter", counter_raw_value, set_counter_raw_value),
+            ("Flag", flag_raw_value, set_flag_raw_value),
+        ]
+    );
+}
+
+#[test]
+fn signed() {
+    assert_parity!(
+        Signed,
+        301,
+        8,
+        [
+            ("S8", s8_raw_value, set_s8_raw_value),
+            ("S16", s16_raw_value, set_s16_raw_value),
+            ("S32", s32_raw_value, set_s32_raw_value),
+            ("S4", s4_raw_value, set_s4_raw_value),
+            (
+                "Temperature",
+                temperature_raw_value,
+                set_temperature_raw_value
+            ),
+        ]
+    );
+}
+
+#[test]
+fn big_endian() {
+    assert_parity!(
+        BigEndian,
+        302,
+        8,
+        [
+            ("U12", u12_raw_value, set_u12_raw_value),
+            ("S16", s16_raw_value, set_s16_raw_value),
+            ("Scaled", scaled_raw_value, set_scaled_raw_value),
+            ("Odd", odd_raw_value, set_odd_raw_value),
+        ]
+    );
+}
+
+#[test]
+fn float() {
+    assert_parity!(
+        Float,
+        303,
+        8,
+        [
+            ("Le", le_raw_value, set_le_raw_value),
+            ("Be", be_raw_value, set_be_raw_value),
+        ]
+    );
+    assert_parity!(
+        Double,
+        304,
+        8,
+        [("Value", value_raw_value, set_value_raw_value)]
+    );
+}
+
+#[test]
+fn bits_64() {
+    assert_parity!(
+        Unsigned64,
+        305,
+        8,
+        [("Value", value_raw_value, set_value_raw_value)]
+    );
+    assert_parity!(
+        Signed64,
+        306,
+        8,
+        [("Value", value_raw_value, set_value_raw_value)]
+    );
+    assert_parity!(
+        Long,
+        307,
+        16,
+        [
+            ("Wide", wide_raw_value, set_wide_raw_value),
+            ("Tail", tail_raw_value, set_tail_raw_value),
+        ]
+    );
+}
@@ -0,0 +1,57 @@
+//! Setters take the same configured type as the getters of overridden signals.
+//!
+//! `include_dbc!` does not read a configuration, `gen/signal_types.rs` is generated via
+//! `dbcc -i tests/dbc/signal_types.dbc --config tests/dbc/signal_types.toml`.
+
+#[rustfmt::skip]
+#[path = "gen/signal_types.rs"]
+mod signal_types;
+
+use dbcc::{can_code_gen, dbc_hash, Config};
+use signal_types::Overrides;
+
+#[test]
+fn generated_up_to_date() {
+    let buffer = std::fs::read("tests/dbc/signal_types.dbc").unwrap();
+    let dbc = can_dbc::DBC::from_slice(&buffer).unwrap();
+    let config = Config::from_file("tests/dbc/signal_types.toml").unwrap();
+    let generated =
+        can_code_gen(&config.opt, &dbc, "signal_types.dbc", &dbc_hash(&buffer)).unwrap();
+    let expected = std::fs::read_to_string("tests/gen/signal_types.rs").unwrap();
+    assert_eq!(generated.scope.to_string(), expected);
+}
+
+#[test]
+fn set_and_get_overridden_signals() {
+    let mut overrides = Overrides::new(vec![0; 8]);
+
+    let scaled: f32 = 12.5;
+    overrides.set_scaled_raw_value(scaled);
+    assert_eq!(overrides.scaled_raw_value(), scaled);
+
+    let small: u32 = 200;
+    overrides.set_small_raw_value(small);
+    assert_eq!(overrides.small_raw_value(), small);
+
+    let flag: u8 = 1;
+    overrides.set_flag_raw_value(flag);
+    assert_eq!(overrides.flag_raw_value(), flag);
+
+    overrides.set_level_raw_value(true);
+    assert!(overrides.level_raw_value());
+
+    let single: f64 = -1.25;
+    overrides.set_single_raw_value(single);
+    assert_eq!(overrides.single_raw_value(), single);
+
+    assert_eq!(
+        overrides.frame_payload(),
+        &[0x69, 0x00, 200, 0x03, 0x00, 0x00, 0xA0, 0xBF]
+    );
+
+    overrides.set_flag_raw_value(0);
+    overrides.set_level_raw_value(false);
+    assert_eq!(overrides.flag_raw_value(), 0);
+    assert!(!overrides.level_raw_value());
+    assert_eq!(overrides.frame_payload()[3], 0);
+}
@@ -0,0 +1,114 @@
+//! Generated `stream_from`, `stream_messages_from` and `send_to` over the in-memory transport.
+
+use dbcc_transport::{mem, CanTransportExt, Frame};
+use futures::executor::block_on;
+use futures::stream::StreamExt;
+
+macro_rules! transport_tests {
+    ($runtime:ident) => {
+        mod $runtime {
+            mod transport {
+                dbcc::include_dbc!("tests/dbc/transport.dbc", $runtime);
+            }
+
+            use super::*;
+            use transport::{Engine, Gear, Message};
+
+            fn engine(speed: f64, temperature: i8) -> Engine {
+                let mut engine = Engine::new(Vec::new());
+                engine.set_speed_raw_value(speed);
+                engine.set_temperature_raw_value(temperature);
+                engine
+            }
+
+            #[test]
+            fn stream_from() {
+                let (mut tx, rx) = mem::channel();
+                block_on(async {
+                    engine(88.5, -20).send_to(&mut tx).await.unwrap();
+                    tx.send(Frame::new(0x7FF, &[0xFF; 8]).unwrap())
+                        .await
+                        .unwrap();
+                    engine(12.3, 40).send_to(&mut tx).await.unwrap();
+                    drop(tx);
+
+                    let engines: Vec<Engine> =
+                        Engine::stream_from(rx).map(Result::unwrap).collect().await;
+                    assert_eq!(engines.len(), 2);
+                    assert_eq!(engines[0].speed_raw_value(), 88.5);
+                    assert_eq!(engines[0].temperature_raw_value(), -20);
+                    assert_eq!(engines[1].speed_raw_value(), 12.3);
+                    assert_eq!(engines[1].temperature_raw_value(), 40);
+                });
+            }
+
+            #[test]
+            fn send_to_frame() {
+                let (mut tx, rx) = mem::channel();
+                let mut gear = Gear::new(Vec::new());
+                gear.set_selected_raw_value(3);
+                block_on(gear.send_to(&mut tx)).unwrap();
+
+                let frame = rx.try_recv().unwrap();
+                assert!(frame.is_extended());
+                assert_eq!(frame.id(), 0x301);
+                assert_eq!(frame.data(), &[3]);
+                assert!(rx.try_recv().is_none());
+            }
+
+            #[test]
+            fn stream_messages_from() {
+                let (mut tx, rx) = mem::channel();
+                let mut gear = Gear::new(Vec::new());
+                gear.set_selected_raw_value(5);
+                block_on(async {
+                    engine(50.0, 0).send_to(&mut tx).await.unwrap();
+                    tx.send(Frame::new(0x7FF, &[0x01, 0x02]).unwrap())
+                        .await
+                        .unwrap();
+                    gear.send_to(&mut tx).await.unwrap();
+                    drop(tx);
+
+                    let mut messages = transport::stream_messages_from(rx, true);
+                    match messages.next().await {
+                        Some(Ok(Message::Engine(engine))) => {
+                            assert_eq!(engine.speed_raw_value(), 50.0)
+                        }
+                        other => panic!("expected Engine, got {:?}", other),
+                    }
+                    match messages.next().await {
+                        Some(Ok(Message::Unknown { id, data })) => {
+                            assert_eq!((id, data), (0x7FF, vec![0x01, 0x02]))
+                        }
+                        other => panic!("expected Unknown, got {:?}", other),
+                    }
+                    match messages.next().await {
+                        Some(Ok(Message::Gear(gear))) => assert_eq!(gear.selected_raw_value(), 5),
+                        other => panic!("expected Gear, got {:?}", other),
+                    }
+                    assert!(messages.next().await.is_none());
+                });
+            }
+
+            #[test]
+            fn stream_messages_from_without_unknown() {
+                let (mut tx, rx) = mem::channel();
+                block_on(async {
+                    tx.send(Frame::new(0x7FF, &[0x01]).unwrap()).await.unwrap();
+                    engine(1.5, 1).send_to(&mut tx).await.unwrap();
+                    drop(tx);
+
+                    let messages: Vec<Message> = transport::stream_messages_from(rx, false)
+                        .map(Result::unwrap)
+                        .collect()
+                        .await;
+                    assert_eq!(messages.len(), 1);
+                    assert!(matches!(messages[0], Message::Engine(_)));
+                });
+            }
+        }
+    };
+}
+
+transport_tests!(tokio);
+transport_tests!(async_std);
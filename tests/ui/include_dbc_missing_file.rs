@@ -0,0 +1,5 @@
+mod missing {
+    dbcc::include_dbc!("tests/dbc/missing.dbc");
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+mod j1939 {
+    dbcc::include_dbc!("examples/j1939.dbc", with_tokio, with_serde);
+}
+
+fn main() {}
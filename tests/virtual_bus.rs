@@ -0,0 +1,50 @@
+//! Generated `send_to` and `stream_from` against `dbcc_transport::vbus::VirtualBus`.
+
+mod transport {
+    dbcc::include_dbc!("tests/dbc/transport.dbc", tokio);
+}
+
+use dbcc_transport::vbus::{CanFilter, VirtualBus};
+use futures::executor::block_on;
+use futures::stream::StreamExt;
+use transport::{Engine, Gear, Message};
+
+#[test]
+fn round_trip() {
+    let bus = VirtualBus::new();
+    let mut ecu = bus.endpoint();
+    let dashboard = bus.endpoint();
+    let gearbox = bus.endpoint();
+    gearbox.set_filters(&[CanFilter::exact(transport::MESSAGE_ID_GEAR)]);
+
+    let mut engine = Engine::new(Vec::new());
+    engine.set_speed_raw_value(88.5);
+    engine.set_temperature_raw_value(-20);
+    let mut gear = Gear::new(Vec::new());
+    gear.set_selected_raw_value(4);
+    block_on(async {
+        engine.send_to(&mut ecu).await.unwrap();
+        gear.send_to(&mut ecu).await.unwrap();
+    });
+    bus.close();
+
+    let engines: Vec<Engine> =
+        block_on(Engine::stream_from(dashboard).map(Result::unwrap).collect());
+    assert_eq!(engines.len(), 1);
+    assert_eq!(engines[0].speed_raw_value(), 88.5);
+    assert_eq!(engines[0].temperature_raw_value(), -20);
+
+    let messages: Vec<Message> = block_on(
+        transport::stream_messages_from(gearbox, true)
+            .map(Result::unwrap)
+            .collect(),
+    );
+    assert_eq!(messages.len(), 1);
+    match &messages[0] {
+        Message::Gear(gear) => assert_eq!(gear.selected_raw_value(), 4),
+        other => panic!("expected Gear, got {:?}", other),
+    }
+
+    // The sender does not receive its own frames
+    assert!(ecu.try_recv().is_none());
+}